@@ -0,0 +1,114 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The directory packed into the initial ramdisk.
+const INITRD_DIR: &str = "initrd";
+/// The directories other file systems are mounted on, which must exist in the initial ramdisk.
+const MOUNT_POINTS: [&str; 3] = ["cdrom", "mnt", "tmp"];
+/// The size of a USTAR block, in bytes.
+const BLOCK_SIZE: usize = 512;
+
+/// Packs the `initrd` directory into a USTAR archive embedded into the kernel.
+///
+/// # Errors
+///
+/// * If the directory can't be read or the archive can't be written.
+fn main() -> io::Result<()> {
+    println!("cargo:rerun-if-changed={INITRD_DIR}");
+
+    let mut archive = Vec::new();
+    for mount_point in MOUNT_POINTS {
+        append_header(&mut archive, &format!("{mount_point}/"), b'5', 0)?;
+    }
+    append_dir(&mut archive, Path::new(INITRD_DIR), "")?;
+
+    // The archive ends with two empty blocks.
+    archive.resize(archive.len() + 2 * BLOCK_SIZE, 0);
+
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR is not set!"));
+    fs::write(out_dir.join("initrd.tar"), archive)
+}
+
+/// Appends a directory and its contents to the archive, in a stable order.
+///
+/// # Arguments
+///
+/// * `archive` - The archive.
+/// * `dir` - The directory on the host.
+/// * `prefix` - The path of the directory inside the archive.
+///
+/// # Errors
+///
+/// * If the directory can't be read.
+/// * If a path is too long for a USTAR header.
+fn append_dir(archive: &mut Vec<u8>, dir: &Path, prefix: &str) -> io::Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(fs::DirEntry::file_name);
+
+    for entry in entries {
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+
+        if entry.file_type()?.is_dir() {
+            if !MOUNT_POINTS.contains(&name.as_str()) {
+                append_header(archive, &format!("{name}/"), b'5', 0)?;
+            }
+            append_dir(archive, &entry.path(), &format!("{name}/"))?;
+        } else {
+            let data = fs::read(entry.path())?;
+            append_header(archive, &name, b'0', data.len())?;
+
+            archive.extend_from_slice(&data);
+            archive.resize(archive.len().next_multiple_of(BLOCK_SIZE), 0);
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends a USTAR header to the archive.
+///
+/// # Arguments
+///
+/// * `archive` - The archive.
+/// * `name` - The path of the entry.
+/// * `kind` - The type flag of the entry.
+/// * `size` - The size of the entry, in bytes.
+///
+/// # Errors
+///
+/// * If the path is longer than 100 bytes.
+fn append_header(archive: &mut Vec<u8>, name: &str, kind: u8, size: usize) -> io::Result<()> {
+    if name.len() > 100 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("'{name}' is too long for the initial ramdisk!"),
+        ));
+    }
+
+    let mode = if kind == b'5' { 0o755 } else { 0o644 };
+
+    let mut header = [0; BLOCK_SIZE];
+    header[0..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(format!("{mode:07o}\0").as_bytes());
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{size:011o}\0").as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[156] = kind;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is calculated with the checksum field filled with spaces.
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|&byte| u32::from(byte)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    archive.extend_from_slice(&header);
+
+    Ok(())
+}
@@ -25,8 +25,8 @@ pub const HEAP_START: usize = 0x4000_0000_0000;
 ///
 /// # Notes
 ///
-/// * This is 100 KiB.
-pub const HEAP_SIZE: usize = 100 * 1024;
+/// * This is 1 MiB, which leaves room for a FAT12/16 table and root directory.
+pub const HEAP_SIZE: usize = 1024 * 1024;
 
 #[global_allocator]
 static ALLOCATOR: Locked<FixedSizeBlockAllocator> = Locked::new(FixedSizeBlockAllocator::new());
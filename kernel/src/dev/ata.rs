@@ -1,22 +1,368 @@
 use alloc::boxed::Box;
-use alloc::{string::String, vec::Vec};
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::{string::String, vec, vec::Vec};
 use bit_field::BitField;
+use core::future::{poll_fn, Future};
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
 use core::{convert::TryInto, hint::spin_loop};
 use lazy_static::lazy_static;
-use spin::Mutex;
+use spin::{Mutex, MutexGuard};
+use x86_64::instructions::interrupts;
 use x86_64::instructions::port::{Port, PortReadOnly, PortWriteOnly};
+use x86_64::PhysAddr;
 
+use crate::dev::block::{self, check_range, BlockDevice};
+use crate::dev::cache::CachedDevice;
+use crate::dev::pci::{self, Bar, DeviceInfo, Match};
 use crate::errors::Error;
+use crate::mem::{alloc_contiguous_frames, phys_to_virt};
 use crate::println;
-use crate::sys::time::clock::uptime;
+use crate::sys::pic;
+use crate::sys::task::timer::timeout;
+use crate::sys::time::clocksource::{read_tsc, tsc_frequency};
 use crate::sys::time::wait;
 
 /// The maximum block size of the ATA bus.
 pub const BLOCK_SIZE: usize = 512;
 
+/// The maximum number of blocks in a single command.
+const MAX_TRANSFER_BLOCKS: usize = 128;
+
+/// The number of blocks addressable by 28-bit LBA commands, which are the only ones used.
+const LBA28_BLOCKS: u32 = 1 << 28;
+
+/// The size of the DMA buffer of a bus, which holds the largest transfer.
+const DMA_BUFFER_SIZE: usize = MAX_TRANSFER_BLOCKS * BLOCK_SIZE;
+
+/// The size of a frame, which is the length of each PRD entry.
+const FRAME_SIZE: usize = 4096;
+
+/// The physical address DMA memory must end below, since PRDs hold 32-bit addresses.
+const DMA_LIMIT: u64 = 1 << 32;
+
+/// How long an interrupt-driven transfer may take before the bus is reset.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The block size of ATAPI drives.
+pub const ATAPI_BLOCK_SIZE: usize = 2048;
+
+/// The maximum number of ATAPI blocks in a single packet command.
+const MAX_ATAPI_TRANSFER_BLOCKS: usize = 32;
+
+/// The SCSI READ CAPACITY (10) operation code.
+const SCSI_READ_CAPACITY: u8 = 0x25;
+
+/// The SCSI READ (10) operation code.
+const SCSI_READ: u8 = 0x28;
+
 lazy_static! {
-    /// The ATA buses.
-    pub static ref BUSES: Mutex<Vec<Bus>> = Mutex::new(Vec::new());
+    /// The ATA buses, locked separately so transfers on one don't stall the other.
+    pub static ref BUSES: [Mutex<Bus>; 2] = [
+        Mutex::new(Bus::new(0, 14, 0x1F0, 0x3F6)),
+        Mutex::new(Bus::new(1, 15, 0x170, 0x376)),
+    ];
+}
+
+/// The transfers in progress on the ATA buses.
+///
+/// # Notes
+///
+/// * These are locked with interrupts disabled, since the IRQ handlers lock them too.
+static CHANNELS: [Mutex<Channel>; 2] = [Mutex::new(Channel::new()), Mutex::new(Channel::new())];
+
+/// The ID of the next task waiting for a bus.
+static NEXT_WAITER: AtomicU64 = AtomicU64::new(0);
+
+/// The ownership of an ATA bus.
+///
+/// # Variants
+///
+/// * `Idle` - Nobody is using the bus.
+/// * `Claimed` - A caller is using the bus.
+/// * `Read` - The bus is waiting for the interrupts of a PIO read, to fill the buffer a block at a time.
+/// * `Write` - The bus is waiting for the interrupts of a PIO write, to send the data a block at a time.
+/// * `Dma` - The bus is waiting for the interrupt of a DMA transfer, to copy the data read into the buffer.
+enum Transfer {
+    Idle,
+    Claimed,
+    Read {
+        buffer: Vec<u8>,
+        offset: usize,
+        completion: Arc<Mutex<Completion>>,
+    },
+    Write {
+        data: Vec<u8>,
+        offset: usize,
+        completion: Arc<Mutex<Completion>>,
+    },
+    Dma {
+        buffer: Vec<u8>,
+        completion: Arc<Mutex<Completion>>,
+    },
+}
+
+impl Transfer {
+    /// Gets the completion of an interrupt-driven transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&Arc<Mutex<Completion>>>` - The completion, if the bus is waiting for a transfer.
+    const fn completion(&self) -> Option<&Arc<Mutex<Completion>>> {
+        match self {
+            Self::Idle | Self::Claimed => None,
+            Self::Read { completion, .. }
+            | Self::Write { completion, .. }
+            | Self::Dma { completion, .. } => Some(completion),
+        }
+    }
+}
+
+/// The state of an ATA bus shared with its IRQ handler.
+///
+/// # Fields
+///
+/// * `transfer` - The ownership of the bus.
+/// * `waiters` - The tasks waiting for the bus to become idle, by waiter ID.
+/// * `finished` - The completion of the last interrupt-driven transfer, freed by the next claim.
+///
+/// # Notes
+///
+/// * The interrupt handler parks the completion here rather than dropping it, so it never frees the buffer, even if the task waiting for the transfer gave up.
+struct Channel {
+    transfer: Transfer,
+    waiters: Vec<(u64, Waker)>,
+    finished: Option<Arc<Mutex<Completion>>>,
+}
+
+impl Channel {
+    /// Creates a new, idle channel.
+    const fn new() -> Self {
+        Self {
+            transfer: Transfer::Idle,
+            waiters: Vec::new(),
+            finished: None,
+        }
+    }
+
+    /// Claims the bus if it's idle.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Option<Arc<Mutex<Completion>>>>` - The parked completion of the last transfer if the bus was claimed, to be freed once the channel is unlocked.
+    fn try_claim(&mut self) -> Option<Option<Arc<Mutex<Completion>>>> {
+        if matches!(self.transfer, Transfer::Idle) {
+            self.transfer = Transfer::Claimed;
+
+            Some(self.finished.take())
+        } else {
+            None
+        }
+    }
+
+    /// Releases the bus and wakes the tasks waiting for it.
+    ///
+    /// # Notes
+    ///
+    /// * This doesn't allocate or free memory, so it's safe to call from an interrupt handler.
+    /// * The wakers stay registered until their waiters claim the bus or give up.
+    fn release(&mut self) {
+        self.transfer = Transfer::Idle;
+
+        for (_, waker) in &self.waiters {
+            waker.wake_by_ref();
+        }
+    }
+}
+
+/// The result of an interrupt-driven transfer.
+///
+/// # Fields
+///
+/// * `result` - The buffer of the transfer, and its result or error register, once the transfer is done.
+/// * `waker` - The task waiting for the transfer.
+///
+/// # Notes
+///
+/// * The buffer is handed back even if the transfer failed, so it's never freed by the interrupt handler.
+#[derive(Default)]
+struct Completion {
+    result: Option<(Vec<u8>, Result<(), u16>)>,
+    waker: Option<Waker>,
+}
+
+/// The exclusive use of an ATA bus, released when dropped.
+///
+/// # Fields
+///
+/// * `bus` - The bus.
+struct Claim {
+    bus: usize,
+}
+
+impl Claim {
+    /// Waits for a bus to become idle and claims it, spinning.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to claim.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The claim.
+    ///
+    /// # Errors
+    ///
+    /// * If the bus does not exist.
+    /// * If the bus stays busy for more than a second.
+    ///
+    /// # Notes
+    ///
+    /// * Interrupts must be enabled, so pending interrupt-driven transfers can complete.
+    fn blocking(bus: u8) -> Result<Self, Error> {
+        let channel = channel(bus)?;
+        let start = read_tsc();
+
+        loop {
+            if let Some(finished) = interrupts::without_interrupts(|| channel.lock().try_claim()) {
+                drop(finished);
+
+                return Ok(Self { bus: bus as usize });
+            }
+
+            if timed_out(start) {
+                return Err(Error::ATA(format!("ATA bus {bus} is busy!")));
+            }
+
+            spin_loop();
+        }
+    }
+
+    /// Waits for a bus to become idle and claims it, yielding to other tasks.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus to claim.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The claim.
+    ///
+    /// # Errors
+    ///
+    /// * If the bus does not exist.
+    async fn acquire(bus: u8) -> Result<Self, Error> {
+        channel(bus)?;
+
+        Ok(Acquire {
+            bus: bus as usize,
+            id: NEXT_WAITER.fetch_add(1, Ordering::Relaxed),
+        }
+        .await)
+    }
+
+    /// Hands the bus over to an interrupt-driven transfer, which releases it once done.
+    ///
+    /// # Arguments
+    ///
+    /// * `transfer` - The transfer.
+    fn hand_over(self, transfer: Transfer) {
+        interrupts::without_interrupts(|| CHANNELS[self.bus].lock().transfer = transfer);
+
+        mem::forget(self);
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        interrupts::without_interrupts(|| CHANNELS[self.bus].lock().release());
+    }
+}
+
+/// A future claiming an ATA bus once it's idle.
+///
+/// # Fields
+///
+/// * `bus` - The bus to claim.
+/// * `id` - The ID of the waiter, so its waker is replaced rather than added on every poll.
+struct Acquire {
+    bus: usize,
+    id: u64,
+}
+
+impl Future for Acquire {
+    type Output = Claim;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Claim> {
+        let claimed = interrupts::without_interrupts(|| {
+            let mut channel = CHANNELS[self.bus].lock();
+
+            if let Some(finished) = channel.try_claim() {
+                channel.waiters.retain(|(id, _)| *id != self.id);
+
+                return Some(finished);
+            }
+
+            match channel.waiters.iter_mut().find(|(id, _)| *id == self.id) {
+                // The task may have moved to another waker since the last poll.
+                Some((_, waker)) => {
+                    if !waker.will_wake(cx.waker()) {
+                        waker.clone_from(cx.waker());
+                    }
+                }
+                None => channel.waiters.push((self.id, cx.waker().clone())),
+            }
+
+            None
+        });
+
+        // The parked completion is freed here, with interrupts enabled again.
+        match claimed {
+            Some(_finished) => Poll::Ready(Claim { bus: self.bus }),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Acquire {
+    fn drop(&mut self) {
+        interrupts::without_interrupts(|| {
+            CHANNELS[self.bus]
+                .lock()
+                .waiters
+                .retain(|(id, _)| *id != self.id);
+        });
+    }
+}
+
+/// A locked and claimed ATA bus.
+///
+/// # Fields
+///
+/// * `bus` - The locked bus.
+/// * `_claim` - The claim, released after the bus is unlocked.
+struct BusGuard {
+    bus: MutexGuard<'static, Bus>,
+    _claim: Claim,
+}
+
+impl Deref for BusGuard {
+    type Target = Bus;
+
+    fn deref(&self) -> &Bus {
+        &self.bus
+    }
+}
+
+impl DerefMut for BusGuard {
+    fn deref_mut(&mut self) -> &mut Bus {
+        &mut self.bus
+    }
 }
 
 /// A command.
@@ -26,11 +372,19 @@ lazy_static! {
 /// * `Identify` - The identify command.
 /// * `Read` - The read command.
 /// * `Write` - The write command.
+/// * `FlushCache` - The flush cache command.
+/// * `ReadDma` - The read DMA command.
+/// * `WriteDma` - The write DMA command.
+/// * `Packet` - The ATAPI packet command.
 #[derive(Debug)]
 enum Command {
     Identify = 0xEC,
     Read = 0x20,
     Write = 0x30,
+    FlushCache = 0xE7,
+    ReadDma = 0xC8,
+    WriteDma = 0xCA,
+    Packet = 0xA0,
 }
 
 /// Represents a device type.
@@ -189,6 +543,170 @@ impl Register {
     }
 }
 
+/// The Bus Master IDE registers and DMA memory of an ATA bus.
+///
+/// # Fields
+///
+/// * `command` - The bus master command register.
+/// * `status` - The bus master status register.
+/// * `prdt_address` - The physical region descriptor table address register.
+/// * `prdt` - The physical address of the PRD table.
+/// * `buffer` - The physical address of the DMA buffer.
+///
+/// # See
+///
+/// * [ATA/ATAPI using DMA](https://wiki.osdev.org/ATA/ATAPI_using_DMA)
+#[derive(Debug, Clone)]
+struct BusMaster {
+    command: Port<u8>,
+    status: Port<u8>,
+    prdt_address: Port<u32>,
+
+    prdt: PhysAddr,
+    buffer: PhysAddr,
+}
+
+impl BusMaster {
+    /// The start bit of the command register.
+    const START: u8 = 1 << 0;
+    /// The direction bit of the command register, set when the drive writes to memory.
+    const READ: u8 = 1 << 3;
+
+    /// The active bit of the status register.
+    const ACTIVE: u8 = 1 << 0;
+    /// The error bit of the status register.
+    const ERROR: u8 = 1 << 1;
+    /// The interrupt bit of the status register.
+    const INTERRUPT: u8 = 1 << 2;
+
+    /// The end of table bit of a PRD entry.
+    const END_OF_TABLE: u16 = 1 << 15;
+
+    /// Sets up Bus Master DMA for a bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `io_base` - The I/O base of the bus master registers of the bus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The bus master.
+    ///
+    /// # Errors
+    ///
+    /// * If the PRD table or DMA buffer can't be allocated below 4 GiB.
+    fn new(io_base: u16) -> Result<Self, Error> {
+        // One frame for the PRD table, followed by the buffer.
+        let frames = 1 + DMA_BUFFER_SIZE / FRAME_SIZE;
+        let prdt = alloc_contiguous_frames(frames, DMA_LIMIT)?;
+
+        Ok(Self {
+            command: Port::new(io_base),
+            status: Port::new(io_base + 2),
+            prdt_address: Port::new(io_base + 4),
+
+            prdt,
+            buffer: prdt + FRAME_SIZE as u64,
+        })
+    }
+
+    /// Gets the DMA buffer.
+    ///
+    /// # Returns
+    ///
+    /// * `&mut [u8]` - The buffer, as mapped in virtual memory.
+    fn buffer(&mut self) -> &mut [u8] {
+        let pointer = phys_to_virt(self.buffer).as_mut_ptr::<u8>();
+
+        unsafe { core::slice::from_raw_parts_mut(pointer, DMA_BUFFER_SIZE) }
+    }
+
+    /// Prepares a transfer, without starting it.
+    ///
+    /// # Arguments
+    ///
+    /// * `length` - The length of the transfer, at most the size of the buffer.
+    /// * `read` - Whether or not the drive writes to memory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If an address or length doesn't fit its PRD field.
+    ///
+    /// # Notes
+    ///
+    /// * Each PRD entry covers a frame of the buffer, so none crosses a 64 KiB boundary.
+    fn prepare(&mut self, length: usize, read: bool) -> Result<(), Error> {
+        let table = phys_to_virt(self.prdt).as_mut_ptr::<u64>();
+
+        let entries = length.div_ceil(FRAME_SIZE);
+        for entry in 0..entries {
+            let address = u32::try_from(self.buffer.as_u64() + (entry * FRAME_SIZE) as u64)?;
+            let size = u16::try_from(FRAME_SIZE.min(length - entry * FRAME_SIZE))?;
+            let flags = if entry + 1 == entries {
+                Self::END_OF_TABLE
+            } else {
+                0
+            };
+
+            unsafe {
+                table.add(entry).write_volatile(
+                    u64::from(address) | u64::from(size) << 32 | u64::from(flags) << 48,
+                );
+            }
+        }
+
+        unsafe {
+            self.command.write(if read { Self::READ } else { 0 });
+            self.prdt_address.write(u32::try_from(self.prdt.as_u64())?);
+
+            // The error and interrupt bits are cleared by writing them.
+            self.status.write(Self::ERROR | Self::INTERRUPT);
+        }
+
+        Ok(())
+    }
+
+    /// Starts the prepared transfer, once the drive received its command.
+    fn start(&mut self) {
+        unsafe {
+            let command = self.command.read();
+            self.command.write(command | Self::START);
+        }
+    }
+
+    /// Checks if the transfer is still running.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the transfer is running.
+    fn active(&mut self) -> bool {
+        let status = unsafe { self.status.read() };
+
+        status & Self::ACTIVE != 0 && status & Self::INTERRUPT == 0
+    }
+
+    /// Stops the transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the transfer failed.
+    fn stop(&mut self) -> bool {
+        unsafe {
+            let command = self.command.read();
+            self.command.write(command & !Self::START);
+
+            let status = self.status.read();
+            self.status.write(Self::ERROR | Self::INTERRUPT);
+
+            status & Self::ERROR != 0
+        }
+    }
+}
+
 /// The ATA bus.
 ///
 /// # Fields
@@ -210,6 +728,8 @@ impl Register {
 /// * `alternate_status` - The alternate status register.
 /// * `device_address` - The device address register.
 /// * `device_control` - The device control register.
+///
+/// * `dma` - The Bus Master IDE registers of the bus, if it supports DMA.
 #[derive(Debug, Clone)]
 pub struct Bus {
     id: u8,
@@ -229,6 +749,8 @@ pub struct Bus {
     alternate_status: Register,
     device_address: Register,
     device_control: Register,
+
+    dma: Option<BusMaster>,
 }
 
 impl Bus {
@@ -264,6 +786,8 @@ impl Bus {
             alternate_status: Register::AlternateStatus(PortReadOnly::new(ctrl_base)),
             device_address: Register::DeviceAddress(PortReadOnly::new(ctrl_base)),
             device_control: Register::DeviceControl(PortWriteOnly::new(ctrl_base)),
+
+            dma: None,
         }
     }
 
@@ -361,7 +885,10 @@ impl Bus {
         // Select the drive.
         self.select_drive(drive)?;
         // Clear the registers.
-        self.write_cmd_params(drive, 0)?;
+        self.write_cmd_params(drive, 0, 1)?;
+        // Send the identify command.
+        self.command.write(Command::Identify as u16)?;
+        wait(400);
 
         // Read the status register.
         let status = self.status.read()?;
@@ -373,9 +900,14 @@ impl Bus {
         // Poll the status register until busy clears.
         self.poll(Status::Busy, false)?;
 
-        // Determine if the drive type.
+        // Determine if the drive type, packet devices abort the command with their signature.
         let device_type = match (self.lba1.read()?, self.lba2.read()?) {
             (0x00, 0x00) => DeviceType::Ata({
+                if self.error()? {
+                    return Err(Error::ATA("ATA identify error!".into()));
+                }
+                self.poll(Status::DataRequest, true)?;
+
                 let mut buffer = Box::new([0; 256]);
                 for chunk in buffer.iter_mut() {
                     *chunk = self.data.read()?;
@@ -407,10 +939,10 @@ impl Bus {
     /// * If the ATA times out.
     /// * If the status register is write-only.
     fn poll(&mut self, bit: Status, value: bool) -> Result<(), Error> {
-        let start = uptime();
+        let start = read_tsc();
 
         while self.status.read()?.get_bit(bit as usize) != value {
-            if uptime() - start > 1.0 {
+            if timed_out(start) {
                 return Err(Error::Internal("ATA timeout.".into()));
             }
 
@@ -425,8 +957,8 @@ impl Bus {
     /// # Arguments
     ///
     /// * `drive` - The drive to read from.
-    /// * `blk` - The block to read from.
-    /// * `buffer` - The buffer to read into.
+    /// * `blk` - The first block to read from.
+    /// * `buffer` - The buffer to read into, at most [`MAX_TRANSFER_BLOCKS`] blocks long.
     ///
     /// # Returns
     ///
@@ -436,14 +968,39 @@ impl Bus {
     ///
     /// * If PIO fails to setup for the given drive and block.
     /// * If the ATA read fails.
+    ///
+    /// # Notes
+    ///
+    /// * DMA is used if the bus supports it, and PIO otherwise or if DMA fails.
     fn read(&mut self, drive: u8, block: u32, buffer: &mut [u8]) -> Result<(), Error> {
-        self.setup_pio(drive, block)?;
+        if self.dma.is_some() {
+            match self.dma_blocking(drive, block, buffer.len(), true) {
+                Ok(()) => {
+                    if let Some(dma) = &mut self.dma {
+                        buffer.copy_from_slice(&dma.buffer()[..buffer.len()]);
+                    }
+
+                    return Ok(());
+                }
+                Err(why) => self.disable_dma(&why),
+            }
+        }
+
+        self.setup_pio(drive, block, buffer.len())?;
         self.write_cmd(Command::Read)?;
 
-        for chunk in buffer.chunks_mut(2) {
-            let data = self.data.read()?.to_le_bytes();
+        for (index, sector) in buffer.chunks_mut(BLOCK_SIZE).enumerate() {
+            // The drive raises DRQ again once the next block is ready.
+            if index > 0 {
+                self.poll(Status::Busy, false)?;
+                self.poll(Status::DataRequest, true)?;
+            }
 
-            chunk.clone_from_slice(&data);
+            for chunk in sector.chunks_mut(2) {
+                let data = self.data.read()?.to_le_bytes();
+
+                chunk.clone_from_slice(&data);
+            }
         }
 
         if self.error()? {
@@ -453,7 +1010,11 @@ impl Bus {
         Ok(())
     }
 
-    /// Resets the bus.
+    /// Flushes the write cache of a drive.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive to flush.
     ///
     /// # Returns
     ///
@@ -461,23 +1022,52 @@ impl Bus {
     ///
     /// # Errors
     ///
-    /// * If the device control register is write-only.
-    fn reset(&mut self) -> Result<(), Error> {
-        self.device_control.write(4)?; // set SRST.
-        wait(5); // Wait for 5 nanoseconds.
+    /// * If the ATA times out.
+    /// * If the ATA returns an error.
+    fn flush(&mut self, drive: u8) -> Result<(), Error> {
+        self.select_drive(drive)?;
+        self.command.write(Command::FlushCache as u16)?;
 
-        self.device_control.write(0)?; // Clear control register.
-        wait(2_000); // Wait for 2 microseconds.
+        // Wait for 400 nanoseconds.
+        wait(400);
+
+        // The flush has no data phase, so only wait for the drive to finish.
+        self.poll(Status::Busy, false)?;
+        self.clear_interrupt()?;
+
+        if self.error()? {
+            return Err(Error::Internal("ATA flush error!".into()));
+        }
 
         Ok(())
     }
 
-    /// Sets up PIO.
+    /// Resets the bus.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device control register is write-only.
+    fn reset(&mut self) -> Result<(), Error> {
+        self.device_control.write(4)?; // set SRST.
+        wait(5); // Wait for 5 nanoseconds.
+
+        self.device_control.write(0)?; // Clear control register.
+        wait(2_000); // Wait for 2 microseconds.
+
+        Ok(())
+    }
+
+    /// Sets up PIO.
     ///
     /// # Arguments
     ///
     /// * `drive` - The drive to setup.
     /// * `block` - The block to setup.
+    /// * `length` - The length of the transfer, in bytes.
     ///
     /// # Returns
     ///
@@ -487,9 +1077,10 @@ impl Bus {
     ///
     /// * If the drive does not exist.
     /// * If the ATA times out.
-    fn setup_pio(&mut self, drive: u8, block: u32) -> Result<(), Error> {
+    /// * If the length isn't between 1 and [`MAX_TRANSFER_BLOCKS`] blocks.
+    fn setup_pio(&mut self, drive: u8, block: u32, length: usize) -> Result<(), Error> {
         self.select_drive(drive)?;
-        self.write_cmd_params(drive, block)?;
+        self.write_cmd_params(drive, block, sector_count(length)?)?;
 
         Ok(())
     }
@@ -499,8 +1090,8 @@ impl Bus {
     /// # Arguments
     ///
     /// * `drive` - The drive to write to.
-    /// * `block` - The block to write to.
-    /// * `buffer` - The buffer to write from.
+    /// * `block` - The first block to write to.
+    /// * `buffer` - The buffer to write from, at most [`MAX_TRANSFER_BLOCKS`] blocks long.
     ///
     /// # Returns
     ///
@@ -512,14 +1103,35 @@ impl Bus {
     /// * If the ATA write fails.
     /// * If the ATA returns an error.
     /// * If the chunk is not a valid u16.
+    ///
+    /// # Notes
+    ///
+    /// * DMA is used if the bus supports it, and PIO otherwise or if DMA fails.
     fn write(&mut self, drive: u8, block: u32, buffer: &[u8]) -> Result<(), Error> {
-        self.setup_pio(drive, block)?;
+        if let Some(dma) = &mut self.dma {
+            dma.buffer()[..buffer.len()].copy_from_slice(buffer);
+
+            match self.dma_blocking(drive, block, buffer.len(), false) {
+                Ok(()) => return Ok(()),
+                Err(why) => self.disable_dma(&why),
+            }
+        }
+
+        self.setup_pio(drive, block, buffer.len())?;
         self.write_cmd(Command::Write)?;
 
-        for chunk in buffer.chunks(2) {
-            let data = u16::from_le_bytes(chunk.try_into()?);
+        for (index, sector) in buffer.chunks(BLOCK_SIZE).enumerate() {
+            // The drive raises DRQ again once it's ready for the next block.
+            if index > 0 {
+                self.poll(Status::Busy, false)?;
+                self.poll(Status::DataRequest, true)?;
+            }
 
-            self.data.write(data)?;
+            for chunk in sector.chunks(2) {
+                let data = u16::from_le_bytes(chunk.try_into()?);
+
+                self.data.write(data)?;
+            }
         }
 
         if self.error()? {
@@ -570,6 +1182,7 @@ impl Bus {
     ///
     /// * `drive` - The drive to write to.
     /// * `block` - The block to write to.
+    /// * `count` - The number of blocks.
     ///
     /// # Returns
     ///
@@ -577,8 +1190,16 @@ impl Bus {
     ///
     /// # Errors
     ///
+    /// * If the blocks aren't addressable with 28-bit LBA.
     /// * If the sector count register is read-only.
-    fn write_cmd_params(&mut self, drive: u8, block: u32) -> Result<(), Error> {
+    fn write_cmd_params(&mut self, drive: u8, block: u32, count: u8) -> Result<(), Error> {
+        // The top bits would overwrite the drive and mode bits.
+        if u64::from(block) + u64::from(count) > u64::from(LBA28_BLOCKS) {
+            return Err(Error::ATA(format!(
+                "Block {block} is beyond the 28-bit LBA range!"
+            )));
+        }
+
         let lba = true;
         let mut bytes = block.to_le_bytes();
 
@@ -587,7 +1208,7 @@ impl Bus {
         bytes[3].set_bit(6, lba);
         bytes[3].set_bit(7, true);
 
-        self.sector_count.write(1)?;
+        self.sector_count.write(u16::from(count))?;
         self.lba0.write(u16::from(bytes[0]))?;
         self.lba1.write(u16::from(bytes[1]))?;
         self.lba2.write(u16::from(bytes[2]))?;
@@ -595,15 +1216,627 @@ impl Bus {
 
         Ok(())
     }
+
+    /// Sends an ATAPI packet command and reads its data.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive to send the packet to.
+    /// * `packet` - The SCSI command.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes read.
+    ///
+    /// # Errors
+    ///
+    /// * If the drive does not exist.
+    /// * If the ATA times out.
+    /// * If the drive rejects the command, such as when there's no medium.
+    fn packet(&mut self, drive: u8, packet: &[u8; 12], buffer: &mut [u8]) -> Result<usize, Error> {
+        self.select_drive(drive)?;
+
+        // Use PIO, and ask for at most the buffer per data request.
+        let limit = u16::try_from(buffer.len().min(0xF800))?;
+        self.features.write(0)?;
+        self.lba1.write(limit & 0xFF)?;
+        self.lba2.write(limit >> 8)?;
+        self.command.write(Command::Packet as u16)?;
+        wait(400);
+
+        self.poll(Status::Busy, false)?;
+        if self.error()? {
+            return Err(Error::ATA("ATAPI packet rejected!".into()));
+        }
+        self.poll(Status::DataRequest, true)?;
+
+        for chunk in packet.chunks_exact(2) {
+            self.data.write(u16::from_le_bytes([chunk[0], chunk[1]]))?;
+        }
+
+        let mut offset = 0;
+        loop {
+            wait(400);
+            self.poll(Status::Busy, false)?;
+
+            let status = self.status.read()?;
+            if status.get_bit(Status::Error as usize) {
+                return Err(Error::ATA(format!(
+                    "ATAPI command {command:#04X} failed with error {error:#04X}!",
+                    command = packet[0],
+                    error = self.error.read()?
+                )));
+            }
+            if !status.get_bit(Status::DataRequest as usize) {
+                break;
+            }
+
+            // The drive tells how much it sends for this data request.
+            let count = usize::from(self.lba1.read()? | self.lba2.read()? << 8);
+            for _ in 0..count.div_ceil(2) {
+                let data = self.data.read()?.to_le_bytes();
+
+                for byte in data {
+                    if let Some(target) = buffer.get_mut(offset) {
+                        *target = byte;
+                    }
+                    offset += 1;
+                }
+            }
+        }
+
+        Ok(offset.min(buffer.len()))
+    }
+
+    /// Starts an interrupt-driven read.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive to read from.
+    /// * `block` - The first block to read.
+    /// * `length` - The length of the read, at most [`MAX_TRANSFER_BLOCKS`] blocks.
+    /// * `completion` - The completion of the transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Transfer, Error>` - The transfer to hand the bus over to.
+    ///
+    /// # Errors
+    ///
+    /// * If the transfer fails to setup for the given drive and block.
+    ///
+    /// # Notes
+    ///
+    /// * The data is read by the interrupt handler, from the DMA buffer if the bus supports DMA.
+    fn start_read(
+        &mut self,
+        drive: u8,
+        block: u32,
+        length: usize,
+        completion: Arc<Mutex<Completion>>,
+    ) -> Result<Transfer, Error> {
+        let buffer = vec![0; length];
+
+        if self.dma.is_some() {
+            self.start_dma(drive, block, length, true)?;
+
+            return Ok(Transfer::Dma { buffer, completion });
+        }
+
+        self.setup_pio(drive, block, length)?;
+        self.command.write(Command::Read as u16)?;
+
+        Ok(Transfer::Read {
+            buffer,
+            offset: 0,
+            completion,
+        })
+    }
+
+    /// Starts an interrupt-driven write.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive to write to.
+    /// * `block` - The first block to write.
+    /// * `data` - The data to write, at most [`MAX_TRANSFER_BLOCKS`] blocks long.
+    /// * `completion` - The completion of the transfer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Transfer, Error>` - The transfer to hand the bus over to.
+    ///
+    /// # Errors
+    ///
+    /// * If the transfer fails to setup for the given drive and block.
+    /// * If the chunk is not a valid u16.
+    ///
+    /// # Notes
+    ///
+    /// * With PIO, the first block is sent here and the others by the interrupt handler.
+    fn start_write(
+        &mut self,
+        drive: u8,
+        block: u32,
+        data: &[u8],
+        completion: Arc<Mutex<Completion>>,
+    ) -> Result<Transfer, Error> {
+        if let Some(dma) = &mut self.dma {
+            dma.buffer()[..data.len()].copy_from_slice(data);
+            self.start_dma(drive, block, data.len(), false)?;
+
+            return Ok(Transfer::Dma {
+                buffer: Vec::new(),
+                completion,
+            });
+        }
+
+        self.setup_pio(drive, block, data.len())?;
+        self.write_cmd(Command::Write)?;
+        self.send_block(&data[..BLOCK_SIZE]);
+
+        Ok(Transfer::Write {
+            data: data.to_vec(),
+            offset: BLOCK_SIZE,
+            completion,
+        })
+    }
+
+    /// Sends a block to the drive, once it's ready for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block.
+    ///
+    /// # Notes
+    ///
+    /// * This doesn't allocate, so it's safe to call from an interrupt handler.
+    fn send_block(&mut self, block: &[u8]) {
+        for chunk in block.chunks_exact(2) {
+            self.data
+                .write(u16::from_le_bytes([chunk[0], chunk[1]]))
+                .ok();
+        }
+    }
+
+    /// Starts a DMA transfer.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive.
+    /// * `block` - The first block.
+    /// * `length` - The length of the transfer, at most [`DMA_BUFFER_SIZE`].
+    /// * `read` - Whether the transfer reads from the drive, or writes to it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the bus doesn't support DMA.
+    /// * If the transfer fails to setup for the given drive and block.
+    ///
+    /// # Notes
+    ///
+    /// * Data written must already be in the DMA buffer.
+    fn start_dma(&mut self, drive: u8, block: u32, length: usize, read: bool) -> Result<(), Error> {
+        let Some(dma) = &mut self.dma else {
+            return Err(Error::ATA("ATA bus doesn't support DMA!".into()));
+        };
+        dma.prepare(length, read)?;
+
+        self.setup_pio(drive, block, length)?;
+        self.command.write(if read {
+            Command::ReadDma
+        } else {
+            Command::WriteDma
+        } as u16)?;
+
+        if let Some(dma) = &mut self.dma {
+            dma.start();
+        }
+
+        Ok(())
+    }
+
+    /// Runs a DMA transfer, spinning until it's done.
+    ///
+    /// # Arguments
+    ///
+    /// * `drive` - The drive.
+    /// * `block` - The first block.
+    /// * `length` - The length of the transfer, at most [`DMA_BUFFER_SIZE`].
+    /// * `read` - Whether the transfer reads from the drive, or writes to it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the transfer fails to setup for the given drive and block.
+    /// * If the ATA times out.
+    /// * If the ATA or the bus master returns an error.
+    fn dma_blocking(
+        &mut self,
+        drive: u8,
+        block: u32,
+        length: usize,
+        read: bool,
+    ) -> Result<(), Error> {
+        self.start_dma(drive, block, length, read)?;
+
+        let start = read_tsc();
+        while self.dma.as_mut().is_some_and(BusMaster::active) {
+            if timed_out(start) {
+                self.dma.as_mut().map(BusMaster::stop);
+
+                return Err(Error::ATA("ATA DMA timeout!".into()));
+            }
+
+            spin_loop();
+        }
+
+        self.finish_dma(&mut [])
+            .map_err(|error| Error::ATA(format!("ATA DMA failed with error {error:#04X}!")))
+    }
+
+    /// Stops using DMA on the bus, after it failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `why` - Why DMA failed.
+    fn disable_dma(&mut self, why: &Error) {
+        println!(
+            "[WARN]: {why} Falling back to PIO on ATA bus {id}.",
+            id = self.id
+        );
+
+        self.dma = None;
+    }
+
+    /// Acknowledges the interrupt of the drive and checks the outcome of the command.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), u16>` - The result of the command, or the error register if it failed.
+    ///
+    /// # Notes
+    ///
+    /// * This doesn't allocate, so it's safe to call from an interrupt handler.
+    fn finish(&mut self) -> Result<(), u16> {
+        let status = self.clear_interrupt().unwrap_or(0);
+
+        if status.get_bit(Status::Error as usize) || status.get_bit(Status::DriveFault as usize) {
+            return Err(self.error.read().unwrap_or(0));
+        }
+
+        Ok(())
+    }
+
+    /// Completes an interrupt-driven read, once the drive raised its interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), u16>` - The result of the read, or the error register if it failed.
+    ///
+    /// # Notes
+    ///
+    /// * This doesn't allocate, so it's safe to call from an interrupt handler.
+    fn finish_read(&mut self, buffer: &mut [u8]) -> Result<(), u16> {
+        self.finish()?;
+
+        for chunk in buffer.chunks_mut(2) {
+            chunk.copy_from_slice(&self.data.read().unwrap_or(0).to_le_bytes());
+        }
+
+        Ok(())
+    }
+
+    /// Completes a DMA transfer, once the drive raised its interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to copy the data read into, empty for writes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), u16>` - The result of the transfer, or the error register if it failed.
+    ///
+    /// # Notes
+    ///
+    /// * This doesn't allocate, so it's safe to call from an interrupt handler.
+    fn finish_dma(&mut self, buffer: &mut [u8]) -> Result<(), u16> {
+        let Some(dma) = &mut self.dma else {
+            return Err(0);
+        };
+
+        let failed = dma.stop();
+        if !buffer.is_empty() && !failed {
+            buffer.copy_from_slice(&dma.buffer()[..buffer.len()]);
+        }
+
+        self.finish()?;
+
+        if failed {
+            return Err(0);
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the number of blocks in a transfer.
+///
+/// # Arguments
+///
+/// * `length` - The length of the transfer, in bytes.
+///
+/// # Returns
+///
+/// * `Result<u8, Error>` - The number of blocks.
+///
+/// # Errors
+///
+/// * If the length isn't between 1 and [`MAX_TRANSFER_BLOCKS`] blocks.
+fn sector_count(length: usize) -> Result<u8, Error> {
+    let count = length / BLOCK_SIZE;
+    if count == 0 || count > MAX_TRANSFER_BLOCKS || !length.is_multiple_of(BLOCK_SIZE) {
+        return Err(Error::ATA(format!(
+            "Transfer of {length} bytes isn't 1 to {MAX_TRANSFER_BLOCKS} blocks!"
+        )));
+    }
+
+    Ok(u8::try_from(count)?)
+}
+
+/// Checks if a second passed while waiting for a drive.
+///
+/// # Arguments
+///
+/// * `start` - The time-stamp counter when waiting started.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the wait timed out.
+///
+/// # Notes
+///
+/// * The time-stamp counter keeps running with interrupts disabled, unlike the PIT-based monotonic clock, so a hung drive can't hang the kernel while a transfer starts.
+fn timed_out(start: u64) -> bool {
+    read_tsc() - start > tsc_frequency()
+}
+
+/// Gets the channel of a bus.
+///
+/// # Arguments
+///
+/// * `bus` - The bus.
+///
+/// # Returns
+///
+/// * `Result<&Mutex<Channel>, Error>` - The channel.
+///
+/// # Errors
+///
+/// * If the bus does not exist.
+fn channel(bus: u8) -> Result<&'static Mutex<Channel>, Error> {
+    CHANNELS
+        .get(bus as usize)
+        .ok_or_else(|| Error::ATA(format!("ATA bus {bus} does not exist!")))
+}
+
+/// Called by the ATA interrupt handlers
+///
+/// Must not block or allocate.
+///
+/// # Arguments
+///
+/// * `bus` - The bus that raised the interrupt.
+pub(crate) fn interrupt(bus: usize) {
+    let Some(channel) = CHANNELS.get(bus) else {
+        return;
+    };
+    let mut channel = channel.lock();
+
+    // Nobody else locks the bus while it's handed over to a transfer.
+    let Some(mut bus) = BUSES[bus].try_lock() else {
+        return;
+    };
+
+    let (buffer, result, completion) = match mem::replace(&mut channel.transfer, Transfer::Idle) {
+        Transfer::Read {
+            mut buffer,
+            offset,
+            completion,
+        } => {
+            let result = bus.finish_read(&mut buffer[offset..offset + BLOCK_SIZE]);
+
+            // Wait for the interrupt of the next block.
+            if result.is_ok() && offset + BLOCK_SIZE < buffer.len() {
+                channel.transfer = Transfer::Read {
+                    buffer,
+                    offset: offset + BLOCK_SIZE,
+                    completion,
+                };
+
+                return;
+            }
+
+            (buffer, result, completion)
+        }
+        Transfer::Write {
+            data,
+            offset,
+            completion,
+        } => {
+            let result = bus.finish();
+
+            // The drive is ready for the next block once it raised its interrupt.
+            if result.is_ok() && offset < data.len() {
+                bus.send_block(&data[offset..offset + BLOCK_SIZE]);
+                channel.transfer = Transfer::Write {
+                    data,
+                    offset: offset + BLOCK_SIZE,
+                    completion,
+                };
+
+                return;
+            }
+
+            (data, result, completion)
+        }
+        Transfer::Dma {
+            mut buffer,
+            completion,
+        } => {
+            let result = bus.finish_dma(&mut buffer);
+
+            (buffer, result, completion)
+        }
+        transfer => {
+            // A stray interrupt, reading the status acknowledges it.
+            channel.transfer = transfer;
+            bus.clear_interrupt().ok();
+
+            return;
+        }
+    };
+
+    {
+        let mut completion = completion.lock();
+        completion.result = Some((buffer, result));
+        if let Some(waker) = &completion.waker {
+            waker.wake_by_ref();
+        }
+    }
+
+    // Every claim takes the parked completion, so there is none to drop here.
+    channel.finished = Some(completion);
+    channel.release();
+}
+
+/// An interrupt-driven transfer handed over to the IRQ handler, abandoned if dropped before it completes.
+///
+/// # Fields
+///
+/// * `bus` - The bus of the transfer.
+/// * `completion` - The completion of the transfer.
+struct InFlight {
+    bus: usize,
+    completion: Arc<Mutex<Completion>>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let abandoned = interrupts::without_interrupts(|| {
+            let mut channel = CHANNELS[self.bus].lock();
+
+            // Once the transfer completed, the bus may already belong to another one.
+            let ours = channel
+                .transfer
+                .completion()
+                .is_some_and(|completion| Arc::ptr_eq(completion, &self.completion));
+            if !ours {
+                return None;
+            }
+
+            let transfer = mem::replace(&mut channel.transfer, Transfer::Idle);
+            let mut bus = BUSES[self.bus].lock();
+            bus.dma.as_mut().map(BusMaster::stop);
+            bus.reset().ok();
+            channel.release();
+
+            Some(transfer)
+        });
+
+        // The buffer of the abandoned transfer is freed here, with interrupts enabled again.
+        drop(abandoned);
+    }
+}
+
+/// Runs an interrupt-driven transfer.
+///
+/// # Arguments
+///
+/// * `bus` - The bus of the drive.
+/// * `start` - Starts the transfer on the locked bus, and returns the transfer to wait for.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The buffer of the transfer, holding the data read for reads.
+///
+/// # Errors
+///
+/// * If the bus does not exist.
+/// * If the transfer fails to start.
+/// * If the ATA times out, in which case the bus is reset.
+/// * If the ATA returns an error.
+async fn transfer(
+    bus: u8,
+    start: impl FnOnce(&mut Bus, Arc<Mutex<Completion>>) -> Result<Transfer, Error>,
+) -> Result<Vec<u8>, Error> {
+    let claim = Claim::acquire(bus).await?;
+    let completion = Arc::new(Mutex::new(Completion::default()));
+
+    // The interrupt can't be handled before the transfer is handed over.
+    interrupts::without_interrupts(|| {
+        let transfer = start(&mut BUSES[bus as usize].lock(), completion.clone())?;
+        claim.hand_over(transfer);
+
+        Ok::<_, Error>(())
+    })?;
+
+    let in_flight = InFlight {
+        bus: bus as usize,
+        completion,
+    };
+    let completed = poll_fn(|cx| {
+        interrupts::without_interrupts(|| {
+            let mut completion = in_flight.completion.lock();
+
+            match completion.result.take() {
+                Some(result) => Poll::Ready(result),
+                None => {
+                    completion.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    });
+
+    let Some((buffer, result)) = timeout(TRANSFER_TIMEOUT, completed).await else {
+        return Err(Error::ATA(format!(
+            "ATA bus {bus} timed out, resetting it!"
+        )));
+    };
+
+    result
+        .map(|()| buffer)
+        .map_err(|error| Error::ATA(format!("ATA transfer failed with error {error:#04X}!")))
 }
 
 /// Initializes the ATA driver.
+///
+/// # Notes
+///
+/// * Every drive is registered as the block device `ata<N>`, where `N` is `bus * 2 + disk`.
+/// * Every ATAPI drive with a medium is registered as the read-only block device `atapi<N>`.
 pub fn init() {
-    {
-        let mut buses = BUSES.lock();
+    // Unmask the IRQs of the buses.
+    for bus in BUSES.iter() {
+        let irq = bus.lock().irq;
+        pic::unmask(irq);
+    }
 
-        buses.push(Bus::new(0, 14, 0x1F0, 0x3F6));
-        buses.push(Bus::new(1, 15, 0x170, 0x376));
+    if pci::register_driver(&IDE_DRIVER) == 0 {
+        println!("[INFO]: => No IDE controller, using PIO");
     }
 
     for drive in list_drives() {
@@ -612,7 +1845,81 @@ pub fn init() {
             bus = drive.bus,
             disk = drive.disk
         );
+
+        let name = format!("ata{index}", index = drive.bus * 2 + drive.disk);
+        if let Err(why) = block::register(&name, Arc::new(CachedDevice::new(Arc::new(drive)))) {
+            println!("[WARN]: {why}");
+        }
+    }
+
+    for drive in list_atapi_drives() {
+        println!(
+            "[INFO]: => ATAPI (Bus: {bus}, Disk: {disk}, Blocks: {blocks})",
+            bus = drive.bus,
+            disk = drive.disk,
+            blocks = drive.block
+        );
+
+        let name = format!("atapi{index}", index = drive.bus * 2 + drive.disk);
+        if let Err(why) = block::register(&name, Arc::new(CachedDevice::new(Arc::new(drive)))) {
+            println!("[WARN]: {why}");
+        }
+    }
+}
+
+/// The PCI driver of the IDE controller, which sets up DMA.
+static IDE_DRIVER: pci::Driver = pci::Driver {
+    name: "ata",
+    // Mass storage controller, IDE interface.
+    matches: &[Match::Class {
+        class: 0x01,
+        subclass: 0x01,
+    }],
+    probe: probe_ide,
+};
+
+/// Sets up Bus Master IDE DMA on the buses, if the IDE controller supports it.
+///
+/// # Arguments
+///
+/// * `info` - The IDE controller.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If another IDE controller already set up DMA.
+///
+/// # Notes
+///
+/// * Buses without DMA keep using PIO.
+/// * The buses themselves are always at the legacy ports.
+fn probe_ide(info: &DeviceInfo) -> Result<(), Error> {
+    if BUSES.iter().any(|bus| bus.lock().dma.is_some()) {
+        return Err(Error::ATA("Only one IDE controller is supported!".into()));
     }
+
+    let Some(Bar::Io { port: io_base, .. }) = info.bars[4].filter(|_| info.prog_if & (1 << 7) != 0)
+    else {
+        println!("[INFO]: => IDE controller doesn't support bus mastering, using PIO");
+        return Ok(());
+    };
+
+    info.device.enable_bus_mastering();
+
+    // The registers of the secondary bus follow those of the primary one.
+    for (io_base, bus) in (io_base..).step_by(8).zip(BUSES.iter()) {
+        match BusMaster::new(io_base) {
+            Ok(dma) => bus.lock().dma = Some(dma),
+            Err(why) => println!("[WARN]: {why} Using PIO."),
+        }
+    }
+
+    println!("[INFO]: => Bus Master IDE DMA (I/O base: {io_base:#X})");
+
+    Ok(())
 }
 
 /// Represents an ATA drive.
@@ -670,15 +1977,18 @@ impl Drive {
     ///
     /// * `Option<Self>` - The drive, if it exists.
     pub fn open(bus: u8, disk: u8) -> Option<Self> {
-        let mut buses = BUSES.lock();
+        let mut bus_lock = lock_bus(bus).ok()?;
 
         // Identify the drive.
-        let Ok(DeviceType::Ata(result)) = buses[bus as usize].identify_drive(disk) else {
+        let Ok(DeviceType::Ata(result)) = bus_lock.identify_drive(disk) else {
             return None;
         };
 
         let buffer = result.map(u16::to_le_bytes).concat();
-        let block = u32::from_be_bytes(buffer[120..124].try_into().ok()?).rotate_left(16);
+        // Only the blocks addressable with 28-bit LBA are used, even on larger drives.
+        let block = u32::from_be_bytes(buffer[120..124].try_into().ok()?)
+            .rotate_left(16)
+            .min(LBA28_BLOCKS);
         let model = String::from_utf8_lossy(&buffer[54..94]).trim().into();
         let serial = String::from_utf8_lossy(&buffer[20..40]).trim().into();
 
@@ -691,6 +2001,68 @@ impl Drive {
         })
     }
 
+    /// Reads consecutive blocks, waiting for the drive's interrupts instead of spinning.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first block to read.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the blocks are out of range.
+    /// * If the ATA times out.
+    /// * If the ATA returns an error.
+    pub async fn read(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        let blocks = (u32::try_from(block)?..).step_by(MAX_TRANSFER_BLOCKS);
+        for (block, chunk) in blocks.zip(buffer.chunks_mut(DMA_BUFFER_SIZE)) {
+            let data = transfer(self.bus, |bus, completion| {
+                bus.start_read(self.disk, block, chunk.len(), completion)
+            })
+            .await?;
+
+            chunk.copy_from_slice(&data);
+        }
+
+        Ok(())
+    }
+
+    /// Writes consecutive blocks, waiting for the drive's interrupts instead of spinning.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first block to write.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the blocks are out of range.
+    /// * If the ATA times out.
+    /// * If the ATA returns an error.
+    pub async fn write(&self, block: u64, data: &[u8]) -> Result<(), Error> {
+        check_range(self, block, data.len())?;
+
+        let blocks = (u32::try_from(block)?..).step_by(MAX_TRANSFER_BLOCKS);
+        for (block, chunk) in blocks.zip(data.chunks(DMA_BUFFER_SIZE)) {
+            transfer(self.bus, |bus, completion| {
+                bus.start_write(self.disk, block, chunk, completion)
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
     /// Gets the formatted size of the drive.
     ///
     /// # Returns
@@ -739,8 +2111,8 @@ pub fn list_drives() -> Vec<Drive> {
 ///
 /// * `bus` - The bus of the drive.
 /// * `drive` - The drive to read from.
-/// * `blk` - The block to read from.
-/// * `buffer` - The buffer to read into.
+/// * `blk` - The first block to read from.
+/// * `buffer` - The buffer to read into, at most 128 blocks long.
 ///
 /// # Returns
 ///
@@ -753,9 +2125,7 @@ pub fn list_drives() -> Vec<Drive> {
 /// * If the ATA read fails.
 /// * If the ATA returns an error.
 pub fn read(bus: u8, drive: u8, block: u32, buffer: &mut [u8]) -> Result<(), Error> {
-    let mut buses = BUSES.lock();
-
-    buses[bus as usize].read(drive, block, buffer)
+    lock_bus(bus)?.read(drive, block, buffer)
 }
 
 /// Writes to a drive.
@@ -764,8 +2134,8 @@ pub fn read(bus: u8, drive: u8, block: u32, buffer: &mut [u8]) -> Result<(), Err
 ///
 /// * `bus` - The bus of the drive.
 /// * `drive` - The drive to write to.
-/// * `block` - The block to write to.
-/// * `buffer` - The buffer to write from.
+/// * `block` - The first block to write to.
+/// * `buffer` - The buffer to write from, at most 128 blocks long.
 ///
 /// # Returns
 ///
@@ -778,7 +2148,226 @@ pub fn read(bus: u8, drive: u8, block: u32, buffer: &mut [u8]) -> Result<(), Err
 /// * If the ATA write fails.
 /// * If the ATA returns an error.
 pub fn write(bus: u8, drive: u8, block: u32, buffer: &[u8]) -> Result<(), Error> {
-    let mut buses = BUSES.lock();
+    lock_bus(bus)?.write(drive, block, buffer)
+}
 
-    buses[bus as usize].write(drive, block, buffer)
+/// Flushes the write cache of a drive.
+///
+/// # Arguments
+///
+/// * `bus` - The bus of the drive.
+/// * `drive` - The drive to flush.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the drive does not exist.
+/// * If the ATA times out.
+/// * If the ATA returns an error.
+pub fn flush(bus: u8, drive: u8) -> Result<(), Error> {
+    lock_bus(bus)?.flush(drive)
+}
+
+/// Claims and locks a bus for a polling transfer.
+///
+/// # Arguments
+///
+/// * `bus` - The bus to lock.
+///
+/// # Returns
+///
+/// * `Result<BusGuard, Error>` - The locked bus.
+///
+/// # Errors
+///
+/// * If the bus does not exist.
+/// * If an interrupt-driven transfer keeps the bus busy.
+fn lock_bus(bus: u8) -> Result<BusGuard, Error> {
+    let claim = Claim::blocking(bus)?;
+
+    Ok(BusGuard {
+        bus: BUSES[bus as usize].lock(),
+        _claim: claim,
+    })
+}
+
+impl BlockDevice for Drive {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        u64::from(self.block)
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        // The bus is released between commands so other drives on it can make progress.
+        let blocks = (u32::try_from(block)?..).step_by(MAX_TRANSFER_BLOCKS);
+        for (block, chunk) in blocks.zip(buffer.chunks_mut(DMA_BUFFER_SIZE)) {
+            read(self.bus, self.disk, block, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&self, block: u64, data: &[u8]) -> Result<(), Error> {
+        check_range(self, block, data.len())?;
+
+        let blocks = (u32::try_from(block)?..).step_by(MAX_TRANSFER_BLOCKS);
+        for (block, chunk) in blocks.zip(data.chunks(DMA_BUFFER_SIZE)) {
+            write(self.bus, self.disk, block, chunk)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks_async<'a>(
+        &'a self,
+        block: u64,
+        data: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a>> {
+        Box::pin(self.write(block, data))
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        flush(self.bus, self.disk)
+    }
+}
+
+/// Represents an ATAPI drive, such as a CD-ROM drive.
+///
+/// # Fields
+///
+/// * `bus` - The bus of the drive.
+/// * `disk` - The disk of the drive.
+///
+/// * `block` - The block count of the medium.
+#[derive(Debug, Clone)]
+pub struct AtapiDrive {
+    pub bus: u8,
+    pub disk: u8,
+
+    block: u32,
+}
+
+impl AtapiDrive {
+    /// Opens an ATAPI drive.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - The bus of the drive.
+    /// * `disk` - The disk of the drive.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>` - The drive, if it exists and holds a medium.
+    pub fn open(bus: u8, disk: u8) -> Option<Self> {
+        let mut bus_lock = lock_bus(bus).ok()?;
+
+        let Ok(DeviceType::Atapi) = bus_lock.identify_drive(disk) else {
+            return None;
+        };
+
+        let mut packet = [0; 12];
+        packet[0] = SCSI_READ_CAPACITY;
+
+        let mut capacity = [0; 8];
+        if let Err(why) = bus_lock.packet(disk, &packet, &mut capacity) {
+            println!("[WARN]: ATAPI (Bus: {bus}, Disk: {disk}) has no medium: {why}");
+            return None;
+        }
+
+        // The capacity holds the last block and the block size, big-endian.
+        let last = u32::from_be_bytes(capacity[0..4].try_into().ok()?);
+        let block_size = u32::from_be_bytes(capacity[4..8].try_into().ok()?);
+        if block_size as usize != ATAPI_BLOCK_SIZE {
+            println!("[WARN]: ATAPI (Bus: {bus}, Disk: {disk}) has {block_size}-byte blocks");
+            return None;
+        }
+
+        Some(Self {
+            bus,
+            disk,
+            block: last.checked_add(1)?,
+        })
+    }
+
+    /// Gets the block count of the medium.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The block count of the medium.
+    #[must_use]
+    pub const fn block_count(&self) -> u32 {
+        self.block
+    }
+}
+
+/// Lists the ATAPI drives holding a medium.
+///
+/// # Returns
+///
+/// * `Vec<AtapiDrive>` - The drives.
+#[must_use]
+pub fn list_atapi_drives() -> Vec<AtapiDrive> {
+    let mut drives = Vec::new();
+    for bus in 0..2 {
+        for disk in 0..2 {
+            if let Some(drive) = AtapiDrive::open(bus, disk) {
+                drives.push(drive);
+            }
+        }
+    }
+
+    drives
+}
+
+impl BlockDevice for AtapiDrive {
+    fn block_size(&self) -> usize {
+        ATAPI_BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        u64::from(self.block)
+    }
+
+    fn read_only(&self) -> bool {
+        true
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        let blocks = (u32::try_from(block)?..).step_by(MAX_ATAPI_TRANSFER_BLOCKS);
+        for (block, chunk) in
+            blocks.zip(buffer.chunks_mut(MAX_ATAPI_TRANSFER_BLOCKS * ATAPI_BLOCK_SIZE))
+        {
+            let count = u16::try_from(chunk.len() / ATAPI_BLOCK_SIZE)?;
+
+            // The block and count are big-endian.
+            let mut packet = [0; 12];
+            packet[0] = SCSI_READ;
+            packet[2..6].copy_from_slice(&block.to_be_bytes());
+            packet[7..9].copy_from_slice(&count.to_be_bytes());
+
+            let read = lock_bus(self.bus)?.packet(self.disk, &packet, chunk)?;
+            if read != chunk.len() {
+                return Err(Error::ATA(format!(
+                    "ATAPI read returned {read} of {length} bytes!",
+                    length = chunk.len()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&self, _block: u64, _data: &[u8]) -> Result<(), Error> {
+        Err(Error::ATA("ATAPI drives are read only!".into()))
+    }
 }
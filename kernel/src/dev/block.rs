@@ -0,0 +1,212 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::errors::Error;
+
+lazy_static! {
+    /// The registered block devices, by name.
+    static ref DEVICES: Mutex<Vec<(String, Arc<dyn BlockDevice>)>> = Mutex::new(Vec::new());
+}
+
+/// A device storing data in fixed-size blocks.
+///
+/// # Notes
+///
+/// * Buffers passed to `read_blocks` and `write_blocks` must be a multiple of the block size long.
+pub trait BlockDevice: Send + Sync {
+    /// Gets the size of a block.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The size of a block, in bytes.
+    fn block_size(&self) -> usize;
+
+    /// Gets the number of blocks.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The number of blocks.
+    fn block_count(&self) -> u64;
+
+    /// Checks if the device is read only.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the device is read only, in which case [`BlockDevice::write_blocks`] fails.
+    fn read_only(&self) -> bool {
+        false
+    }
+
+    /// Reads consecutive blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first block to read.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the blocks are out of range.
+    /// * If the buffer isn't a multiple of the block size long.
+    /// * If the device fails to read.
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error>;
+
+    /// Writes consecutive blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first block to write.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the blocks are out of range.
+    /// * If the data isn't a multiple of the block size long.
+    /// * If the device is read only.
+    /// * If the device fails to write.
+    fn write_blocks(&self, block: u64, data: &[u8]) -> Result<(), Error>;
+
+    /// Writes consecutive blocks, yielding to other tasks while the device is busy.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first block to write.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Pin<Box<dyn Future<Output = Result<(), Error>> + 'a>>` - The write, failing like [`BlockDevice::write_blocks`].
+    ///
+    /// # Notes
+    ///
+    /// * Devices without interrupt-driven transfers write synchronously.
+    fn write_blocks_async<'a>(
+        &'a self,
+        block: u64,
+        data: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<(), Error>> + 'a>> {
+        Box::pin(core::future::ready(self.write_blocks(block, data)))
+    }
+
+    /// Writes any data cached by the device to its storage.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to flush.
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Checks that a transfer fits a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+/// * `block` - The first block of the transfer.
+/// * `length` - The length of the transfer, in bytes.
+///
+/// # Returns
+///
+/// * `Result<u64, Error>` - The number of blocks in the transfer.
+///
+/// # Errors
+///
+/// * If the length isn't a multiple of the block size.
+/// * If the transfer goes past the last block.
+pub fn check_range(device: &dyn BlockDevice, block: u64, length: usize) -> Result<u64, Error> {
+    let block_size = device.block_size();
+    if !length.is_multiple_of(block_size) {
+        return Err(Error::Internal(format!(
+            "Transfer of {length} bytes isn't a multiple of the block size ({block_size})!"
+        )));
+    }
+
+    let count = u64::try_from(length / block_size)?;
+    if block
+        .checked_add(count)
+        .is_none_or(|end| end > device.block_count())
+    {
+        return Err(Error::Internal(format!(
+            "Blocks {block}..{end} are out of range!",
+            end = block.saturating_add(count)
+        )));
+    }
+
+    Ok(count)
+}
+
+/// Registers a block device.
+///
+/// # Arguments
+///
+/// * `name` - The name of the device, such as `ata0`.
+/// * `device` - The device.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If a device with the same name is already registered.
+pub fn register(name: &str, device: Arc<dyn BlockDevice>) -> Result<(), Error> {
+    let mut devices = DEVICES.lock();
+
+    if devices.iter().any(|(existing, _)| existing == name) {
+        return Err(Error::Internal(format!(
+            "Block device '{name}' is already registered!"
+        )));
+    }
+
+    devices.push((name.into(), device));
+
+    Ok(())
+}
+
+/// Gets a registered block device.
+///
+/// # Arguments
+///
+/// * `name` - The name of the device.
+///
+/// # Returns
+///
+/// * `Option<Arc<dyn BlockDevice>>` - The device, if it's registered.
+#[must_use]
+pub fn get(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    DEVICES
+        .lock()
+        .iter()
+        .find(|(existing, _)| existing == name)
+        .map(|(_, device)| device.clone())
+}
+
+/// Lists the registered block devices, in registration order.
+///
+/// # Returns
+///
+/// * `Vec<(String, Arc<dyn BlockDevice>)>` - The names of the devices and the devices.
+#[must_use]
+pub fn devices() -> Vec<(String, Arc<dyn BlockDevice>)> {
+    DEVICES.lock().clone()
+}
@@ -0,0 +1,484 @@
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::allocator::HEAP_SIZE;
+use crate::dev::block::{check_range, BlockDevice};
+use crate::errors::Error;
+use crate::println;
+use crate::sys::task::timer::Interval;
+
+/// The share of the heap used by the block cache.
+///
+/// # Notes
+///
+/// * This is 1/8 of the heap, so 128 KiB or 256 sectors with the default heap.
+const CACHE_SIZE: usize = HEAP_SIZE / 8;
+
+/// The interval between periodic flushes of dirty blocks.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+lazy_static! {
+    /// The block cache shared by all cached devices.
+    static ref CACHE: Mutex<Cache> = Mutex::new(Cache::new(CACHE_SIZE));
+}
+
+/// A cached block.
+///
+/// # Fields
+///
+/// * `data` - The contents of the block.
+/// * `dirty` - Whether or not the block was written since it was last stored.
+/// * `last_used` - The access stamp of the block, used for LRU eviction.
+/// * `last_written` - The access stamp of the last write, telling if the block changed while being written back.
+struct Entry {
+    data: Vec<u8>,
+    dirty: bool,
+    last_used: u64,
+    last_written: u64,
+}
+
+/// Consecutive dirty blocks of a device, written back in a single transfer.
+///
+/// # Fields
+///
+/// * `id` - The identifier of the device in the cache.
+/// * `device` - The device.
+/// * `start` - The first block.
+/// * `data` - The contents of the blocks.
+/// * `stamps` - The write stamp of each block when the run was gathered.
+struct Run {
+    id: usize,
+    device: Arc<dyn BlockDevice>,
+    start: u64,
+    data: Vec<u8>,
+    stamps: Vec<u64>,
+}
+
+/// A write-back cache of blocks, keyed by device and block.
+///
+/// # Fields
+///
+/// * `devices` - The cached devices, indexed by their identifier.
+/// * `entries` - The cached blocks.
+/// * `recency` - The keys of the cached blocks, by access stamp.
+/// * `clock` - The next access stamp.
+/// * `size` - The number of bytes cached.
+/// * `capacity` - The maximum number of bytes cached.
+struct Cache {
+    devices: Vec<Arc<dyn BlockDevice>>,
+    entries: BTreeMap<(usize, u64), Entry>,
+    recency: BTreeMap<u64, (usize, u64)>,
+    clock: u64,
+    size: usize,
+    capacity: usize,
+}
+
+impl Cache {
+    /// Creates a new, empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of bytes cached.
+    const fn new(capacity: usize) -> Self {
+        Self {
+            devices: Vec::new(),
+            entries: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            size: 0,
+            capacity,
+        }
+    }
+
+    /// Marks a cached block as the most recently used one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The device and block.
+    fn touch(&mut self, key: (usize, u64)) {
+        let stamp = self.clock;
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(&key) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = stamp;
+            self.recency.insert(stamp, key);
+        }
+    }
+
+    /// Gets a cached block.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The device and block.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&mut Entry>` - The block, if it's cached.
+    fn get(&mut self, key: (usize, u64)) -> Option<&mut Entry> {
+        self.touch(key);
+
+        self.entries.get_mut(&key)
+    }
+
+    /// Caches a block, evicting the least recently used clean blocks to make room.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The device and block.
+    /// * `data` - The contents of the block.
+    /// * `dirty` - Whether or not the block must be written back.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the block is cached, which fails if only dirty blocks are left to evict.
+    ///
+    /// # Notes
+    ///
+    /// * Dirty blocks are never evicted, since the cache is locked, and they are only clean once [`write_back`] stores them.
+    fn insert(&mut self, key: (usize, u64), data: &[u8], dirty: bool) -> bool {
+        let stamp = self.clock;
+        if let Some(entry) = self.get(key) {
+            entry.data.copy_from_slice(data);
+            if dirty {
+                entry.dirty = true;
+                entry.last_written = stamp;
+            }
+
+            return true;
+        }
+
+        let mut victims = Vec::new();
+        let mut freed = 0;
+        for (&stamp, victim) in &self.recency {
+            if self.size - freed + data.len() <= self.capacity {
+                break;
+            }
+
+            if let Some(entry) = self.entries.get(victim).filter(|entry| !entry.dirty) {
+                freed += entry.data.len();
+                victims.push(stamp);
+            }
+        }
+
+        if self.size - freed + data.len() > self.capacity {
+            return false;
+        }
+
+        for stamp in victims {
+            if let Some(victim) = self.recency.remove(&stamp) {
+                if let Some(entry) = self.entries.remove(&victim) {
+                    self.size -= entry.data.len();
+                }
+            }
+        }
+
+        let stamp = self.clock;
+        self.clock += 1;
+
+        self.size += data.len();
+        self.recency.insert(stamp, key);
+        self.entries.insert(
+            key,
+            Entry {
+                data: data.to_vec(),
+                dirty,
+                last_used: stamp,
+                last_written: stamp,
+            },
+        );
+
+        true
+    }
+
+    /// Gathers the dirty blocks of a device, or of every device, into runs of consecutive blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device, or `None` for every device.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Run>` - The runs, which are still dirty until [`Cache::mark_clean`].
+    fn dirty_runs(&self, device: Option<usize>) -> Vec<Run> {
+        let mut runs: Vec<Run> = Vec::new();
+
+        for (&(id, block), entry) in &self.entries {
+            if !entry.dirty || device.is_some_and(|device| device != id) {
+                continue;
+            }
+
+            // Extend the current run if the block directly follows it.
+            if let Some(run) = runs.last_mut() {
+                if run.id == id && run.start + run.stamps.len() as u64 == block {
+                    run.data.extend_from_slice(&entry.data);
+                    run.stamps.push(entry.last_written);
+
+                    continue;
+                }
+            }
+
+            runs.push(Run {
+                id,
+                device: self.devices[id].clone(),
+                start: block,
+                data: entry.data.clone(),
+                stamps: vec![entry.last_written],
+            });
+        }
+
+        runs
+    }
+
+    /// Marks the blocks of a run as clean, once it's written back.
+    ///
+    /// # Arguments
+    ///
+    /// * `run` - The run.
+    ///
+    /// # Notes
+    ///
+    /// * Blocks written again since the run was gathered stay dirty.
+    fn mark_clean(&mut self, run: &Run) {
+        for (block, &stamp) in (run.start..).zip(&run.stamps) {
+            if let Some(entry) = self.entries.get_mut(&(run.id, block)) {
+                if entry.last_written == stamp {
+                    entry.dirty = false;
+                }
+            }
+        }
+    }
+}
+
+/// Writes back the dirty blocks of a device, or of every device.
+///
+/// # Arguments
+///
+/// * `device` - The device, or `None` for every device.
+///
+/// # Returns
+///
+/// * `Result<usize, Error>` - The number of blocks written back.
+///
+/// # Errors
+///
+/// * If a device fails to write.
+///
+/// # Notes
+///
+/// * Blocks stay cached, and are only clean once their run is written.
+/// * Every run is tried, and the first failure is returned.
+/// * The cache isn't locked while writing, so other tasks can use it.
+fn write_back(device: Option<usize>) -> Result<usize, Error> {
+    let runs = CACHE.lock().dirty_runs(device);
+
+    let mut written = 0;
+    let mut failure = None;
+    for run in runs {
+        // A failing device doesn't keep the other runs from being written back.
+        if let Err(why) = run.device.write_blocks(run.start, &run.data) {
+            failure.get_or_insert(why);
+            continue;
+        }
+
+        CACHE.lock().mark_clean(&run);
+        written += run.stamps.len();
+    }
+
+    failure.map_or(Ok(written), Err)
+}
+
+/// Writes back the dirty blocks of every device, yielding to other tasks while the devices are busy.
+///
+/// # Returns
+///
+/// * `Result<usize, Error>` - The number of blocks written back.
+///
+/// # Errors
+///
+/// * If a device fails to write.
+///
+/// # Notes
+///
+/// * This is [`write_back`] through [`BlockDevice::write_blocks_async`].
+async fn write_back_async() -> Result<usize, Error> {
+    let runs = CACHE.lock().dirty_runs(None);
+
+    let mut written = 0;
+    let mut failure = None;
+    for run in runs {
+        if let Err(why) = run.device.write_blocks_async(run.start, &run.data).await {
+            failure.get_or_insert(why);
+            continue;
+        }
+
+        CACHE.lock().mark_clean(&run);
+        written += run.stamps.len();
+    }
+
+    failure.map_or(Ok(written), Err)
+}
+
+/// A block device whose blocks go through the block cache.
+///
+/// # Fields
+///
+/// * `id` - The identifier of the device in the cache.
+/// * `device` - The underlying device.
+///
+/// # Notes
+///
+/// * Writes are only stored on the device when the device is flushed, or [`sync`] or [`flush_periodically`] runs.
+/// * Writes go straight to the device when the cache is full of dirty blocks.
+pub struct CachedDevice {
+    id: usize,
+    device: Arc<dyn BlockDevice>,
+}
+
+impl CachedDevice {
+    /// Puts a block device behind the block cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The cached device.
+    #[must_use]
+    pub fn new(device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = CACHE.lock();
+
+        let id = cache.devices.len();
+        cache.devices.push(device.clone());
+
+        Self { id, device }
+    }
+}
+
+impl BlockDevice for CachedDevice {
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        self.device.block_count()
+    }
+
+    fn read_only(&self) -> bool {
+        self.device.read_only()
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        let block_size = self.block_size();
+        let count = buffer.len() / block_size;
+
+        let mut index = 0;
+        while index < count {
+            let mut cache = CACHE.lock();
+            if let Some(entry) = cache.get((self.id, block + index as u64)) {
+                buffer[index * block_size..(index + 1) * block_size].copy_from_slice(&entry.data);
+                index += 1;
+
+                continue;
+            }
+
+            // Read each run of missing blocks in a single transfer, without holding the cache.
+            let first = index;
+            while index < count && !cache.entries.contains_key(&(self.id, block + index as u64)) {
+                index += 1;
+            }
+            drop(cache);
+
+            let range = first * block_size..index * block_size;
+            self.device
+                .read_blocks(block + first as u64, &mut buffer[range.clone()])?;
+
+            // Blocks written while the device was reading are newer than what it read.
+            let mut cache = CACHE.lock();
+            for (offset, data) in (first..).zip(buffer[range].chunks_exact_mut(block_size)) {
+                let key = (self.id, block + offset as u64);
+                match cache.get(key) {
+                    Some(entry) => data.copy_from_slice(&entry.data),
+                    None => {
+                        cache.insert(key, data, false);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&self, block: u64, data: &[u8]) -> Result<(), Error> {
+        check_range(self, block, data.len())?;
+        // The blocks could never be written back, so they mustn't be cached.
+        if self.read_only() {
+            return Err(Error::Internal("Device is read only!".into()));
+        }
+
+        for (block, data) in (block..).zip(data.chunks_exact(self.block_size())) {
+            if CACHE.lock().insert((self.id, block), data, true) {
+                continue;
+            }
+
+            // The cache is full of dirty blocks, so the block is written through.
+            self.device.write_blocks(block, data)?;
+            if let Some(entry) = CACHE.lock().get((self.id, block)) {
+                entry.data.copy_from_slice(data);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        write_back(Some(self.id))?;
+
+        self.device.flush()
+    }
+}
+
+/// Writes every dirty block back to its device.
+///
+/// # Returns
+///
+/// * `Result<usize, Error>` - The number of blocks written back.
+///
+/// # Errors
+///
+/// * If a device fails to write or flush.
+pub fn sync() -> Result<usize, Error> {
+    let written = write_back(None)?;
+
+    let devices = CACHE.lock().devices.clone();
+    devices.iter().try_for_each(|device| device.flush())?;
+
+    Ok(written)
+}
+
+/// Writes dirty blocks back to their devices every few seconds.
+///
+/// # Notes
+///
+/// * This never returns, and is meant to be spawned on the executor.
+/// * Other tasks keep running while the devices write, but the write caches of the devices themselves are only flushed by [`sync`].
+pub async fn flush_periodically() {
+    let mut interval = Interval::new(FLUSH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(why) = write_back_async().await {
+            println!("[WARN]: Failed to flush the block cache: {why}");
+        }
+    }
+}
@@ -1,9 +1,24 @@
 use crate::println;
 
 pub mod ata;
+pub mod block;
+pub mod cache;
+pub mod partition;
+pub mod pci;
+pub mod virtio;
+pub mod virtio_blk;
 
 /// Initializes the device drivers.
 pub fn init() {
+    println!("[INFO]: Enumerating the PCI bus...");
+    pci::init();
+
     println!("[INFO]: Initializing the ATA driver...");
     ata::init();
+
+    println!("[INFO]: Initializing the virtio-blk driver...");
+    virtio_blk::init();
+
+    println!("[INFO]: Reading the partition tables...");
+    partition::init();
 }
@@ -0,0 +1,640 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+use crate::dev::block::{self, check_range, BlockDevice};
+use crate::errors::Error;
+use crate::println;
+
+/// The offset of the partition entries in an MBR.
+const MBR_ENTRIES_OFFSET: usize = 446;
+/// The size of an MBR partition entry, in bytes.
+const MBR_ENTRY_SIZE: usize = 16;
+/// The MBR partition type of an unused entry.
+const MBR_EMPTY: u8 = 0x00;
+/// The MBR partition types of extended partitions (CHS, LBA and Linux).
+const MBR_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+/// The MBR partition type protecting a GPT.
+const MBR_GPT_PROTECTIVE: u8 = 0xEE;
+/// The maximum number of logical partitions followed in an extended partition.
+const MAX_LOGICAL_PARTITIONS: usize = 128;
+
+/// The signature of a GPT header.
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// The minimum size of a GPT header, in bytes.
+const GPT_HEADER_SIZE: usize = 92;
+/// The minimum size of a GPT partition entry, in bytes.
+const GPT_ENTRY_SIZE: usize = 128;
+/// The largest partition entry array that is read, enough for 1024 entries of 128 bytes.
+const GPT_MAX_ENTRIES_SIZE: usize = 128 * 1024;
+
+/// The type of a partition.
+///
+/// # Variants
+///
+/// * `Mbr` - The system ID of an MBR partition.
+/// * `Gpt` - The type GUID of a GPT partition, as stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    Mbr(u8),
+    Gpt([u8; 16]),
+}
+
+/// A partition of a block device, exposed as a block device of its own.
+///
+/// # Fields
+///
+/// * `device` - The device holding the partition.
+/// * `start` - The first block of the partition on the device.
+/// * `count` - The number of blocks in the partition.
+/// * `kind` - The type of the partition.
+pub struct Partition {
+    device: Arc<dyn BlockDevice>,
+    start: u64,
+    count: u64,
+    kind: PartitionType,
+}
+
+impl Partition {
+    /// Creates a new partition.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device holding the partition.
+    /// * `start` - The first block of the partition on the device.
+    /// * `count` - The number of blocks in the partition.
+    /// * `kind` - The type of the partition.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The new partition.
+    ///
+    /// # Errors
+    ///
+    /// * If the partition is empty or goes past the end of the device.
+    pub fn new(
+        device: Arc<dyn BlockDevice>,
+        start: u64,
+        count: u64,
+        kind: PartitionType,
+    ) -> Result<Self, Error> {
+        if count == 0
+            || start
+                .checked_add(count)
+                .is_none_or(|end| end > device.block_count())
+        {
+            return Err(Error::Internal(format!(
+                "Partition at block {start} with {count} blocks doesn't fit the device!"
+            )));
+        }
+
+        Ok(Self {
+            device,
+            start,
+            count,
+            kind,
+        })
+    }
+
+    /// Gets the first block of the partition on its device.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The first block.
+    #[must_use]
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// Gets the type of the partition.
+    ///
+    /// # Returns
+    ///
+    /// * `PartitionType` - The type.
+    #[must_use]
+    pub const fn kind(&self) -> PartitionType {
+        self.kind
+    }
+}
+
+impl BlockDevice for Partition {
+    fn block_size(&self) -> usize {
+        self.device.block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        self.count
+    }
+
+    fn read_only(&self) -> bool {
+        self.device.read_only()
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        self.device.read_blocks(self.start + block, buffer)
+    }
+
+    fn write_blocks(&self, block: u64, data: &[u8]) -> Result<(), Error> {
+        check_range(self, block, data.len())?;
+
+        self.device.write_blocks(self.start + block, data)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        self.device.flush()
+    }
+}
+
+/// Reads the partition table of a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+///
+/// # Returns
+///
+/// * `Result<Vec<(u32, Partition)>, Error>` - The partitions and their numbers, empty if the device isn't partitioned.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+/// * If the device has a GPT whose primary and backup copies are both corrupt.
+///
+/// # Notes
+///
+/// * MBR partitions are numbered like on Linux: primary partitions are 1 to 4, and logical partitions start at 5.
+/// * Devices whose blocks aren't 512 bytes long, such as optical drives, are never partitioned.
+pub fn scan(device: &Arc<dyn BlockDevice>) -> Result<Vec<(u32, Partition)>, Error> {
+    // Partition tables address 512-byte sectors, so optical media are never partitioned here.
+    if device.block_size() != 512 {
+        return Ok(Vec::new());
+    }
+
+    let mbr = read_block(device.as_ref(), 0)?;
+    if mbr.len() < 512 || mbr[510..512] != [0x55, 0xAA] {
+        return Ok(Vec::new());
+    }
+
+    let entries: Vec<MbrEntry> = (0..4)
+        .map(|i| MbrEntry::parse(&mbr[MBR_ENTRIES_OFFSET + i * MBR_ENTRY_SIZE..]))
+        .collect::<Result<_, _>>()?;
+
+    // A volume without a partition table has a boot sector here, whose entries are garbage.
+    if entries
+        .iter()
+        .any(|entry| !entry.is_plausible(device.block_count()))
+    {
+        return Ok(Vec::new());
+    }
+
+    if entries.iter().any(|entry| entry.kind == MBR_GPT_PROTECTIVE) {
+        return scan_gpt(device);
+    }
+
+    scan_mbr(device, &entries)
+}
+
+/// An MBR partition entry.
+///
+/// # Fields
+///
+/// * `status` - The boot indicator, `0x80` for bootable partitions.
+/// * `kind` - The system ID.
+/// * `start` - The first block, relative to the table holding the entry.
+/// * `count` - The number of blocks.
+#[derive(Debug, Clone, Copy)]
+struct MbrEntry {
+    status: u8,
+    kind: u8,
+    start: u32,
+    count: u32,
+}
+
+impl MbrEntry {
+    /// Decodes an MBR partition entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The decoded entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry is truncated.
+    fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            status: bytes[0],
+            kind: bytes[4],
+            start: u32::from_le_bytes(bytes[8..12].try_into()?),
+            count: u32::from_le_bytes(bytes[12..16].try_into()?),
+        })
+    }
+
+    /// Checks if the entry could be part of a partition table.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_count` - The number of blocks on the device.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the entry is unused or describes a partition inside the device.
+    fn is_plausible(&self, block_count: u64) -> bool {
+        if self.kind == MBR_EMPTY {
+            return true;
+        }
+
+        // A protective entry may claim more blocks than the disk has.
+        (self.status == 0x00 || self.status == 0x80)
+            && self.start != 0
+            && (self.kind == MBR_GPT_PROTECTIVE
+                || u64::from(self.start) + u64::from(self.count) <= block_count)
+    }
+}
+
+/// Reads the partitions of an MBR.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+/// * `entries` - The primary partition entries.
+///
+/// # Returns
+///
+/// * `Result<Vec<(u32, Partition)>, Error>` - The partitions and their numbers.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+fn scan_mbr(
+    device: &Arc<dyn BlockDevice>,
+    entries: &[MbrEntry],
+) -> Result<Vec<(u32, Partition)>, Error> {
+    let mut partitions = Vec::new();
+    let mut logical_number = 5;
+
+    for (number, entry) in (1..).zip(entries) {
+        if entry.kind == MBR_EMPTY || entry.count == 0 {
+            continue;
+        }
+
+        if MBR_EXTENDED.contains(&entry.kind) {
+            for logical in scan_extended(device, u64::from(entry.start))? {
+                partitions.push((logical_number, logical));
+                logical_number += 1;
+            }
+
+            continue;
+        }
+
+        match Partition::new(
+            device.clone(),
+            u64::from(entry.start),
+            u64::from(entry.count),
+            PartitionType::Mbr(entry.kind),
+        ) {
+            Ok(partition) => partitions.push((number, partition)),
+            Err(why) => println!("[WARN]: Skipping partition {number}: {why}"),
+        }
+    }
+
+    Ok(partitions)
+}
+
+/// Reads the logical partitions of an extended partition.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+/// * `extended_start` - The first block of the extended partition.
+///
+/// # Returns
+///
+/// * `Result<Vec<Partition>, Error>` - The logical partitions, in order.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+///
+/// # Notes
+///
+/// * Each extended boot record holds a logical partition relative to itself, and a link to the next record relative to the extended partition.
+fn scan_extended(
+    device: &Arc<dyn BlockDevice>,
+    extended_start: u64,
+) -> Result<Vec<Partition>, Error> {
+    let mut partitions = Vec::new();
+    let mut ebr_block = extended_start;
+
+    // Corrupt tables may link back to an earlier record, so the chain is bounded.
+    for _ in 0..MAX_LOGICAL_PARTITIONS {
+        let ebr = read_block(device.as_ref(), ebr_block)?;
+        if ebr.len() < 512 || ebr[510..512] != [0x55, 0xAA] {
+            println!("[WARN]: Extended boot record at block {ebr_block} is invalid");
+            break;
+        }
+
+        let logical = MbrEntry::parse(&ebr[MBR_ENTRIES_OFFSET..])?;
+        let next = MbrEntry::parse(&ebr[MBR_ENTRIES_OFFSET + MBR_ENTRY_SIZE..])?;
+
+        if logical.kind != MBR_EMPTY && logical.count != 0 {
+            match Partition::new(
+                device.clone(),
+                ebr_block + u64::from(logical.start),
+                u64::from(logical.count),
+                PartitionType::Mbr(logical.kind),
+            ) {
+                Ok(partition) => partitions.push(partition),
+                Err(why) => println!("[WARN]: Skipping logical partition: {why}"),
+            }
+        }
+
+        if next.kind == MBR_EMPTY || next.start == 0 {
+            break;
+        }
+        ebr_block = extended_start + u64::from(next.start);
+    }
+
+    Ok(partitions)
+}
+
+/// A GPT header.
+///
+/// # Fields
+///
+/// * `entries_block` - The first block of the partition entries.
+/// * `entry_count` - The number of partition entries.
+/// * `entry_size` - The size of a partition entry, in bytes.
+/// * `entries_crc32` - The CRC32 of the partition entries.
+#[derive(Debug, Clone, Copy)]
+struct GptHeader {
+    entries_block: u64,
+    entry_count: u32,
+    entry_size: u32,
+    entries_crc32: u32,
+}
+
+impl GptHeader {
+    /// Reads and checks a GPT header.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device.
+    /// * `block` - The block holding the header.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The header.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the signature, size, location or CRC32 of the header is wrong.
+    fn read(device: &dyn BlockDevice, block: u64) -> Result<Self, Error> {
+        let data = read_block(device, block)?;
+
+        if data.get(0..8) != Some(GPT_SIGNATURE.as_slice()) {
+            return Err(Error::Internal(format!(
+                "No GPT header signature at block {block}!"
+            )));
+        }
+
+        let size = u32::from_le_bytes(data[12..16].try_into()?) as usize;
+        if size < GPT_HEADER_SIZE || size > data.len() {
+            return Err(Error::Internal(format!("Invalid GPT header size {size}!")));
+        }
+
+        // The CRC32 is calculated with its own field zeroed.
+        let stored_crc32 = u32::from_le_bytes(data[16..20].try_into()?);
+        let mut header = data[..size].to_vec();
+        header[16..20].fill(0);
+        if crc32(&header) != stored_crc32 {
+            return Err(Error::Internal(format!(
+                "GPT header at block {block} has a bad CRC32!"
+            )));
+        }
+
+        if u64::from_le_bytes(data[24..32].try_into()?) != block {
+            return Err(Error::Internal(format!(
+                "GPT header at block {block} belongs somewhere else!"
+            )));
+        }
+
+        let header = Self {
+            entries_block: u64::from_le_bytes(data[72..80].try_into()?),
+            entry_count: u32::from_le_bytes(data[80..84].try_into()?),
+            entry_size: u32::from_le_bytes(data[84..88].try_into()?),
+            entries_crc32: u32::from_le_bytes(data[88..92].try_into()?),
+        };
+
+        if (header.entry_size as usize) < GPT_ENTRY_SIZE || !header.entry_size.is_power_of_two() {
+            return Err(Error::Internal(format!(
+                "Invalid GPT entry size {size}!",
+                size = header.entry_size
+            )));
+        }
+
+        Ok(header)
+    }
+
+    /// Reads and checks the partition entries of a GPT header.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, Error>` - The raw partition entries.
+    ///
+    /// # Errors
+    ///
+    /// * If the entries are larger than [`GPT_MAX_ENTRIES_SIZE`].
+    /// * If the device fails to read.
+    /// * If the entries have a bad CRC32.
+    fn read_entries(&self, device: &dyn BlockDevice) -> Result<Vec<u8>, Error> {
+        // The header is untrusted, so its entries mustn't exhaust the heap.
+        let length = (self.entry_count as usize)
+            .checked_mul(self.entry_size as usize)
+            .filter(|&length| length <= GPT_MAX_ENTRIES_SIZE)
+            .ok_or_else(|| {
+                Error::Internal(format!(
+                    "GPT has too many partition entries ({count} of {size} bytes)!",
+                    count = self.entry_count,
+                    size = self.entry_size
+                ))
+            })?;
+        let block_size = device.block_size();
+
+        let mut data = vec![0; length.next_multiple_of(block_size)];
+        device.read_blocks(self.entries_block, &mut data)?;
+        data.truncate(length);
+
+        if crc32(&data) != self.entries_crc32 {
+            return Err(Error::Internal(
+                "GPT partition entries have a bad CRC32!".into(),
+            ));
+        }
+
+        Ok(data)
+    }
+}
+
+/// Reads the partitions of a GPT.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+///
+/// # Returns
+///
+/// * `Result<Vec<(u32, Partition)>, Error>` - The partitions and their numbers.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+/// * If the primary and backup copies of the GPT are both corrupt.
+///
+/// # Notes
+///
+/// * The backup copy at the end of the device is used if the primary one is corrupt.
+fn scan_gpt(device: &Arc<dyn BlockDevice>) -> Result<Vec<(u32, Partition)>, Error> {
+    let primary = GptHeader::read(device.as_ref(), 1)
+        .and_then(|header| Ok((header, header.read_entries(device.as_ref())?)));
+
+    let (header, entries) = match primary {
+        Ok(primary) => primary,
+        Err(why) => {
+            println!("[WARN]: {why} Trying the backup GPT...");
+
+            let last_block = device.block_count().saturating_sub(1);
+            let header = GptHeader::read(device.as_ref(), last_block)?;
+
+            (header, header.read_entries(device.as_ref())?)
+        }
+    };
+
+    let mut partitions = Vec::new();
+    for (number, entry) in (1..).zip(entries.chunks_exact(header.entry_size as usize)) {
+        let kind: [u8; 16] = entry[0..16].try_into()?;
+        if kind == [0; 16] {
+            continue;
+        }
+
+        // The last block is inclusive.
+        let first = u64::from_le_bytes(entry[32..40].try_into()?);
+        let last = u64::from_le_bytes(entry[40..48].try_into()?);
+        let count = last.checked_add(1).and_then(|end| end.checked_sub(first));
+
+        match count
+            .ok_or_else(|| Error::Internal(format!("Partition {number} ends before it starts!")))
+            .and_then(|count| {
+                Partition::new(device.clone(), first, count, PartitionType::Gpt(kind))
+            }) {
+            Ok(partition) => partitions.push((number, partition)),
+            Err(why) => println!("[WARN]: Skipping partition {number}: {why}"),
+        }
+    }
+
+    Ok(partitions)
+}
+
+/// Reads a single block.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+/// * `block` - The block.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The contents of the block.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+fn read_block(device: &dyn BlockDevice, block: u64) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; device.block_size()];
+    device.read_blocks(block, &mut data)?;
+
+    Ok(data)
+}
+
+/// The lookup table of the CRC32 used by GPT (IEEE 802.3, reflected).
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xEDB8_8320
+            };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+};
+
+/// Calculates the CRC32 of some data.
+///
+/// # Arguments
+///
+/// * `data` - The data.
+///
+/// # Returns
+///
+/// * `u32` - The CRC32.
+#[must_use]
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Discovers the partitions of every registered block device.
+///
+/// # Notes
+///
+/// * Partitions are registered as block devices named after their device and number, such as `ata0p1`.
+pub fn init() {
+    for (name, device) in block::devices() {
+        let partitions = match scan(&device) {
+            Ok(partitions) => partitions,
+            Err(why) => {
+                println!("[WARN]: Failed to read the partition table of {name}: {why}");
+                continue;
+            }
+        };
+
+        for (number, partition) in partitions {
+            let partition_name: String = format!("{name}p{number}");
+            println!(
+                "[INFO]: => {partition_name} ({count} blocks at block {start})",
+                count = partition.block_count(),
+                start = partition.start()
+            );
+
+            if let Err(why) = block::register(&partition_name, Arc::new(partition)) {
+                println!("[WARN]: {why}");
+            }
+        }
+    }
+}
@@ -0,0 +1,667 @@
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt;
+use lazy_static::lazy_static;
+use spin::Mutex;
+use x86_64::instructions::port::Port;
+use x86_64::{PhysAddr, VirtAddr};
+
+use crate::errors::Error;
+use crate::mem::map_mmio;
+use crate::println;
+
+/// The configuration address and data ports.
+static CONFIG_PORTS: Mutex<(Port<u32>, Port<u32>)> =
+    Mutex::new((Port::new(0xCF8), Port::new(0xCFC)));
+
+/// The memory-mapped configuration space, once it's known.
+static ECAM: Mutex<Option<Ecam>> = Mutex::new(None);
+
+lazy_static! {
+    /// The devices found on the PCI bus, and the drivers that can bind to them.
+    static ref REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+        devices: Vec::new(),
+        drivers: Vec::new(),
+    });
+}
+
+/// The value read from the vendor ID of a missing device.
+const NO_VENDOR: u16 = 0xFFFF;
+/// The number of base address registers of a general device.
+const DEVICE_BARS: usize = 6;
+/// The number of base address registers of a PCI-to-PCI bridge.
+const BRIDGE_BARS: usize = 2;
+/// The size of the configuration space of a bus in the ECAM, in bytes.
+const ECAM_BUS_SIZE: u64 = 1 << 20;
+
+/// A function of a device on the PCI bus.
+///
+/// # Fields
+///
+/// * `bus` - The bus of the device.
+/// * `slot` - The slot of the device on the bus.
+/// * `function` - The function of the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Device {
+    pub bus: u8,
+    pub slot: u8,
+    pub function: u8,
+}
+
+impl Device {
+    /// Reads a register of the configuration space.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the register, rounded down to a multiple of 4.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The value of the register.
+    #[must_use]
+    pub fn read(&self, offset: u8) -> u32 {
+        if let Some(address) = self.ecam_address(offset) {
+            return unsafe { core::ptr::read_volatile(address.as_ptr::<u32>()) };
+        }
+
+        let mut ports = CONFIG_PORTS.lock();
+
+        unsafe {
+            ports.0.write(self.address(offset));
+            ports.1.read()
+        }
+    }
+
+    /// Writes a register of the configuration space.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the register, rounded down to a multiple of 4.
+    /// * `value` - The value to write.
+    pub fn write(&self, offset: u8, value: u32) {
+        if let Some(address) = self.ecam_address(offset) {
+            unsafe { core::ptr::write_volatile(address.as_mut_ptr::<u32>(), value) };
+
+            return;
+        }
+
+        let mut ports = CONFIG_PORTS.lock();
+
+        unsafe {
+            ports.0.write(self.address(offset));
+            ports.1.write(value);
+        }
+    }
+
+    /// Gets the vendor ID of the device.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - The vendor ID, `0xFFFF` if there's no device.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn vendor_id(&self) -> u16 {
+        self.read(0x00) as u16
+    }
+
+    /// Gets the class of the device.
+    ///
+    /// # Returns
+    ///
+    /// * `(u8, u8, u8)` - The class code, subclass and programming interface.
+    #[must_use]
+    pub fn class(&self) -> (u8, u8, u8) {
+        let [_, prog_if, subclass, class] = self.read(0x08).to_le_bytes();
+
+        (class, subclass, prog_if)
+    }
+
+    /// Gets a base address register of the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the register, from 0 to 5.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The raw value of the register.
+    #[must_use]
+    pub fn bar(&self, index: u8) -> u32 {
+        self.read(0x10 + index * 4)
+    }
+
+    /// Lets the device access memory on its own, for DMA.
+    pub fn enable_bus_mastering(&self) {
+        let command = self.read(0x04);
+
+        // Only the command half of the register is written, the status half is write-one-to-clear.
+        self.write(0x04, (command & 0xFFFF) | 1 << 2);
+    }
+
+    /// Checks if the device has more than one function.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the device is multi-function.
+    fn is_multifunction(&self) -> bool {
+        self.read(0x0C) & (1 << 23) != 0
+    }
+
+    /// Gets the layout of the configuration space.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The header type, without the multi-function bit.
+    fn header_type(&self) -> u8 {
+        self.read(0x0C).to_le_bytes()[2] & 0x7F
+    }
+
+    /// Gets the configuration address of a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the register.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The address to write to the configuration address port.
+    fn address(&self, offset: u8) -> u32 {
+        1 << 31
+            | u32::from(self.bus) << 16
+            | u32::from(self.slot) << 11
+            | u32::from(self.function) << 8
+            | u32::from(offset & 0xFC)
+    }
+
+    /// Gets the memory-mapped address of a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the register.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<VirtAddr>` - The address, if the bus is in the ECAM.
+    fn ecam_address(&self, offset: u8) -> Option<VirtAddr> {
+        let ecam = (*ECAM.lock())?;
+        if !(ecam.start_bus..=ecam.end_bus).contains(&self.bus) {
+            return None;
+        }
+
+        Some(
+            ecam.base
+                + (u64::from(self.bus - ecam.start_bus) << 20
+                    | u64::from(self.slot) << 15
+                    | u64::from(self.function) << 12
+                    | u64::from(offset & 0xFC)),
+        )
+    }
+}
+
+impl fmt::Display for Device {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{bus:02x}:{slot:02x}.{function}",
+            bus = self.bus,
+            slot = self.slot,
+            function = self.function
+        )
+    }
+}
+
+/// A memory-mapped configuration space.
+///
+/// # Fields
+///
+/// * `base` - The virtual address of the configuration space of the first bus.
+/// * `start_bus` - The first bus.
+/// * `end_bus` - The last bus.
+#[derive(Debug, Clone, Copy)]
+struct Ecam {
+    base: VirtAddr,
+    start_bus: u8,
+    end_bus: u8,
+}
+
+/// A decoded base address register.
+///
+/// # Variants
+///
+/// * `Io` - A range of I/O ports.
+/// * `Memory` - A range of memory-mapped registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    Io {
+        port: u16,
+        size: u32,
+    },
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+    },
+}
+
+/// What was found about a device while enumerating the PCI bus.
+///
+/// # Fields
+///
+/// * `device` - The address of the device.
+/// * `vendor_id` - The vendor ID.
+/// * `device_id` - The device ID.
+/// * `class` - The class code.
+/// * `subclass` - The subclass.
+/// * `prog_if` - The programming interface.
+/// * `revision` - The revision ID.
+/// * `bars` - The base address registers, `None` if unused or the upper half of a 64-bit register.
+/// * `interrupt_line` - The legacy IRQ the firmware routed the device to.
+/// * `interrupt_pin` - The interrupt pin of the device, `0` if it doesn't use one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub device: Device,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    pub bars: [Option<Bar>; DEVICE_BARS],
+    pub interrupt_line: u8,
+    pub interrupt_pin: u8,
+}
+
+impl DeviceInfo {
+    /// Reads the configuration space of a device.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - What was found about the device.
+    fn read(device: Device) -> Self {
+        let [vendor_low, vendor_high, device_low, device_high] = device.read(0x00).to_le_bytes();
+        let [revision, prog_if, subclass, class] = device.read(0x08).to_le_bytes();
+        let [interrupt_line, interrupt_pin, ..] = device.read(0x3C).to_le_bytes();
+
+        let bars = match device.header_type() {
+            0x00 => read_bars(device, DEVICE_BARS),
+            0x01 => read_bars(device, BRIDGE_BARS),
+            _ => [None; DEVICE_BARS],
+        };
+
+        Self {
+            device,
+            vendor_id: u16::from_le_bytes([vendor_low, vendor_high]),
+            device_id: u16::from_le_bytes([device_low, device_high]),
+            class,
+            subclass,
+            prog_if,
+            revision,
+            bars,
+            interrupt_line,
+            interrupt_pin,
+        }
+    }
+
+    /// Checks if the device is a PCI-to-PCI bridge.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<u8>` - The bus behind the bridge, if it's one.
+    fn secondary_bus(&self) -> Option<u8> {
+        ((self.class, self.subclass) == (0x06, 0x04) && self.device.header_type() == 0x01)
+            .then(|| self.device.read(0x18).to_le_bytes()[1])
+    }
+}
+
+/// How a driver recognizes the devices it handles.
+///
+/// # Variants
+///
+/// * `Id` - A specific vendor and device ID.
+/// * `Class` - Any device of a class and subclass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Match {
+    Id { vendor: u16, device: u16 },
+    Class { class: u8, subclass: u8 },
+}
+
+impl Match {
+    /// Checks if a device matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `info` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the device matches.
+    #[must_use]
+    pub fn matches(&self, info: &DeviceInfo) -> bool {
+        match *self {
+            Self::Id { vendor, device } => (info.vendor_id, info.device_id) == (vendor, device),
+            Self::Class { class, subclass } => (info.class, info.subclass) == (class, subclass),
+        }
+    }
+}
+
+/// A driver for PCI devices.
+///
+/// # Fields
+///
+/// * `name` - The name of the driver.
+/// * `matches` - The devices the driver handles.
+/// * `probe` - Sets up a matching device, failing if the driver can't handle it after all.
+pub struct Driver {
+    pub name: &'static str,
+    pub matches: &'static [Match],
+    pub probe: fn(&DeviceInfo) -> Result<(), Error>,
+}
+
+/// The devices found on the PCI bus, and the drivers that can bind to them.
+///
+/// # Fields
+///
+/// * `devices` - The devices, and the name of the driver bound to each.
+/// * `drivers` - The registered drivers.
+struct Registry {
+    devices: Vec<(DeviceInfo, Option<&'static str>)>,
+    drivers: Vec<&'static Driver>,
+}
+
+/// Decodes the base address registers of a device.
+///
+/// # Arguments
+///
+/// * `device` - The device.
+/// * `count` - The number of registers in its header.
+///
+/// # Returns
+///
+/// * `[Option<Bar>; DEVICE_BARS]` - The registers.
+///
+/// # Notes
+///
+/// * Sizes are found by writing all ones and reading back which bits stuck, with decoding disabled meanwhile.
+#[allow(clippy::cast_possible_truncation)]
+fn read_bars(device: Device, count: usize) -> [Option<Bar>; DEVICE_BARS] {
+    let mut bars = [None; DEVICE_BARS];
+
+    let command = device.read(0x04) & 0xFFFF;
+    device.write(0x04, command & !0b11);
+
+    // Reads a register and the mask of its writable bits.
+    let probe = |index: usize| {
+        let offset = 0x10 + index as u8 * 4;
+        let value = device.read(offset);
+        device.write(offset, 0xFFFF_FFFF);
+        let mask = device.read(offset);
+        device.write(offset, value);
+
+        (value, mask)
+    };
+
+    let mut index = 0;
+    while index < count {
+        let (value, mask) = probe(index);
+
+        if value & 1 == 1 {
+            // The upper half of I/O registers may not be implemented.
+            let size = (!(mask & 0xFFFF_FFFC | 0xFFFF_0000)).wrapping_add(1);
+            if mask & 0xFFFF_FFFC != 0 {
+                bars[index] = Some(Bar::Io {
+                    port: (value & 0xFFFC) as u16,
+                    size,
+                });
+            }
+        } else {
+            let wide = (value >> 1) & 0b11 == 0b10 && index + 1 < count;
+            let (high, high_mask) = if wide {
+                index += 1;
+                probe(index)
+            } else {
+                (0, 0xFFFF_FFFF)
+            };
+
+            let mask = u64::from(high_mask) << 32 | u64::from(mask & 0xFFFF_FFF0);
+            if mask & 0xFFFF_FFF0 != 0 || wide && high_mask != 0 {
+                bars[index - usize::from(wide)] = Some(Bar::Memory {
+                    address: u64::from(high) << 32 | u64::from(value & 0xFFFF_FFF0),
+                    size: (!mask).wrapping_add(1),
+                    prefetchable: value & (1 << 3) != 0,
+                });
+            }
+        }
+
+        index += 1;
+    }
+
+    device.write(0x04, command);
+
+    bars
+}
+
+/// Finds the devices on a bus, and on the buses behind its bridges.
+///
+/// # Arguments
+///
+/// * `bus` - The bus.
+/// * `devices` - The list to add the devices to.
+fn scan_bus(bus: u8, devices: &mut Vec<DeviceInfo>) {
+    for slot in 0..32 {
+        let device = Device {
+            bus,
+            slot,
+            function: 0,
+        };
+        if device.vendor_id() == NO_VENDOR {
+            continue;
+        }
+
+        let functions = if device.is_multifunction() { 8 } else { 1 };
+        for function in 0..functions {
+            let device = Device {
+                bus,
+                slot,
+                function,
+            };
+            if device.vendor_id() == NO_VENDOR {
+                continue;
+            }
+
+            let info = DeviceInfo::read(device);
+            devices.push(info);
+
+            // Buses are numbered depth first, so a bridge can't lead back to an earlier bus.
+            if let Some(secondary) = info.secondary_bus().filter(|secondary| *secondary > bus) {
+                scan_bus(secondary, devices);
+            }
+        }
+    }
+}
+
+/// Switches configuration space accesses to the memory-mapped configuration space.
+///
+/// # Arguments
+///
+/// * `base` - The physical address of the configuration space of bus 0, as given by the ACPI MCFG table.
+/// * `start_bus` - The first bus it covers.
+/// * `end_bus` - The last bus it covers.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the configuration space fails to map.
+///
+/// # Notes
+///
+/// * Buses outside the range keep using the configuration ports.
+pub fn enable_ecam(base: PhysAddr, start_bus: u8, end_bus: u8) -> Result<(), Error> {
+    if end_bus < start_bus {
+        return Err(Error::Internal(format!(
+            "Invalid ECAM bus range {start_bus}-{end_bus}!"
+        )));
+    }
+
+    let first = base + u64::from(start_bus) * ECAM_BUS_SIZE;
+    let size = u64::from(end_bus - start_bus + 1) * ECAM_BUS_SIZE;
+    let address = map_mmio(first, size)?;
+
+    ECAM.lock().replace(Ecam {
+        base: address,
+        start_bus,
+        end_bus,
+    });
+
+    Ok(())
+}
+
+/// Registers a driver, and probes it with the matching devices no other driver is bound to.
+///
+/// # Arguments
+///
+/// * `driver` - The driver.
+///
+/// # Returns
+///
+/// * `usize` - The number of devices the driver was bound to.
+///
+/// # Notes
+///
+/// * Drivers registered before [`init`] are probed once the bus is enumerated.
+pub fn register_driver(driver: &'static Driver) -> usize {
+    REGISTRY.lock().drivers.push(driver);
+
+    probe(driver)
+}
+
+/// Probes a driver with the matching devices no other driver is bound to.
+///
+/// # Arguments
+///
+/// * `driver` - The driver.
+///
+/// # Returns
+///
+/// * `usize` - The number of devices the driver was bound to.
+fn probe(driver: &'static Driver) -> usize {
+    let candidates: Vec<DeviceInfo> = REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .filter(|(info, bound)| bound.is_none() && driver.matches.iter().any(|id| id.matches(info)))
+        .map(|(info, _)| *info)
+        .collect();
+
+    // The registry isn't locked while probing, so drivers can look up other devices.
+    let mut bound = 0;
+    for info in candidates {
+        match (driver.probe)(&info) {
+            Ok(()) => {
+                let mut registry = REGISTRY.lock();
+                if let Some(entry) = registry
+                    .devices
+                    .iter_mut()
+                    .find(|(other, _)| other.device == info.device)
+                {
+                    entry.1 = Some(driver.name);
+                }
+                bound += 1;
+            }
+            Err(why) => println!(
+                "[WARN]: {name} failed to bind to {device}: {why}",
+                name = driver.name,
+                device = info.device
+            ),
+        }
+    }
+
+    bound
+}
+
+/// Gets the devices found on the PCI bus.
+///
+/// # Returns
+///
+/// * `Vec<(DeviceInfo, Option<&'static str>)>` - The devices, and the name of the driver bound to each.
+#[must_use]
+pub fn devices() -> Vec<(DeviceInfo, Option<&'static str>)> {
+    REGISTRY.lock().devices.clone()
+}
+
+/// Finds the first device of a class.
+///
+/// # Arguments
+///
+/// * `class` - The class code.
+/// * `subclass` - The subclass.
+///
+/// # Returns
+///
+/// * `Option<Device>` - The device, if one is present.
+#[must_use]
+pub fn find(class: u8, subclass: u8) -> Option<Device> {
+    let id = Match::Class { class, subclass };
+
+    REGISTRY
+        .lock()
+        .devices
+        .iter()
+        .find(|(info, _)| id.matches(info))
+        .map(|(info, _)| info.device)
+}
+
+/// Enumerates the PCI bus.
+///
+/// # Notes
+///
+/// * A multi-function host bridge means there's a host controller, and a root bus, per function.
+/// * Drivers that were registered early are probed afterwards.
+pub fn init() {
+    let mut devices = Vec::new();
+
+    let host = Device {
+        bus: 0,
+        slot: 0,
+        function: 0,
+    };
+    if host.is_multifunction() {
+        for function in 0..8 {
+            let host = Device { function, ..host };
+            if host.vendor_id() != NO_VENDOR {
+                scan_bus(function, &mut devices);
+            }
+        }
+    } else {
+        scan_bus(0, &mut devices);
+    }
+
+    for info in &devices {
+        println!(
+            "[INFO]: => {device} {vendor:04x}:{id:04x} (Class: {class:02x}:{subclass:02x}:{prog_if:02x}, IRQ: {irq})",
+            device = info.device,
+            vendor = info.vendor_id,
+            id = info.device_id,
+            class = info.class,
+            subclass = info.subclass,
+            prog_if = info.prog_if,
+            irq = info.interrupt_line
+        );
+    }
+
+    let drivers = {
+        let mut registry = REGISTRY.lock();
+        registry.devices = devices.into_iter().map(|info| (info, None)).collect();
+
+        registry.drivers.clone()
+    };
+    for driver in drivers {
+        probe(driver);
+    }
+}
@@ -0,0 +1,627 @@
+use alloc::format;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use x86_64::instructions::port::Port;
+use x86_64::{PhysAddr, VirtAddr};
+
+use crate::dev::pci::{Bar, DeviceInfo};
+use crate::errors::Error;
+use crate::mem::{alloc_contiguous_frames, map_mmio, phys_to_virt};
+
+/// The PCI vendor ID of virtio devices.
+pub const VENDOR_ID: u16 = 0x1AF4;
+
+/// The driver noticed the device.
+const STATUS_ACKNOWLEDGE: u8 = 1;
+/// The driver knows how to drive the device.
+const STATUS_DRIVER: u8 = 2;
+/// The driver is ready.
+const STATUS_DRIVER_OK: u8 = 4;
+/// The driver accepted the features.
+const STATUS_FEATURES_OK: u8 = 8;
+/// The driver gave up on the device.
+const STATUS_FAILED: u8 = 128;
+
+/// The device follows the virtio 1.0 specification, rather than the legacy interface.
+const FEATURE_VERSION_1: u64 = 1 << 32;
+
+/// The capability ID of vendor-specific capabilities, which virtio uses for its structures.
+const CAPABILITY_VENDOR: u8 = 0x09;
+/// The virtio capability of the common configuration.
+const CAPABILITY_COMMON: u8 = 1;
+/// The virtio capability of the notification area.
+const CAPABILITY_NOTIFY: u8 = 2;
+/// The virtio capability of the device-specific configuration.
+const CAPABILITY_DEVICE: u8 = 4;
+
+/// The legacy register of the device features.
+const LEGACY_DEVICE_FEATURES: u16 = 0x00;
+/// The legacy register of the driver features.
+const LEGACY_DRIVER_FEATURES: u16 = 0x04;
+/// The legacy register of the page frame of the selected queue.
+const LEGACY_QUEUE_ADDRESS: u16 = 0x08;
+/// The legacy register of the size of the selected queue.
+const LEGACY_QUEUE_SIZE: u16 = 0x0C;
+/// The legacy register selecting a queue.
+const LEGACY_QUEUE_SELECT: u16 = 0x0E;
+/// The legacy register notifying a queue.
+const LEGACY_QUEUE_NOTIFY: u16 = 0x10;
+/// The legacy register of the device status.
+const LEGACY_STATUS: u16 = 0x12;
+/// The legacy device-specific configuration, without MSI-X.
+const LEGACY_DEVICE_CONFIG: u16 = 0x14;
+/// The alignment of the used ring of legacy queues.
+const LEGACY_ALIGNMENT: usize = 4096;
+
+/// The largest queue negotiated with modern devices.
+const MAX_QUEUE_SIZE: u16 = 256;
+/// The physical address queues must end below, so legacy devices can address them by page frame.
+const QUEUE_LIMIT: u64 = 1 << 44;
+/// The size of a frame, in bytes.
+const FRAME_SIZE: usize = 4096;
+
+/// The descriptor continues in the `next` descriptor.
+const DESCRIPTOR_NEXT: u16 = 1;
+/// The device writes to the descriptor, rather than reading it.
+const DESCRIPTOR_WRITE: u16 = 2;
+/// The driver doesn't want to be interrupted when buffers are used.
+const AVAIL_NO_INTERRUPT: u16 = 1;
+
+/// The size of a descriptor, in bytes.
+const DESCRIPTOR_SIZE: usize = 16;
+
+/// How a virtio device is reached over PCI.
+///
+/// # Variants
+///
+/// * `Legacy` - The registers of a legacy or transitional device, in I/O space.
+/// * `Modern` - The memory-mapped structures of a virtio 1.0 device.
+#[derive(Debug, Clone, Copy)]
+pub enum Transport {
+    Legacy {
+        base: u16,
+    },
+    Modern {
+        common: VirtAddr,
+        notify: VirtAddr,
+        notify_multiplier: u32,
+        device: VirtAddr,
+    },
+}
+
+impl Transport {
+    /// Finds the transport of a device, preferring the modern one.
+    ///
+    /// # Arguments
+    ///
+    /// * `info` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The transport.
+    ///
+    /// # Errors
+    ///
+    /// * If the device has neither the modern capabilities, nor a legacy I/O BAR.
+    /// * If the modern structures fail to map.
+    pub fn new(info: &DeviceInfo) -> Result<Self, Error> {
+        if let Some(transport) = Self::modern(info)? {
+            return Ok(transport);
+        }
+
+        match info.bars[0] {
+            Some(Bar::Io { port, .. }) => Ok(Self::Legacy { base: port }),
+            _ => Err(Error::Virtio(format!(
+                "{device} has no usable transport!",
+                device = info.device
+            ))),
+        }
+    }
+
+    /// Finds the modern structures of a device through its capabilities.
+    ///
+    /// # Arguments
+    ///
+    /// * `info` - The device.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Self>, Error>` - The transport, if the device has the modern capabilities.
+    ///
+    /// # Errors
+    ///
+    /// * If a structure fails to map.
+    fn modern(info: &DeviceInfo) -> Result<Option<Self>, Error> {
+        let device = info.device;
+
+        // The capability list is only valid if the status register says so.
+        if device.read(0x04) & (1 << 20) == 0 {
+            return Ok(None);
+        }
+
+        let (mut common, mut notify, mut device_config) = (None, None, None);
+        let mut notify_multiplier = 0;
+
+        let mut pointer = device.read(0x34).to_le_bytes()[0] & 0xFC;
+        while pointer != 0 {
+            let [id, next, _, kind] = device.read(pointer).to_le_bytes();
+
+            if id == CAPABILITY_VENDOR {
+                let bar = device.read(pointer.wrapping_add(4)).to_le_bytes()[0];
+                let offset = device.read(pointer.wrapping_add(8));
+                let length = device.read(pointer.wrapping_add(12));
+
+                let address = match info.bars.get(usize::from(bar)).copied().flatten() {
+                    Some(Bar::Memory { address, .. }) => Some((
+                        PhysAddr::new(address + u64::from(offset)),
+                        u64::from(length),
+                    )),
+                    _ => None,
+                };
+
+                match (kind, address) {
+                    (CAPABILITY_COMMON, Some(address)) => common = Some(address),
+                    (CAPABILITY_NOTIFY, Some(address)) => {
+                        notify_multiplier = device.read(pointer.wrapping_add(16));
+                        notify = Some(address);
+                    }
+                    (CAPABILITY_DEVICE, Some(address)) => device_config = Some(address),
+                    _ => {}
+                }
+            }
+
+            pointer = next & 0xFC;
+        }
+
+        let (Some(common), Some(notify), Some(device_config)) = (common, notify, device_config)
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self::Modern {
+            common: map_mmio(common.0, common.1)?,
+            notify: map_mmio(notify.0, notify.1)?,
+            notify_multiplier,
+            device: map_mmio(device_config.0, device_config.1)?,
+        }))
+    }
+
+    /// Checks if the device uses the legacy interface.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the transport is legacy.
+    #[must_use]
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, Self::Legacy { .. })
+    }
+
+    /// Resets the device, and negotiates its features.
+    ///
+    /// # Arguments
+    ///
+    /// * `wanted` - The device-specific features the driver supports.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64, Error>` - The features both sides support.
+    ///
+    /// # Errors
+    ///
+    /// * If the device doesn't accept the features.
+    pub fn negotiate(&self, wanted: u64) -> Result<u64, Error> {
+        self.set_status(0);
+        self.set_status(STATUS_ACKNOWLEDGE);
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+
+        match *self {
+            Self::Legacy { base } => {
+                // Legacy devices only have 32 feature bits, and no handshake.
+                let offered =
+                    u64::from(unsafe { Port::<u32>::new(base + LEGACY_DEVICE_FEATURES).read() });
+                let features = offered & wanted & 0xFFFF_FFFF;
+                #[allow(clippy::cast_possible_truncation)]
+                unsafe {
+                    Port::<u32>::new(base + LEGACY_DRIVER_FEATURES).write(features as u32);
+                }
+
+                Ok(features)
+            }
+            Self::Modern { common, .. } => {
+                let mut offered = 0;
+                for select in 0..2 {
+                    write_mmio::<u32>(common, 0x00, select);
+                    offered |= u64::from(read_mmio::<u32>(common, 0x04)) << (select * 32);
+                }
+
+                let features = offered & (wanted | FEATURE_VERSION_1);
+                if features & FEATURE_VERSION_1 == 0 {
+                    self.set_status(STATUS_FAILED);
+                    return Err(Error::Virtio("Device doesn't support virtio 1.0!".into()));
+                }
+
+                for select in 0..2 {
+                    write_mmio::<u32>(common, 0x08, select);
+                    #[allow(clippy::cast_possible_truncation)]
+                    write_mmio::<u32>(common, 0x0C, (features >> (select * 32)) as u32);
+                }
+
+                self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+                if self.status() & STATUS_FEATURES_OK == 0 {
+                    self.set_status(STATUS_FAILED);
+                    return Err(Error::Virtio("Device rejected the features!".into()));
+                }
+
+                Ok(features)
+            }
+        }
+    }
+
+    /// Sets up a virtqueue.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the queue.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Virtqueue, Error>` - The queue.
+    ///
+    /// # Errors
+    ///
+    /// * If the device doesn't have the queue.
+    /// * If the queue memory fails to allocate.
+    pub fn setup_queue(&self, index: u16) -> Result<Virtqueue, Error> {
+        match *self {
+            Self::Legacy { base } => {
+                let size = unsafe {
+                    Port::<u16>::new(base + LEGACY_QUEUE_SELECT).write(index);
+                    Port::<u16>::new(base + LEGACY_QUEUE_SIZE).read()
+                };
+                if size == 0 {
+                    return Err(Error::Virtio(format!("Queue {index} doesn't exist!")));
+                }
+
+                let queue = Virtqueue::new(index, size, 0, true)?;
+                #[allow(clippy::cast_possible_truncation)]
+                unsafe {
+                    Port::<u32>::new(base + LEGACY_QUEUE_ADDRESS)
+                        .write((queue.descriptors.as_u64() >> 12) as u32);
+                }
+
+                Ok(queue)
+            }
+            Self::Modern {
+                common,
+                notify_multiplier,
+                ..
+            } => {
+                write_mmio::<u16>(common, 0x16, index);
+                let size = read_mmio::<u16>(common, 0x18).min(MAX_QUEUE_SIZE);
+                if size == 0 {
+                    return Err(Error::Virtio(format!("Queue {index} doesn't exist!")));
+                }
+
+                let notify_offset =
+                    u64::from(read_mmio::<u16>(common, 0x1E)) * u64::from(notify_multiplier);
+                let queue = Virtqueue::new(index, size, notify_offset, false)?;
+
+                write_mmio::<u16>(common, 0x18, size);
+                write_mmio::<u64>(common, 0x20, queue.descriptors.as_u64());
+                write_mmio::<u64>(common, 0x28, queue.driver_area().as_u64());
+                write_mmio::<u64>(common, 0x30, queue.device_area().as_u64());
+                write_mmio::<u16>(common, 0x1C, 1);
+
+                Ok(queue)
+            }
+        }
+    }
+
+    /// Resets the device, so it stops using its queues and their memory.
+    ///
+    /// # Notes
+    ///
+    /// * The device must be set up again, from [`Transport::negotiate`], before it's used.
+    pub fn reset(&self) {
+        self.set_status(0);
+
+        // Modern devices are done resetting once the status reads back as 0.
+        while self.status() != 0 {
+            spin_loop();
+        }
+    }
+
+    /// Tells the device the driver is ready.
+    pub fn driver_ok(&self) {
+        // Legacy devices have no feature handshake.
+        let features_ok = if self.is_legacy() {
+            0
+        } else {
+            STATUS_FEATURES_OK
+        };
+
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | features_ok | STATUS_DRIVER_OK);
+    }
+
+    /// Tells the device a queue has new buffers.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The queue.
+    pub fn notify(&self, queue: &Virtqueue) {
+        // The buffers must be visible to the device before it's notified.
+        fence(Ordering::SeqCst);
+
+        match *self {
+            Self::Legacy { base } => unsafe {
+                Port::<u16>::new(base + LEGACY_QUEUE_NOTIFY).write(queue.index);
+            },
+            Self::Modern { notify, .. } => {
+                write_mmio::<u16>(notify, queue.notify_offset, queue.index);
+            }
+        }
+    }
+
+    /// Reads the device-specific configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the field.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The field.
+    #[must_use]
+    pub fn read_config(&self, offset: u16) -> u32 {
+        match *self {
+            Self::Legacy { base } => unsafe {
+                Port::<u32>::new(base + LEGACY_DEVICE_CONFIG + offset).read()
+            },
+            Self::Modern { device, .. } => read_mmio::<u32>(device, u64::from(offset)),
+        }
+    }
+
+    /// Gets the device status.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The status.
+    fn status(&self) -> u8 {
+        match *self {
+            Self::Legacy { base } => unsafe { Port::<u8>::new(base + LEGACY_STATUS).read() },
+            Self::Modern { common, .. } => read_mmio::<u8>(common, 0x14),
+        }
+    }
+
+    /// Sets the device status, `0` resetting the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The status.
+    fn set_status(&self, status: u8) {
+        match *self {
+            Self::Legacy { base } => unsafe {
+                Port::<u8>::new(base + LEGACY_STATUS).write(status);
+            },
+            Self::Modern { common, .. } => write_mmio::<u8>(common, 0x14, status),
+        }
+    }
+}
+
+/// A split virtqueue, shared with the device.
+///
+/// # Fields
+///
+/// * `index` - The index of the queue.
+/// * `size` - The number of descriptors.
+/// * `notify_offset` - The offset of the notification register of modern queues.
+/// * `descriptors` - The physical address of the descriptor table.
+/// * `avail_offset` - The offset of the available ring from the descriptor table.
+/// * `used_offset` - The offset of the used ring from the descriptor table.
+/// * `free` - The free descriptors.
+/// * `last_used` - The index of the next used element to look at.
+///
+/// # Notes
+///
+/// * The queue memory is never freed, like the rest of the DMA memory.
+pub struct Virtqueue {
+    index: u16,
+    size: u16,
+    notify_offset: u64,
+    descriptors: PhysAddr,
+    avail_offset: usize,
+    used_offset: usize,
+    free: Vec<u16>,
+    last_used: u16,
+}
+
+impl Virtqueue {
+    /// Allocates a virtqueue.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the queue.
+    /// * `size` - The number of descriptors.
+    /// * `notify_offset` - The offset of the notification register of modern queues.
+    /// * `legacy` - Whether or not the used ring must be page aligned, as legacy devices expect.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The queue.
+    ///
+    /// # Errors
+    ///
+    /// * If the queue memory fails to allocate.
+    fn new(index: u16, size: u16, notify_offset: u64, legacy: bool) -> Result<Self, Error> {
+        let entries = usize::from(size);
+
+        // The descriptor table, then the available ring, then the used ring.
+        let avail_offset = entries * DESCRIPTOR_SIZE;
+        let avail_end = avail_offset + 6 + 2 * entries;
+        let used_offset = if legacy {
+            avail_end.next_multiple_of(LEGACY_ALIGNMENT)
+        } else {
+            avail_end.next_multiple_of(4)
+        };
+        let length = used_offset + 6 + 8 * entries;
+
+        let descriptors = alloc_contiguous_frames(length.div_ceil(FRAME_SIZE), QUEUE_LIMIT)?;
+
+        let queue = Self {
+            index,
+            size,
+            notify_offset,
+            descriptors,
+            avail_offset,
+            used_offset,
+            free: (0..size).rev().collect(),
+            last_used: 0,
+        };
+
+        // Buffers are polled for, so the device needn't interrupt.
+        write_mmio::<u16>(queue.virt(), queue.avail_offset as u64, AVAIL_NO_INTERRUPT);
+
+        Ok(queue)
+    }
+
+    /// Gets the physical address of the available ring.
+    ///
+    /// # Returns
+    ///
+    /// * `PhysAddr` - The address.
+    fn driver_area(&self) -> PhysAddr {
+        self.descriptors + self.avail_offset as u64
+    }
+
+    /// Gets the physical address of the used ring.
+    ///
+    /// # Returns
+    ///
+    /// * `PhysAddr` - The address.
+    fn device_area(&self) -> PhysAddr {
+        self.descriptors + self.used_offset as u64
+    }
+
+    /// Gets the virtual address of the queue memory.
+    ///
+    /// # Returns
+    ///
+    /// * `VirtAddr` - The address.
+    fn virt(&self) -> VirtAddr {
+        phys_to_virt(self.descriptors)
+    }
+
+    /// Makes a chain of buffers available to the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffers` - The physical address, length, and whether or not the device writes it, of each buffer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u16, Error>` - The head of the chain, which identifies it once it's used.
+    ///
+    /// # Errors
+    ///
+    /// * If there aren't enough free descriptors.
+    ///
+    /// # Notes
+    ///
+    /// * The device must be notified afterwards, see [`Transport::notify`].
+    pub fn submit(&mut self, buffers: &[(PhysAddr, u32, bool)]) -> Result<u16, Error> {
+        if buffers.is_empty() || buffers.len() > self.free.len() {
+            return Err(Error::Virtio("Not enough free descriptors!".into()));
+        }
+
+        let base = self.virt();
+        let chain: Vec<u16> = (0..buffers.len()).filter_map(|_| self.free.pop()).collect();
+
+        for (position, ((address, length, writable), descriptor)) in
+            buffers.iter().zip(&chain).enumerate()
+        {
+            let next = chain.get(position + 1);
+            let mut flags = if *writable { DESCRIPTOR_WRITE } else { 0 };
+            if next.is_some() {
+                flags |= DESCRIPTOR_NEXT;
+            }
+
+            let offset = u64::from(*descriptor) * DESCRIPTOR_SIZE as u64;
+            write_mmio::<u64>(base, offset, address.as_u64());
+            write_mmio::<u32>(base, offset + 8, *length);
+            write_mmio::<u16>(base, offset + 12, flags);
+            write_mmio::<u16>(base, offset + 14, next.copied().unwrap_or_default());
+        }
+
+        // Publish the head in the ring before the index, so the device never sees a stale entry.
+        let avail = self.avail_offset as u64;
+        let index = read_mmio::<u16>(base, avail + 2);
+        write_mmio::<u16>(base, avail + 4 + u64::from(index % self.size) * 2, chain[0]);
+        fence(Ordering::SeqCst);
+        write_mmio::<u16>(base, avail + 2, index.wrapping_add(1));
+
+        Ok(chain[0])
+    }
+
+    /// Takes the next chain the device is done with.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(u16, u32)>` - The head of the chain, and the number of bytes the device wrote, if one was used.
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let base = self.virt();
+        let used = self.used_offset as u64;
+
+        if read_mmio::<u16>(base, used + 2) == self.last_used {
+            return None;
+        }
+        // The element must be read after the index that published it.
+        fence(Ordering::SeqCst);
+
+        let element = used + 4 + u64::from(self.last_used % self.size) * 8;
+        #[allow(clippy::cast_possible_truncation)]
+        let head = read_mmio::<u32>(base, element) as u16;
+        let length = read_mmio::<u32>(base, element + 4);
+        self.last_used = self.last_used.wrapping_add(1);
+
+        // Return the descriptors of the chain to the free list.
+        let mut descriptor = head;
+        loop {
+            self.free.push(descriptor);
+
+            let offset = u64::from(descriptor) * DESCRIPTOR_SIZE as u64;
+            if read_mmio::<u16>(base, offset + 12) & DESCRIPTOR_NEXT == 0 {
+                break;
+            }
+            descriptor = read_mmio::<u16>(base, offset + 14);
+        }
+
+        Some((head, length))
+    }
+}
+
+/// Reads a memory-mapped register.
+///
+/// # Arguments
+///
+/// * `base` - The address of the structure.
+/// * `offset` - The offset of the register.
+///
+/// # Returns
+///
+/// * `T` - The value of the register.
+fn read_mmio<T: Copy>(base: VirtAddr, offset: u64) -> T {
+    unsafe { read_volatile((base + offset).as_ptr::<T>()) }
+}
+
+/// Writes a memory-mapped register.
+///
+/// # Arguments
+///
+/// * `base` - The address of the structure.
+/// * `offset` - The offset of the register.
+/// * `value` - The value to write.
+fn write_mmio<T: Copy>(base: VirtAddr, offset: u64, value: T) {
+    unsafe { write_volatile((base + offset).as_mut_ptr::<T>(), value) }
+}
@@ -0,0 +1,332 @@
+use alloc::format;
+use alloc::sync::Arc;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use spin::Mutex;
+use x86_64::PhysAddr;
+
+use crate::dev::block::{self, check_range, BlockDevice};
+use crate::dev::cache::CachedDevice;
+use crate::dev::pci::{self, DeviceInfo, Match};
+use crate::dev::virtio::{Transport, Virtqueue, VENDOR_ID};
+use crate::errors::Error;
+use crate::mem::{alloc_contiguous_frames, phys_to_virt};
+use crate::println;
+use crate::sys::time::clocksource::{read_tsc, tsc_frequency};
+
+/// The size of a sector, in bytes.
+const SECTOR_SIZE: usize = 512;
+/// The maximum number of sectors in a single request.
+const MAX_TRANSFER_SECTORS: usize = 128;
+/// The size of the DMA buffer, in bytes.
+const DMA_BUFFER_SIZE: usize = MAX_TRANSFER_SECTORS * SECTOR_SIZE;
+/// The size of a frame, in bytes.
+const FRAME_SIZE: usize = 4096;
+/// The physical address DMA memory must end below.
+const DMA_LIMIT: u64 = 1 << 44;
+/// How long a request may take, in seconds.
+const REQUEST_TIMEOUT: u64 = 5;
+
+/// The device is read only.
+const FEATURE_READ_ONLY: u64 = 1 << 5;
+/// The device has a write cache that can be flushed.
+const FEATURE_FLUSH: u64 = 1 << 9;
+
+/// Reads sectors.
+const REQUEST_IN: u32 = 0;
+/// Writes sectors.
+const REQUEST_OUT: u32 = 1;
+/// Flushes the write cache.
+const REQUEST_FLUSH: u32 = 4;
+
+/// The size of a request header, in bytes.
+const HEADER_SIZE: usize = 16;
+/// The status of a successful request.
+const STATUS_OK: u8 = 0;
+/// The status of a request the device doesn't support.
+const STATUS_UNSUPPORTED: u8 = 2;
+
+/// The number of virtio block devices found so far, used to name them.
+static DEVICE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The PCI driver of virtio block devices.
+static DRIVER: pci::Driver = pci::Driver {
+    name: "virtio-blk",
+    // Transitional and modern block devices.
+    matches: &[
+        Match::Id {
+            vendor: VENDOR_ID,
+            device: 0x1001,
+        },
+        Match::Id {
+            vendor: VENDOR_ID,
+            device: 0x1042,
+        },
+    ],
+    probe,
+};
+
+/// A virtio block device.
+///
+/// # Fields
+///
+/// * `transport` - How the device is reached.
+/// * `queue` - The request queue and its DMA memory.
+/// * `capacity` - The number of sectors.
+/// * `read_only` - Whether or not the device is read only.
+/// * `flush` - Whether or not the device has a write cache to flush.
+/// * `failed` - Whether or not the device was reset after a request timed out.
+///
+/// # Notes
+///
+/// * Requests are polled for, one at a time, so the device never interrupts.
+pub struct VirtioBlock {
+    transport: Transport,
+    queue: Mutex<Queue>,
+    capacity: u64,
+    read_only: bool,
+    flush: bool,
+    failed: AtomicBool,
+}
+
+/// The request queue of a virtio block device.
+///
+/// # Fields
+///
+/// * `virtqueue` - The virtqueue.
+/// * `request` - The physical address of the request header, followed by the status byte.
+/// * `buffer` - The physical address of the DMA buffer.
+struct Queue {
+    virtqueue: Virtqueue,
+    request: PhysAddr,
+    buffer: PhysAddr,
+}
+
+impl VirtioBlock {
+    /// Sets up a virtio block device.
+    ///
+    /// # Arguments
+    ///
+    /// * `info` - The PCI device.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The block device.
+    ///
+    /// # Errors
+    ///
+    /// * If the device has no usable transport.
+    /// * If the device rejects the features.
+    /// * If the request queue or DMA memory fails to allocate.
+    pub fn new(info: &DeviceInfo) -> Result<Self, Error> {
+        let transport = Transport::new(info)?;
+        info.device.enable_bus_mastering();
+
+        let features = transport.negotiate(FEATURE_READ_ONLY | FEATURE_FLUSH)?;
+        let virtqueue = transport.setup_queue(0)?;
+
+        let queue = Queue {
+            virtqueue,
+            request: alloc_contiguous_frames(1, DMA_LIMIT)?,
+            buffer: alloc_contiguous_frames(DMA_BUFFER_SIZE / FRAME_SIZE, DMA_LIMIT)?,
+        };
+
+        // The capacity is a 64-bit field, read in halves.
+        let capacity =
+            u64::from(transport.read_config(0)) | u64::from(transport.read_config(4)) << 32;
+
+        transport.driver_ok();
+
+        Ok(Self {
+            transport,
+            queue: Mutex::new(queue),
+            capacity,
+            read_only: features & FEATURE_READ_ONLY != 0,
+            flush: features & FEATURE_FLUSH != 0,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Sends a request, and waits for it to complete.
+    ///
+    /// # Arguments
+    ///
+    /// * `queue` - The locked request queue.
+    /// * `kind` - The kind of the request.
+    /// * `sector` - The first sector of the request.
+    /// * `length` - The number of bytes transferred through the DMA buffer.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device was reset after an earlier request timed out.
+    /// * If the device doesn't complete the request in time.
+    /// * If the device fails the request.
+    ///
+    /// # Notes
+    ///
+    /// * A request that times out resets the device, since it may still complete it later, and every later request fails.
+    #[allow(clippy::cast_possible_truncation)]
+    fn request(
+        &self,
+        queue: &mut Queue,
+        kind: u32,
+        sector: u64,
+        length: usize,
+    ) -> Result<(), Error> {
+        if self.failed.load(Ordering::Relaxed) {
+            return Err(Error::Virtio(
+                "Device was reset after a request timed out!".into(),
+            ));
+        }
+
+        let header = phys_to_virt(queue.request).as_mut_ptr::<u8>();
+        unsafe {
+            core::ptr::write_volatile(header.cast::<u32>(), kind);
+            core::ptr::write_volatile(header.add(4).cast::<u32>(), 0);
+            core::ptr::write_volatile(header.add(8).cast::<u64>(), sector);
+            core::ptr::write_volatile(header.add(HEADER_SIZE), 0xFF);
+        }
+
+        let status = (queue.request + HEADER_SIZE as u64, 1, true);
+        let header = (queue.request, HEADER_SIZE as u32, false);
+        let head = if length == 0 {
+            queue.virtqueue.submit(&[header, status])?
+        } else {
+            let data = (queue.buffer, length as u32, kind == REQUEST_IN);
+            queue.virtqueue.submit(&[header, data, status])?
+        };
+        self.transport.notify(&queue.virtqueue);
+
+        // The time-stamp counter keeps running with interrupts disabled.
+        let start = read_tsc();
+        loop {
+            match queue.virtqueue.pop_used() {
+                Some((used, _)) if used == head => break,
+                Some(_) => {}
+                None if read_tsc() - start > REQUEST_TIMEOUT * tsc_frequency() => {
+                    // The request buffers are reused, so the device mustn't complete the request later.
+                    self.transport.reset();
+                    self.failed.store(true, Ordering::Relaxed);
+
+                    return Err(Error::Virtio(
+                        "Request timed out, resetting the device!".into(),
+                    ));
+                }
+                None => spin_loop(),
+            }
+        }
+
+        let status = phys_to_virt(status.0).as_ptr::<u8>();
+        match unsafe { core::ptr::read_volatile(status) } {
+            STATUS_OK => Ok(()),
+            STATUS_UNSUPPORTED => Err(Error::Virtio("Request isn't supported!".into())),
+            status => Err(Error::Virtio(format!(
+                "Request failed at sector {sector} with status {status}!"
+            ))),
+        }
+    }
+}
+
+impl BlockDevice for VirtioBlock {
+    fn block_size(&self) -> usize {
+        SECTOR_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        self.capacity
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn read_blocks(&self, block: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(self, block, buffer.len())?;
+
+        let mut queue = self.queue.lock();
+        for (sector, chunk) in (block..)
+            .step_by(MAX_TRANSFER_SECTORS)
+            .zip(buffer.chunks_mut(DMA_BUFFER_SIZE))
+        {
+            self.request(&mut queue, REQUEST_IN, sector, chunk.len())?;
+
+            let data = phys_to_virt(queue.buffer).as_ptr::<u8>();
+            chunk.copy_from_slice(unsafe { core::slice::from_raw_parts(data, chunk.len()) });
+        }
+
+        Ok(())
+    }
+
+    fn write_blocks(&self, block: u64, data: &[u8]) -> Result<(), Error> {
+        check_range(self, block, data.len())?;
+        if self.read_only {
+            return Err(Error::Virtio("Device is read only!".into()));
+        }
+
+        let mut queue = self.queue.lock();
+        for (sector, chunk) in (block..)
+            .step_by(MAX_TRANSFER_SECTORS)
+            .zip(data.chunks(DMA_BUFFER_SIZE))
+        {
+            let buffer = phys_to_virt(queue.buffer).as_mut_ptr::<u8>();
+            unsafe { core::slice::from_raw_parts_mut(buffer, chunk.len()) }.copy_from_slice(chunk);
+
+            self.request(&mut queue, REQUEST_OUT, sector, chunk.len())?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        if !self.flush {
+            return Ok(());
+        }
+
+        self.request(&mut self.queue.lock(), REQUEST_FLUSH, 0, 0)
+    }
+}
+
+/// Sets up a virtio block device, and registers it.
+///
+/// # Arguments
+///
+/// * `info` - The PCI device.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the device fails to set up.
+/// * If the block device fails to register.
+fn probe(info: &DeviceInfo) -> Result<(), Error> {
+    let device = VirtioBlock::new(info)?;
+
+    let name = format!(
+        "virtio{index}",
+        index = DEVICE_COUNT.fetch_add(1, Ordering::Relaxed)
+    );
+    println!(
+        "[INFO]: => virtio-blk ({name}, Blocks: {blocks}{mode})",
+        blocks = device.block_count(),
+        mode = if device.read_only() {
+            ", read only"
+        } else {
+            ""
+        }
+    );
+
+    block::register(&name, Arc::new(CachedDevice::new(Arc::new(device))))
+}
+
+/// Initializes the virtio block driver.
+pub fn init() {
+    if pci::register_driver(&DRIVER) == 0 {
+        println!("[INFO]: => No virtio block devices");
+    }
+}
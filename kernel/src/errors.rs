@@ -18,6 +18,8 @@ use x86_64::structures::paging::Size4KiB;
 /// * `MemoryLayout` - A memory layout error.
 /// * `InvalidRegister` - An invalid register error.
 /// * `InvalidAddress` - An invalid address error.
+/// * `ACPI` - An ACPI table error.
+/// * `Virtio` - A virtio device error.
 /// * `Conversion` - A conversion error.
 /// * `Task` - A task error.
 /// * `FileSystem` - A file system error.
@@ -35,6 +37,10 @@ pub enum Error {
     InvalidRegister(String),
     #[error("ATA Error: {0}")]
     ATA(String),
+    #[error("ACPI Error: {0}")]
+    ACPI(String),
+    #[error("Virtio Error: {0}")]
+    Virtio(String),
     #[error("Conversion Error: {0}")]
     Conversion(String),
     #[error("Task Error: {0}")]
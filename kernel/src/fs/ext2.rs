@@ -0,0 +1,2523 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::convert::TryInto;
+use spin::Mutex;
+
+use crate::dev::block::BlockDevice;
+use crate::errors::Error;
+use crate::fs::vfs::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+use crate::sys::time::clock;
+
+/// The offset of the superblock, in bytes.
+const SUPERBLOCK_OFFSET: usize = 1024;
+/// The size of the superblock, in bytes.
+const SUPERBLOCK_SIZE: usize = 1024;
+/// The magic number of the superblock.
+const MAGIC: u16 = 0xEF53;
+/// The largest supported block size, as a shift of 1024.
+const MAX_LOG_BLOCK_SIZE: u32 = 6;
+/// The size of a block group descriptor, in bytes.
+const GROUP_DESCRIPTOR_SIZE: usize = 32;
+/// The most block groups mounted, keeping the descriptor table within 128 KiB of the heap.
+const MAX_GROUPS: u32 = 4096;
+
+/// The inode of the root directory.
+const ROOT_INODE: u32 = 2;
+/// The size of an inode in revision 0 file systems.
+const GOOD_OLD_INODE_SIZE: u16 = 128;
+/// The first non-reserved inode in revision 0 file systems.
+const GOOD_OLD_FIRST_INODE: u32 = 11;
+
+/// The number of block pointers in an inode.
+const BLOCK_POINTERS: usize = 15;
+/// The number of direct block pointers in an inode.
+const DIRECT_BLOCKS: usize = 12;
+/// The size of an inode's block pointers, which hold the target of fast symbolic links.
+const FAST_SYMLINK_SIZE: usize = BLOCK_POINTERS * 4;
+
+/// Directory entries store their file type.
+const INCOMPAT_FILETYPE: u32 = 0x0002;
+/// The incompatible features this driver understands.
+const SUPPORTED_INCOMPAT: u32 = INCOMPAT_FILETYPE;
+/// Superblock backups are only kept in some block groups.
+const RO_COMPAT_SPARSE_SUPER: u32 = 0x0001;
+/// Files may be larger than 4 GiB.
+const RO_COMPAT_LARGE_FILE: u32 = 0x0002;
+/// The read-only compatible features this driver can write with.
+const SUPPORTED_RO_COMPAT: u32 = RO_COMPAT_SPARSE_SUPER | RO_COMPAT_LARGE_FILE;
+
+/// The mask of the file type in an inode's mode.
+const MODE_TYPE_MASK: u16 = 0xF000;
+/// The file type of a directory.
+const MODE_DIRECTORY: u16 = 0x4000;
+/// The file type of a regular file.
+const MODE_FILE: u16 = 0x8000;
+/// The file type of a symbolic link.
+const MODE_SYMLINK: u16 = 0xA000;
+/// The mask of the permissions in an inode's mode, including the setuid, setgid and sticky bits.
+const MODE_PERMISSIONS_MASK: u16 = 0o7777;
+/// The owner write permission.
+const MODE_OWNER_WRITE: u16 = 0o200;
+/// The permissions of new files.
+const DEFAULT_FILE_PERMISSIONS: u16 = 0o644;
+/// The permissions of new directories.
+const DEFAULT_DIRECTORY_PERMISSIONS: u16 = 0o755;
+
+/// The flag of directories indexed by a hash tree, which must be cleared when they're modified.
+const FLAG_INDEX: u32 = 0x1000;
+
+/// The size of a directory entry without its name.
+const DIR_ENTRY_HEADER_SIZE: usize = 8;
+/// The maximum length of a name, in bytes.
+const MAX_NAME_LENGTH: usize = 255;
+/// The directory entry file type of a regular file.
+const FILE_TYPE_FILE: u8 = 1;
+/// The directory entry file type of a directory.
+const FILE_TYPE_DIRECTORY: u8 = 2;
+
+/// The superblock of an ext2 file system.
+///
+/// # Fields
+///
+/// * `inodes_count` - The number of inodes.
+/// * `blocks_count` - The number of blocks.
+/// * `free_blocks_count` - The number of free blocks.
+/// * `free_inodes_count` - The number of free inodes.
+/// * `first_data_block` - The block holding the superblock.
+/// * `log_block_size` - The block size, as a shift of 1024.
+/// * `blocks_per_group` - The number of blocks in a block group.
+/// * `inodes_per_group` - The number of inodes in a block group.
+/// * `revision` - The revision of the file system.
+/// * `first_inode` - The first non-reserved inode.
+/// * `inode_size` - The size of an inode, in bytes.
+/// * `feature_incompat` - The features the driver must support to mount the file system.
+/// * `feature_ro_compat` - The features the driver must support to write to the file system.
+/// * `raw` - The raw superblock, so unknown fields are preserved.
+#[derive(Debug, Clone)]
+pub struct Superblock {
+    pub inodes_count: u32,
+    pub blocks_count: u32,
+    pub free_blocks_count: u32,
+    pub free_inodes_count: u32,
+    pub first_data_block: u32,
+    pub log_block_size: u32,
+    pub blocks_per_group: u32,
+    pub inodes_per_group: u32,
+    pub revision: u32,
+    pub first_inode: u32,
+    pub inode_size: u16,
+    pub feature_incompat: u32,
+    pub feature_ro_compat: u32,
+    raw: Vec<u8>,
+}
+
+impl Superblock {
+    /// Parses and validates a superblock.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw superblock.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The superblock.
+    ///
+    /// # Errors
+    ///
+    /// * If the magic number is wrong.
+    /// * If the geometry of the file system is invalid.
+    /// * If the file system uses incompatible features.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let u16_at = |offset: usize| -> Result<u16, Error> {
+            Ok(u16::from_le_bytes(bytes[offset..offset + 2].try_into()?))
+        };
+        let u32_at = |offset: usize| -> Result<u32, Error> {
+            Ok(u32::from_le_bytes(bytes[offset..offset + 4].try_into()?))
+        };
+
+        if u16_at(56)? != MAGIC {
+            return Err(Error::FileSystem("Not an ext2 file system!".into()));
+        }
+
+        // Revision 0 has fixed inodes, and no feature flags.
+        let revision = u32_at(76)?;
+        let (first_inode, inode_size, feature_incompat, feature_ro_compat) = if revision == 0 {
+            (GOOD_OLD_FIRST_INODE, GOOD_OLD_INODE_SIZE, 0, 0)
+        } else {
+            (u32_at(84)?, u16_at(88)?, u32_at(96)?, u32_at(100)?)
+        };
+
+        let superblock = Self {
+            inodes_count: u32_at(0)?,
+            blocks_count: u32_at(4)?,
+            free_blocks_count: u32_at(12)?,
+            free_inodes_count: u32_at(16)?,
+            first_data_block: u32_at(20)?,
+            log_block_size: u32_at(24)?,
+            blocks_per_group: u32_at(32)?,
+            inodes_per_group: u32_at(40)?,
+            revision,
+            first_inode,
+            inode_size,
+            feature_incompat,
+            feature_ro_compat,
+            raw: bytes[..SUPERBLOCK_SIZE].to_vec(),
+        };
+        superblock.validate()?;
+
+        Ok(superblock)
+    }
+
+    /// Gets the size of a block.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The size of a block, in bytes.
+    #[must_use]
+    pub fn block_size(&self) -> usize {
+        1024 << self.log_block_size
+    }
+
+    /// Gets the number of block groups.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of block groups.
+    #[must_use]
+    pub fn group_count(&self) -> u32 {
+        (self.blocks_count - self.first_data_block).div_ceil(self.blocks_per_group)
+    }
+
+    /// Checks if the driver can write to the file system.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not every read-only compatible feature is supported.
+    #[must_use]
+    pub fn writable(&self) -> bool {
+        self.feature_ro_compat & !SUPPORTED_RO_COMPAT == 0
+    }
+
+    /// Validates the superblock.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the geometry of the file system is invalid.
+    /// * If the file system has more than [`MAX_GROUPS`] block groups.
+    /// * If the file system uses incompatible features.
+    fn validate(&self) -> Result<(), Error> {
+        if self.log_block_size > MAX_LOG_BLOCK_SIZE {
+            return Err(Error::FileSystem("Invalid ext2 block size!".into()));
+        }
+
+        let bits_per_block = self.block_size() as u32 * 8;
+        if self.blocks_per_group == 0
+            || self.blocks_per_group > bits_per_block
+            || self.inodes_per_group == 0
+            || self.inodes_per_group > bits_per_block
+            || self.first_data_block >= self.blocks_count
+        {
+            return Err(Error::FileSystem(
+                "Invalid ext2 block group geometry!".into(),
+            ));
+        }
+
+        if self.group_count() > MAX_GROUPS {
+            return Err(Error::FileSystem(format!(
+                "Too many ext2 block groups ({count})!",
+                count = self.group_count()
+            )));
+        }
+
+        if u64::from(self.group_count()) * u64::from(self.inodes_per_group)
+            < u64::from(self.inodes_count)
+        {
+            return Err(Error::FileSystem("Invalid ext2 inode count!".into()));
+        }
+
+        if !self.inode_size.is_power_of_two()
+            || self.inode_size < GOOD_OLD_INODE_SIZE
+            || usize::from(self.inode_size) > self.block_size()
+        {
+            return Err(Error::FileSystem(format!(
+                "Invalid ext2 inode size {size}!",
+                size = self.inode_size
+            )));
+        }
+
+        let unsupported = self.feature_incompat & !SUPPORTED_INCOMPAT;
+        if unsupported != 0 {
+            return Err(Error::FileSystem(format!(
+                "Unsupported ext2 features {unsupported:#x}!"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Encodes the superblock.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The raw superblock.
+    ///
+    /// # Notes
+    ///
+    /// * Only the free counts, the features and the write time can change.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut raw = self.raw.clone();
+
+        raw[12..16].copy_from_slice(&self.free_blocks_count.to_le_bytes());
+        raw[16..20].copy_from_slice(&self.free_inodes_count.to_le_bytes());
+        raw[48..52].copy_from_slice(&now().to_le_bytes());
+        if self.revision != 0 {
+            raw[100..104].copy_from_slice(&self.feature_ro_compat.to_le_bytes());
+        }
+
+        raw
+    }
+}
+
+/// The descriptor of a block group.
+///
+/// # Fields
+///
+/// * `block_bitmap` - The block holding the block usage bitmap.
+/// * `inode_bitmap` - The block holding the inode usage bitmap.
+/// * `inode_table` - The first block of the inode table.
+/// * `free_blocks_count` - The number of free blocks.
+/// * `free_inodes_count` - The number of free inodes.
+/// * `used_dirs_count` - The number of directories.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupDescriptor {
+    pub block_bitmap: u32,
+    pub inode_bitmap: u32,
+    pub inode_table: u32,
+    pub free_blocks_count: u16,
+    pub free_inodes_count: u16,
+    pub used_dirs_count: u16,
+}
+
+impl GroupDescriptor {
+    /// Parses a block group descriptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw descriptor.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The descriptor.
+    ///
+    /// # Errors
+    ///
+    /// * If the descriptor is truncated.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        Ok(Self {
+            block_bitmap: u32::from_le_bytes(bytes[0..4].try_into()?),
+            inode_bitmap: u32::from_le_bytes(bytes[4..8].try_into()?),
+            inode_table: u32::from_le_bytes(bytes[8..12].try_into()?),
+            free_blocks_count: u16::from_le_bytes(bytes[12..14].try_into()?),
+            free_inodes_count: u16::from_le_bytes(bytes[14..16].try_into()?),
+            used_dirs_count: u16::from_le_bytes(bytes[16..18].try_into()?),
+        })
+    }
+
+    /// Encodes the block group descriptor.
+    ///
+    /// # Returns
+    ///
+    /// * `[u8; GROUP_DESCRIPTOR_SIZE]` - The raw descriptor.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; GROUP_DESCRIPTOR_SIZE] {
+        let mut bytes = [0; GROUP_DESCRIPTOR_SIZE];
+
+        bytes[0..4].copy_from_slice(&self.block_bitmap.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.inode_bitmap.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.inode_table.to_le_bytes());
+        bytes[12..14].copy_from_slice(&self.free_blocks_count.to_le_bytes());
+        bytes[14..16].copy_from_slice(&self.free_inodes_count.to_le_bytes());
+        bytes[16..18].copy_from_slice(&self.used_dirs_count.to_le_bytes());
+
+        bytes
+    }
+}
+
+/// An inode as stored in the inode table.
+///
+/// # Fields
+///
+/// * `mode` - The file type and permissions.
+/// * `uid` - The owner.
+/// * `size` - The size, in bytes.
+/// * `atime` - The last access time, in seconds since the Unix epoch.
+/// * `ctime` - The last change time.
+/// * `mtime` - The last modification time.
+/// * `dtime` - The deletion time.
+/// * `gid` - The group.
+/// * `links_count` - The number of hard links.
+/// * `sectors` - The number of 512-byte sectors allocated, including indirect blocks.
+/// * `flags` - The inode flags.
+/// * `blocks` - The direct, indirect, doubly and triply indirect block pointers.
+/// * `file_acl` - The extended attribute block.
+/// * `raw` - The raw inode, so unknown fields are preserved.
+#[derive(Debug, Clone)]
+struct InodeData {
+    mode: u16,
+    uid: u16,
+    size: u64,
+    atime: u32,
+    ctime: u32,
+    mtime: u32,
+    dtime: u32,
+    gid: u16,
+    links_count: u16,
+    sectors: u32,
+    flags: u32,
+    blocks: [u32; BLOCK_POINTERS],
+    file_acl: u32,
+    raw: Vec<u8>,
+}
+
+impl InodeData {
+    /// Creates a new inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of an inode on disk.
+    /// * `mode` - The file type and permissions.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The inode.
+    fn new(size: u16, mode: u16) -> Self {
+        let now = now();
+
+        Self {
+            mode,
+            uid: 0,
+            size: 0,
+            atime: now,
+            ctime: now,
+            mtime: now,
+            dtime: 0,
+            gid: 0,
+            links_count: 1,
+            sectors: 0,
+            flags: 0,
+            blocks: [0; BLOCK_POINTERS],
+            file_acl: 0,
+            raw: vec![0; usize::from(size)],
+        }
+    }
+
+    /// Parses an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The inode.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is truncated.
+    fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let u16_at = |offset: usize| -> Result<u16, Error> {
+            Ok(u16::from_le_bytes(bytes[offset..offset + 2].try_into()?))
+        };
+        let u32_at = |offset: usize| -> Result<u32, Error> {
+            Ok(u32::from_le_bytes(bytes[offset..offset + 4].try_into()?))
+        };
+
+        let mode = u16_at(0)?;
+
+        // The upper half of the size is only used by regular files.
+        let mut size = u64::from(u32_at(4)?);
+        if mode & MODE_TYPE_MASK == MODE_FILE {
+            size |= u64::from(u32_at(108)?) << 32;
+        }
+
+        let mut blocks = [0; BLOCK_POINTERS];
+        for (index, block) in blocks.iter_mut().enumerate() {
+            *block = u32_at(40 + index * 4)?;
+        }
+
+        Ok(Self {
+            mode,
+            uid: u16_at(2)?,
+            size,
+            atime: u32_at(8)?,
+            ctime: u32_at(12)?,
+            mtime: u32_at(16)?,
+            dtime: u32_at(20)?,
+            gid: u16_at(24)?,
+            links_count: u16_at(26)?,
+            sectors: u32_at(28)?,
+            flags: u32_at(32)?,
+            blocks,
+            file_acl: u32_at(104)?,
+            raw: bytes.to_vec(),
+        })
+    }
+
+    /// Encodes the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<u8>` - The raw inode.
+    #[allow(clippy::cast_possible_truncation)]
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut raw = self.raw.clone();
+
+        raw[0..2].copy_from_slice(&self.mode.to_le_bytes());
+        raw[2..4].copy_from_slice(&self.uid.to_le_bytes());
+        raw[4..8].copy_from_slice(&(self.size as u32).to_le_bytes());
+        raw[8..12].copy_from_slice(&self.atime.to_le_bytes());
+        raw[12..16].copy_from_slice(&self.ctime.to_le_bytes());
+        raw[16..20].copy_from_slice(&self.mtime.to_le_bytes());
+        raw[20..24].copy_from_slice(&self.dtime.to_le_bytes());
+        raw[24..26].copy_from_slice(&self.gid.to_le_bytes());
+        raw[26..28].copy_from_slice(&self.links_count.to_le_bytes());
+        raw[28..32].copy_from_slice(&self.sectors.to_le_bytes());
+        raw[32..36].copy_from_slice(&self.flags.to_le_bytes());
+        for (index, block) in self.blocks.iter().enumerate() {
+            raw[40 + index * 4..44 + index * 4].copy_from_slice(&block.to_le_bytes());
+        }
+        raw[104..108].copy_from_slice(&self.file_acl.to_le_bytes());
+        if self.is_file() {
+            raw[108..112].copy_from_slice(&((self.size >> 32) as u32).to_le_bytes());
+        }
+
+        raw
+    }
+
+    /// Checks if the inode is a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the inode is a directory.
+    fn is_dir(&self) -> bool {
+        self.mode & MODE_TYPE_MASK == MODE_DIRECTORY
+    }
+
+    /// Checks if the inode is a regular file.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the inode is a regular file.
+    fn is_file(&self) -> bool {
+        self.mode & MODE_TYPE_MASK == MODE_FILE
+    }
+
+    /// Gets the kind of the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `InodeKind` - The kind of the inode.
+    ///
+    /// # Notes
+    ///
+    /// * Special files, such as devices, are reported as files, and are read only.
+    fn kind(&self) -> InodeKind {
+        match self.mode & MODE_TYPE_MASK {
+            MODE_DIRECTORY => InodeKind::Directory,
+            MODE_SYMLINK => InodeKind::Symlink,
+            _ => InodeKind::File,
+        }
+    }
+
+    /// Checks if the inode is a symbolic link stored in its block pointers.
+    ///
+    /// # Arguments
+    ///
+    /// * `block_size` - The block size of the file system.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the inode is a fast symbolic link.
+    ///
+    /// # Notes
+    ///
+    /// * Fast symbolic links have no blocks, except for an extended attribute block.
+    fn is_fast_symlink(&self, block_size: usize) -> bool {
+        let attribute_sectors = if self.file_acl == 0 {
+            0
+        } else {
+            block_size as u32 / 512
+        };
+
+        self.mode & MODE_TYPE_MASK == MODE_SYMLINK && self.sectors == attribute_sectors
+    }
+}
+
+/// An entry of a directory.
+///
+/// # Fields
+///
+/// * `inode` - The inode of the entry.
+/// * `name` - The name of the entry.
+#[derive(Debug, Clone)]
+struct DirectoryEntry {
+    inode: u32,
+    name: String,
+}
+
+/// A mounted ext2 file system.
+///
+/// # Fields
+///
+/// * `device` - The device holding the file system.
+/// * `superblock` - The superblock.
+/// * `groups` - The block group descriptors.
+/// * `read_only` - Whether or not the file system is mounted read only.
+///
+/// # Notes
+///
+/// * Inodes are addressed by number, so hard links share their data.
+/// * Symbolic links are listed as files, whose contents are their target.
+/// * Backup superblocks and group descriptors aren't updated, as they only matter to `e2fsck`.
+pub struct Ext2 {
+    device: Arc<dyn BlockDevice>,
+    superblock: Superblock,
+    groups: Vec<GroupDescriptor>,
+    read_only: bool,
+}
+
+impl Ext2 {
+    /// Gets the superblock.
+    ///
+    /// # Returns
+    ///
+    /// * `&Superblock` - The superblock.
+    #[must_use]
+    pub fn superblock(&self) -> &Superblock {
+        &self.superblock
+    }
+
+    /// Checks if the file system is mounted read only.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the file system is read only.
+    #[must_use]
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Writes any buffered changes to the device.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to write.
+    pub fn sync(&self) -> Result<(), Error> {
+        self.device.flush()
+    }
+
+    /// Gets the size of a block.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The size of a block, in bytes.
+    fn block_size(&self) -> usize {
+        self.superblock.block_size()
+    }
+
+    /// Gets the number of sectors counted for each allocated block.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of 512-byte sectors in a block.
+    fn sectors_per_block(&self) -> u32 {
+        self.block_size() as u32 / 512
+    }
+
+    /// Fails if the file system is read only.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system is read only.
+    fn check_writable(&self) -> Result<(), Error> {
+        if self.read_only {
+            return Err(Error::FileSystem("File system is read only!".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Reads a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, Error>` - The contents of the block.
+    ///
+    /// # Errors
+    ///
+    /// * If the block is out of range.
+    /// * If the device fails to read.
+    fn read_block(&self, block: u32) -> Result<Vec<u8>, Error> {
+        self.check_block(block)?;
+
+        let mut data = vec![0; self.block_size()];
+        read_bytes(&*self.device, block as usize * self.block_size(), &mut data)?;
+
+        Ok(data)
+    }
+
+    /// Writes a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block.
+    /// * `data` - The contents of the block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the block is out of range.
+    /// * If the device fails to write.
+    fn write_block(&self, block: u32, data: &[u8]) -> Result<(), Error> {
+        self.check_block(block)?;
+
+        write_bytes(&*self.device, block as usize * self.block_size(), data)
+    }
+
+    /// Checks if a block is part of the file system.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the block is out of range.
+    fn check_block(&self, block: u32) -> Result<(), Error> {
+        if block < self.superblock.first_data_block || block >= self.superblock.blocks_count {
+            return Err(Error::FileSystem(format!("Invalid block {block}!")));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the superblock.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to write.
+    fn write_superblock(&self) -> Result<(), Error> {
+        write_bytes(
+            &*self.device,
+            SUPERBLOCK_OFFSET,
+            &self.superblock.to_bytes(),
+        )
+    }
+
+    /// Writes the descriptor of a block group.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The block group.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to write.
+    fn write_group(&self, group: u32) -> Result<(), Error> {
+        // The descriptor table starts in the block after the superblock.
+        let table = (self.superblock.first_data_block as usize + 1) * self.block_size();
+
+        write_bytes(
+            &*self.device,
+            table + group as usize * GROUP_DESCRIPTOR_SIZE,
+            &self.groups[group as usize].to_bytes(),
+        )
+    }
+
+    /// Locates an inode in the inode table.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The offset of the inode on the device, in bytes.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is out of range.
+    fn inode_offset(&self, number: u32) -> Result<usize, Error> {
+        if number == 0 || number > self.superblock.inodes_count {
+            return Err(Error::FileSystem(format!("Invalid inode {number}!")));
+        }
+
+        let group = (number - 1) / self.superblock.inodes_per_group;
+        let index = (number - 1) % self.superblock.inodes_per_group;
+        let table = self.groups[group as usize].inode_table as usize * self.block_size();
+
+        Ok(table + index as usize * usize::from(self.superblock.inode_size))
+    }
+
+    /// Reads an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<InodeData, Error>` - The inode.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is out of range.
+    /// * If the device fails to read.
+    fn read_inode(&self, number: u32) -> Result<InodeData, Error> {
+        let mut raw = vec![0; usize::from(self.superblock.inode_size)];
+        read_bytes(&*self.device, self.inode_offset(number)?, &mut raw)?;
+
+        InodeData::parse(&raw)
+    }
+
+    /// Writes an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    /// * `inode` - The contents of the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is out of range.
+    /// * If the device fails to write.
+    fn write_inode(&self, number: u32, inode: &InodeData) -> Result<(), Error> {
+        write_bytes(&*self.device, self.inode_offset(number)?, &inode.to_bytes())
+    }
+
+    /// Gets the block group of an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The block group.
+    fn group_of(&self, number: u32) -> u32 {
+        (number - 1) / self.superblock.inodes_per_group
+    }
+
+    /// Allocates a bit in a block or inode bitmap.
+    ///
+    /// # Arguments
+    ///
+    /// * `bitmap` - The bitmap to allocate from.
+    /// * `goal` - The block group to try first.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(u32, u32), Error>` - The block group and the index of the bit in it.
+    ///
+    /// # Errors
+    ///
+    /// * If no bit is free.
+    /// * If the device fails to read or write.
+    fn allocate_bit(&mut self, bitmap: Bitmap, goal: u32) -> Result<(u32, u32), Error> {
+        let group_count = self.superblock.group_count();
+
+        for group in (goal..group_count).chain(0..goal) {
+            let descriptor = self.groups[group as usize];
+            let (block, free, bits) = match bitmap {
+                Bitmap::Blocks => (
+                    descriptor.block_bitmap,
+                    descriptor.free_blocks_count,
+                    // The last group may be shorter than the others.
+                    (self.superblock.blocks_count
+                        - self.superblock.first_data_block
+                        - group * self.superblock.blocks_per_group)
+                        .min(self.superblock.blocks_per_group),
+                ),
+                Bitmap::Inodes => (
+                    descriptor.inode_bitmap,
+                    descriptor.free_inodes_count,
+                    self.superblock.inodes_per_group,
+                ),
+            };
+            if free == 0 {
+                continue;
+            }
+
+            let mut data = self.read_block(block)?;
+            let Some(bit) = (0..bits).find(|bit| data[*bit as usize / 8] & (1 << (bit % 8)) == 0)
+            else {
+                continue;
+            };
+            data[bit as usize / 8] |= 1 << (bit % 8);
+            self.write_block(block, &data)?;
+
+            let descriptor = &mut self.groups[group as usize];
+            match bitmap {
+                Bitmap::Blocks => {
+                    descriptor.free_blocks_count -= 1;
+                    self.superblock.free_blocks_count =
+                        self.superblock.free_blocks_count.saturating_sub(1);
+                }
+                Bitmap::Inodes => {
+                    descriptor.free_inodes_count -= 1;
+                    self.superblock.free_inodes_count =
+                        self.superblock.free_inodes_count.saturating_sub(1);
+                }
+            }
+            self.write_group(group)?;
+            self.write_superblock()?;
+
+            return Ok((group, bit));
+        }
+
+        Err(Error::FileSystem(match bitmap {
+            Bitmap::Blocks => "No space left on the device!".into(),
+            Bitmap::Inodes => "No free inodes left on the device!".into(),
+        }))
+    }
+
+    /// Frees a bit in a block or inode bitmap.
+    ///
+    /// # Arguments
+    ///
+    /// * `bitmap` - The bitmap to free in.
+    /// * `group` - The block group.
+    /// * `bit` - The index of the bit in the block group.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the bit is already free.
+    /// * If the device fails to read or write.
+    fn free_bit(&mut self, bitmap: Bitmap, group: u32, bit: u32) -> Result<(), Error> {
+        let descriptor = self.groups[group as usize];
+        let block = match bitmap {
+            Bitmap::Blocks => descriptor.block_bitmap,
+            Bitmap::Inodes => descriptor.inode_bitmap,
+        };
+
+        let mut data = self.read_block(block)?;
+        if data[bit as usize / 8] & (1 << (bit % 8)) == 0 {
+            return Err(Error::FileSystem(format!(
+                "Freeing a free bit {bit} in block group {group}!"
+            )));
+        }
+        data[bit as usize / 8] &= !(1 << (bit % 8));
+        self.write_block(block, &data)?;
+
+        let descriptor = &mut self.groups[group as usize];
+        match bitmap {
+            Bitmap::Blocks => {
+                descriptor.free_blocks_count += 1;
+                self.superblock.free_blocks_count += 1;
+            }
+            Bitmap::Inodes => {
+                descriptor.free_inodes_count += 1;
+                self.superblock.free_inodes_count += 1;
+            }
+        }
+        self.write_group(group)?;
+
+        self.write_superblock()
+    }
+
+    /// Allocates a zeroed block.
+    ///
+    /// # Arguments
+    ///
+    /// * `goal` - The block group to try first.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The block.
+    ///
+    /// # Errors
+    ///
+    /// * If the device is full.
+    /// * If the device fails to read or write.
+    fn allocate_block(&mut self, goal: u32) -> Result<u32, Error> {
+        let (group, bit) = self.allocate_bit(Bitmap::Blocks, goal)?;
+        let block =
+            self.superblock.first_data_block + group * self.superblock.blocks_per_group + bit;
+
+        self.write_block(block, &vec![0; self.block_size()])?;
+
+        Ok(block)
+    }
+
+    /// Frees a block.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The block.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the block is out of range or already free.
+    /// * If the device fails to read or write.
+    fn free_block(&mut self, block: u32) -> Result<(), Error> {
+        self.check_block(block)?;
+
+        let index = block - self.superblock.first_data_block;
+        self.free_bit(
+            Bitmap::Blocks,
+            index / self.superblock.blocks_per_group,
+            index % self.superblock.blocks_per_group,
+        )
+    }
+
+    /// Allocates an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `goal` - The block group to try first.
+    /// * `directory` - Whether or not the inode is a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The inode.
+    ///
+    /// # Errors
+    ///
+    /// * If no inode is free.
+    /// * If the device fails to read or write.
+    fn allocate_inode(&mut self, goal: u32, directory: bool) -> Result<u32, Error> {
+        let (group, bit) = self.allocate_bit(Bitmap::Inodes, goal)?;
+        let number = group * self.superblock.inodes_per_group + bit + 1;
+
+        if number < self.superblock.first_inode || number > self.superblock.inodes_count {
+            return Err(Error::FileSystem(format!(
+                "Allocated a reserved inode {number}!"
+            )));
+        }
+
+        if directory {
+            self.groups[group as usize].used_dirs_count += 1;
+            self.write_group(group)?;
+        }
+
+        Ok(number)
+    }
+
+    /// Frees an inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    /// * `directory` - Whether or not the inode was a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is already free.
+    /// * If the device fails to read or write.
+    fn free_inode(&mut self, number: u32, directory: bool) -> Result<(), Error> {
+        let group = self.group_of(number);
+
+        if directory {
+            let descriptor = &mut self.groups[group as usize];
+            descriptor.used_dirs_count = descriptor.used_dirs_count.saturating_sub(1);
+        }
+
+        self.free_bit(
+            Bitmap::Inodes,
+            group,
+            (number - 1) % self.superblock.inodes_per_group,
+        )
+    }
+
+    /// Gets the path to a block of a file through the indirect blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the block in the file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(usize, Vec<usize>), Error>` - The block pointer of the inode, and the pointer to follow in each indirect block.
+    ///
+    /// # Errors
+    ///
+    /// * If the index is past what triply indirect blocks can address.
+    fn block_path(&self, index: u64) -> Result<(usize, Vec<usize>), Error> {
+        let per_block = (self.block_size() / 4) as u64;
+
+        let mut index = index;
+        if index < DIRECT_BLOCKS as u64 {
+            return Ok((index as usize, Vec::new()));
+        }
+        index -= DIRECT_BLOCKS as u64;
+
+        let mut span = per_block;
+        for depth in 1..=3 {
+            if index < span {
+                let path = (0..depth)
+                    .rev()
+                    .map(|level| (index / per_block.pow(level) % per_block) as usize)
+                    .collect();
+
+                return Ok((DIRECT_BLOCKS + depth as usize - 1, path));
+            }
+            index -= span;
+            span *= per_block;
+        }
+
+        Err(Error::FileSystem("File too large!".into()))
+    }
+
+    /// Gets a block of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode of the file.
+    /// * `index` - The index of the block in the file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The block, `0` if it's a hole.
+    ///
+    /// # Errors
+    ///
+    /// * If the index is out of range.
+    /// * If the device fails to read.
+    fn block_at(&self, inode: &InodeData, index: u64) -> Result<u32, Error> {
+        let (pointer, path) = self.block_path(index)?;
+
+        let mut block = inode.blocks[pointer];
+        for offset in path {
+            if block == 0 {
+                break;
+            }
+
+            let data = self.read_block(block)?;
+            block = u32::from_le_bytes(data[offset * 4..offset * 4 + 4].try_into()?);
+        }
+
+        Ok(block)
+    }
+
+    /// Gets a block of a file, allocating it and any missing indirect blocks.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode of the file.
+    /// * `inode` - The contents of the inode, which the caller writes back.
+    /// * `index` - The index of the block in the file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The block.
+    ///
+    /// # Errors
+    ///
+    /// * If the index is out of range.
+    /// * If the device is full.
+    /// * If the device fails to read or write.
+    fn allocate_block_at(
+        &mut self,
+        number: u32,
+        inode: &mut InodeData,
+        index: u64,
+    ) -> Result<u32, Error> {
+        let goal = self.group_of(number);
+        let (pointer, path) = self.block_path(index)?;
+
+        if inode.blocks[pointer] == 0 {
+            inode.blocks[pointer] = self.allocate_block(goal)?;
+            inode.sectors += self.sectors_per_block();
+        }
+
+        let mut block = inode.blocks[pointer];
+        for offset in path {
+            let mut data = self.read_block(block)?;
+            let range = offset * 4..offset * 4 + 4;
+
+            let mut next = u32::from_le_bytes(data[range.clone()].try_into()?);
+            if next == 0 {
+                next = self.allocate_block(goal)?;
+                inode.sectors += self.sectors_per_block();
+
+                data[range].copy_from_slice(&next.to_le_bytes());
+                self.write_block(block, &data)?;
+            }
+            block = next;
+        }
+
+        Ok(block)
+    }
+
+    /// Frees the blocks of a file from an index on.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode of the file, which the caller writes back.
+    /// * `first` - The index of the first block to free.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn free_blocks_from(&mut self, inode: &mut InodeData, first: u64) -> Result<(), Error> {
+        for pointer in (first as usize).min(DIRECT_BLOCKS)..DIRECT_BLOCKS {
+            if inode.blocks[pointer] != 0 {
+                self.free_block(inode.blocks[pointer])?;
+                inode.blocks[pointer] = 0;
+                inode.sectors = inode.sectors.saturating_sub(self.sectors_per_block());
+            }
+        }
+
+        let per_block = (self.block_size() / 4) as u64;
+        let mut start = DIRECT_BLOCKS as u64;
+        let mut span = per_block;
+        for depth in 1..=3 {
+            let pointer = DIRECT_BLOCKS + depth as usize - 1;
+
+            if inode.blocks[pointer] != 0
+                && first < start + span
+                && self.free_tree(inode, inode.blocks[pointer], depth, start, first)?
+            {
+                self.free_block(inode.blocks[pointer])?;
+                inode.blocks[pointer] = 0;
+                inode.sectors = inode.sectors.saturating_sub(self.sectors_per_block());
+            }
+
+            start += span;
+            span *= per_block;
+        }
+
+        Ok(())
+    }
+
+    /// Frees the blocks of a file from an index on, below an indirect block.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode of the file, which the caller writes back.
+    /// * `block` - The indirect block.
+    /// * `depth` - The levels of indirection of the block.
+    /// * `start` - The index of the first block of the file below the indirect block.
+    /// * `first` - The index of the first block to free.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, Error>` - Whether or not the indirect block is now empty, and can be freed.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn free_tree(
+        &mut self,
+        inode: &mut InodeData,
+        block: u32,
+        depth: u32,
+        start: u64,
+        first: u64,
+    ) -> Result<bool, Error> {
+        let per_block = self.block_size() / 4;
+        let child_span = (per_block as u64).pow(depth - 1);
+
+        let mut data = self.read_block(block)?;
+        let mut empty = true;
+        let mut changed = false;
+
+        for offset in 0..per_block {
+            let range = offset * 4..offset * 4 + 4;
+            let child = u32::from_le_bytes(data[range.clone()].try_into()?);
+            if child == 0 {
+                continue;
+            }
+
+            let child_start = start + offset as u64 * child_span;
+            let free = if child_start + child_span <= first {
+                false
+            } else if depth == 1 {
+                true
+            } else {
+                self.free_tree(inode, child, depth - 1, child_start, first)?
+            };
+
+            if free {
+                self.free_block(child)?;
+                inode.sectors = inode.sectors.saturating_sub(self.sectors_per_block());
+                data[range].fill(0);
+                changed = true;
+            } else {
+                empty = false;
+            }
+        }
+
+        // An empty indirect block is freed by the caller instead.
+        if changed && !empty {
+            self.write_block(block, &data)?;
+        }
+
+        Ok(empty)
+    }
+
+    /// Reads from a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode of the file.
+    /// * `offset` - The offset to start reading at.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes read, `0` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    fn read_data(
+        &self,
+        inode: &InodeData,
+        offset: usize,
+        buffer: &mut [u8],
+    ) -> Result<usize, Error> {
+        let size = usize::try_from(inode.size)?;
+        let count = size.saturating_sub(offset).min(buffer.len());
+        if count == 0 {
+            return Ok(0);
+        }
+
+        // Fast symbolic links keep their target in the block pointers.
+        if inode.is_fast_symlink(self.block_size()) {
+            let target = &inode.raw[40..40 + FAST_SYMLINK_SIZE.min(size)];
+            buffer[..count].copy_from_slice(&target[offset..offset + count]);
+
+            return Ok(count);
+        }
+
+        let block_size = self.block_size();
+        let mut done = 0;
+        while done < count {
+            let position = offset + done;
+            let skip = position % block_size;
+            let length = (block_size - skip).min(count - done);
+
+            // Holes read as zeroes.
+            let block = self.block_at(inode, (position / block_size) as u64)?;
+            if block == 0 {
+                buffer[done..done + length].fill(0);
+            } else {
+                let data = self.read_block(block)?;
+                buffer[done..done + length].copy_from_slice(&data[skip..skip + length]);
+            }
+
+            done += length;
+        }
+
+        Ok(count)
+    }
+
+    /// Writes to a file, growing it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode of the file.
+    /// * `inode` - The contents of the inode, which the caller writes back.
+    /// * `offset` - The offset to start writing at.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// * If the file would be too large.
+    /// * If the device is full.
+    /// * If the device fails to read or write.
+    fn write_data(
+        &mut self,
+        number: u32,
+        inode: &mut InodeData,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<usize, Error> {
+        let end = (offset + data.len()) as u64;
+        self.check_size(end)?;
+
+        let block_size = self.block_size();
+        let mut done = 0;
+        while done < data.len() {
+            let position = offset + done;
+            let skip = position % block_size;
+            let length = (block_size - skip).min(data.len() - done);
+
+            let index = (position / block_size) as u64;
+            let mut block = self.block_at(inode, index)?;
+            if block == 0 {
+                block = self.allocate_block_at(number, inode, index)?;
+            }
+
+            // Partially written blocks are read first.
+            if length == block_size {
+                self.write_block(block, &data[done..done + length])?;
+            } else {
+                let mut contents = self.read_block(block)?;
+                contents[skip..skip + length].copy_from_slice(&data[done..done + length]);
+                self.write_block(block, &contents)?;
+            }
+
+            done += length;
+        }
+
+        inode.size = inode.size.max(end);
+        inode.mtime = now();
+        inode.ctime = inode.mtime;
+
+        Ok(done)
+    }
+
+    /// Truncates or extends a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode of the file, which the caller writes back.
+    /// * `size` - The new size.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file would be too large.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * Extending a file leaves a hole, which reads as zeroes.
+    fn resize(&mut self, inode: &mut InodeData, size: u64) -> Result<(), Error> {
+        self.check_size(size)?;
+
+        if size < inode.size {
+            let block_size = self.block_size() as u64;
+            self.free_blocks_from(inode, size.div_ceil(block_size))?;
+
+            // The end of the last block must read as zeroes if the file grows again.
+            let skip = (size % block_size) as usize;
+            if skip != 0 {
+                let block = self.block_at(inode, size / block_size)?;
+                if block != 0 {
+                    let mut data = self.read_block(block)?;
+                    data[skip..].fill(0);
+                    self.write_block(block, &data)?;
+                }
+            }
+        }
+
+        inode.size = size;
+        inode.mtime = now();
+        inode.ctime = inode.mtime;
+
+        Ok(())
+    }
+
+    /// Checks if a file can grow to a size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the size needs large files, and the feature isn't enabled.
+    fn check_size(&self, size: u64) -> Result<(), Error> {
+        if size > u64::from(u32::MAX)
+            && self.superblock.feature_ro_compat & RO_COMPAT_LARGE_FILE == 0
+        {
+            return Err(Error::FileSystem("File too large!".into()));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the entries of a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The inode of the directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<DirectoryEntry>, Error>` - The entries, including `.` and `..`.
+    ///
+    /// # Errors
+    ///
+    /// * If a directory entry is corrupted.
+    /// * If the device fails to read.
+    fn read_entries(&self, directory: &InodeData) -> Result<Vec<DirectoryEntry>, Error> {
+        let mut entries = Vec::new();
+
+        for index in 0..directory.size / self.block_size() as u64 {
+            let block = self.block_at(directory, index)?;
+            if block == 0 {
+                continue;
+            }
+
+            let data = self.read_block(block)?;
+            let mut offset = 0;
+            while offset < data.len() {
+                let (inode, length, name) = parse_entry(&data, offset)?;
+                if inode != 0 {
+                    entries.push(DirectoryEntry {
+                        inode,
+                        name: String::from_utf8_lossy(name).into_owned(),
+                    });
+                }
+
+                offset += length;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Finds an entry of a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The inode of the directory.
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<u32>, Error>` - The inode of the entry, if it exists.
+    ///
+    /// # Errors
+    ///
+    /// * If a directory entry is corrupted.
+    /// * If the device fails to read.
+    fn find_entry(&self, directory: &InodeData, name: &str) -> Result<Option<u32>, Error> {
+        Ok(self
+            .read_entries(directory)?
+            .into_iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.inode))
+    }
+
+    /// Checks if a directory only holds `.` and `..`.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The inode of the directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<bool, Error>` - Whether or not the directory is empty.
+    ///
+    /// # Errors
+    ///
+    /// * If a directory entry is corrupted.
+    /// * If the device fails to read.
+    fn is_empty(&self, directory: &InodeData) -> Result<bool, Error> {
+        Ok(self
+            .read_entries(directory)?
+            .iter()
+            .all(|entry| entry.name == "." || entry.name == ".."))
+    }
+
+    /// Adds an entry to a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode of the directory.
+    /// * `directory` - The contents of the inode, which the caller writes back.
+    /// * `name` - The name of the entry.
+    /// * `inode` - The inode of the entry.
+    /// * `file_type` - The file type of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If a directory entry is corrupted.
+    /// * If the device is full.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * The entry takes the slack after an existing entry if it fits, or a new block otherwise.
+    fn add_entry(
+        &mut self,
+        number: u32,
+        directory: &mut InodeData,
+        name: &str,
+        inode: u32,
+        file_type: u8,
+    ) -> Result<(), Error> {
+        let block_size = self.block_size();
+        let needed = entry_size(name.len());
+        let file_type = if self.superblock.feature_incompat & INCOMPAT_FILETYPE == 0 {
+            0
+        } else {
+            file_type
+        };
+
+        for index in 0..directory.size / block_size as u64 {
+            let block = self.block_at(directory, index)?;
+            if block == 0 {
+                continue;
+            }
+
+            let mut data = self.read_block(block)?;
+            let mut offset = 0;
+            while offset < data.len() {
+                let (existing, length, existing_name) = parse_entry(&data, offset)?;
+                let used = if existing == 0 {
+                    0
+                } else {
+                    entry_size(existing_name.len())
+                };
+
+                if length - used >= needed {
+                    // Split the existing entry, keeping its own size.
+                    if used != 0 {
+                        data[offset + 4..offset + 6].copy_from_slice(&(used as u16).to_le_bytes());
+                    }
+                    write_entry(
+                        &mut data,
+                        offset + used,
+                        inode,
+                        length - used,
+                        name,
+                        file_type,
+                    );
+                    self.write_block(block, &data)?;
+
+                    directory.mtime = now();
+                    directory.ctime = directory.mtime;
+                    directory.flags &= !FLAG_INDEX;
+
+                    return Ok(());
+                }
+
+                offset += length;
+            }
+        }
+
+        // Append a new block holding only the entry.
+        let index = directory.size / block_size as u64;
+        let block = self.allocate_block_at(number, directory, index)?;
+
+        let mut data = vec![0; block_size];
+        write_entry(&mut data, 0, inode, block_size, name, file_type);
+        self.write_block(block, &data)?;
+
+        directory.size += block_size as u64;
+        directory.mtime = now();
+        directory.ctime = directory.mtime;
+        directory.flags &= !FLAG_INDEX;
+
+        Ok(())
+    }
+
+    /// Removes an entry from a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The inode of the directory, which the caller writes back.
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry doesn't exist.
+    /// * If a directory entry is corrupted.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * The space of the entry is merged into the previous entry of its block.
+    fn remove_entry(&mut self, directory: &mut InodeData, name: &str) -> Result<(), Error> {
+        for index in 0..directory.size / self.block_size() as u64 {
+            let block = self.block_at(directory, index)?;
+            if block == 0 {
+                continue;
+            }
+
+            let mut data = self.read_block(block)?;
+            let mut previous: Option<usize> = None;
+            let mut offset = 0;
+            while offset < data.len() {
+                let (inode, length, entry_name) = parse_entry(&data, offset)?;
+
+                if inode != 0 && entry_name == name.as_bytes() {
+                    if let Some(previous) = previous {
+                        let previous_length =
+                            u16::from_le_bytes(data[previous + 4..previous + 6].try_into()?);
+                        let merged = previous_length + length as u16;
+                        data[previous + 4..previous + 6].copy_from_slice(&merged.to_le_bytes());
+                    } else {
+                        // The first entry of a block can't be merged, so it's only marked unused.
+                        data[offset..offset + 4].fill(0);
+                    }
+                    self.write_block(block, &data)?;
+
+                    directory.mtime = now();
+                    directory.ctime = directory.mtime;
+                    directory.flags &= !FLAG_INDEX;
+
+                    return Ok(());
+                }
+
+                previous = Some(offset);
+                offset += length;
+            }
+        }
+
+        Err(Error::FileSystem(format!(
+            "No such file or directory: '{name}'!"
+        )))
+    }
+
+    /// Points an existing entry of a directory to another inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The inode of the directory.
+    /// * `name` - The name of the entry.
+    /// * `inode` - The new inode of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry doesn't exist.
+    /// * If a directory entry is corrupted.
+    /// * If the device fails to read or write.
+    fn relink_entry(&self, directory: &InodeData, name: &str, inode: u32) -> Result<(), Error> {
+        for index in 0..directory.size / self.block_size() as u64 {
+            let block = self.block_at(directory, index)?;
+            if block == 0 {
+                continue;
+            }
+
+            let mut data = self.read_block(block)?;
+            let mut offset = 0;
+            while offset < data.len() {
+                let (existing, length, entry_name) = parse_entry(&data, offset)?;
+
+                if existing != 0 && entry_name == name.as_bytes() {
+                    data[offset..offset + 4].copy_from_slice(&inode.to_le_bytes());
+
+                    return self.write_block(block, &data);
+                }
+
+                offset += length;
+            }
+        }
+
+        Err(Error::FileSystem(format!(
+            "No such file or directory: '{name}'!"
+        )))
+    }
+
+    /// Looks up an entry of a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The inode of the directory.
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The inode of the entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    /// * If the entry doesn't exist.
+    /// * If the device fails to read.
+    fn lookup(&self, parent: u32, name: &str) -> Result<u32, Error> {
+        let directory = self.read_inode(parent)?;
+        if !directory.is_dir() {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+
+        self.find_entry(&directory, name)?
+            .ok_or_else(|| Error::FileSystem(format!("No such file or directory: '{name}'!")))
+    }
+
+    /// Creates a file or directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The inode of the parent directory.
+    /// * `name` - The name of the entry.
+    /// * `kind` - The kind of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The inode of the entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system is read only.
+    /// * If the parent isn't a directory.
+    /// * If the name is invalid or already exists.
+    /// * If the device is full.
+    /// * If the device fails to read or write.
+    pub fn create(&mut self, parent: u32, name: &str, kind: InodeKind) -> Result<u32, Error> {
+        self.check_writable()?;
+        check_name(name)?;
+        if kind == InodeKind::Symlink {
+            return Err(Error::FileSystem(
+                "Creating symbolic links isn't supported!".into(),
+            ));
+        }
+
+        let mut directory = self.read_inode(parent)?;
+        if !directory.is_dir() {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+        if self.find_entry(&directory, name)?.is_some() {
+            return Err(Error::FileSystem(format!("'{name}' already exists!")));
+        }
+
+        let is_dir = kind == InodeKind::Directory;
+        let number = self.allocate_inode(self.group_of(parent), is_dir)?;
+
+        let (mut inode, file_type) = if is_dir {
+            let mut inode = InodeData::new(
+                self.superblock.inode_size,
+                MODE_DIRECTORY | DEFAULT_DIRECTORY_PERMISSIONS,
+            );
+
+            // A directory is linked from its parent and its own `.` entry.
+            inode.links_count = 2;
+            directory.links_count += 1;
+
+            (inode, FILE_TYPE_DIRECTORY)
+        } else {
+            let inode = InodeData::new(
+                self.superblock.inode_size,
+                MODE_FILE | DEFAULT_FILE_PERMISSIONS,
+            );
+
+            (inode, FILE_TYPE_FILE)
+        };
+
+        if is_dir {
+            let block_size = self.block_size();
+            let block = self.allocate_block_at(number, &mut inode, 0)?;
+            let dot_size = entry_size(1);
+            let file_type = if self.superblock.feature_incompat & INCOMPAT_FILETYPE == 0 {
+                0
+            } else {
+                FILE_TYPE_DIRECTORY
+            };
+
+            let mut data = vec![0; block_size];
+            write_entry(&mut data, 0, number, dot_size, ".", file_type);
+            write_entry(
+                &mut data,
+                dot_size,
+                parent,
+                block_size - dot_size,
+                "..",
+                file_type,
+            );
+            self.write_block(block, &data)?;
+
+            inode.size = block_size as u64;
+        }
+
+        self.write_inode(number, &inode)?;
+        self.add_entry(parent, &mut directory, name, number, file_type)?;
+        self.write_inode(parent, &directory)?;
+
+        Ok(number)
+    }
+
+    /// Removes an entry from a directory, deleting its inode once it has no links left.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The inode of the parent directory.
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system is read only.
+    /// * If the entry doesn't exist or is a non-empty directory.
+    /// * If the device fails to read or write.
+    pub fn unlink(&mut self, parent: u32, name: &str) -> Result<(), Error> {
+        self.check_writable()?;
+
+        let number = self.lookup(parent, name)?;
+        let mut directory = self.read_inode(parent)?;
+        let mut inode = self.read_inode(number)?;
+
+        if inode.is_dir() {
+            if !self.is_empty(&inode)? {
+                return Err(Error::FileSystem(format!("'{name}' is not empty!")));
+            }
+
+            // The `..` entry of the directory linked its parent.
+            directory.links_count = directory.links_count.saturating_sub(1);
+            inode.links_count = 0;
+        } else {
+            inode.links_count = inode.links_count.saturating_sub(1);
+        }
+
+        self.remove_entry(&mut directory, name)?;
+        self.write_inode(parent, &directory)?;
+
+        inode.ctime = now();
+        if inode.links_count == 0 {
+            self.release(number, &mut inode)
+        } else {
+            self.write_inode(number, &inode)
+        }
+    }
+
+    /// Moves an entry, replacing any existing target.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The inode of the directory holding the entry.
+    /// * `name` - The name of the entry.
+    /// * `new_parent` - The inode of the directory to move the entry to.
+    /// * `new_name` - The new name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system is read only.
+    /// * If the entry doesn't exist.
+    /// * If a directory would be moved into itself.
+    /// * If the target can't be replaced.
+    /// * If the device fails to read or write.
+    pub fn rename(
+        &mut self,
+        parent: u32,
+        name: &str,
+        new_parent: u32,
+        new_name: &str,
+    ) -> Result<(), Error> {
+        self.check_writable()?;
+        check_name(new_name)?;
+
+        let number = self.lookup(parent, name)?;
+        if parent == new_parent && name == new_name {
+            return Ok(());
+        }
+
+        let mut inode = self.read_inode(number)?;
+        if inode.is_dir() && parent != new_parent {
+            // Walk up from the new parent, which must not be below the directory.
+            let mut ancestor = new_parent;
+            while ancestor != ROOT_INODE {
+                if ancestor == number {
+                    return Err(Error::FileSystem(format!(
+                        "Can't move '{name}' into itself!"
+                    )));
+                }
+                ancestor = self.lookup(ancestor, "..")?;
+            }
+        }
+
+        let mut destination = self.read_inode(new_parent)?;
+        if !destination.is_dir() {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+
+        if let Some(target) = self.find_entry(&destination, new_name)? {
+            // Both names are already links to the same inode.
+            if target == number {
+                return Ok(());
+            }
+
+            let target_inode = self.read_inode(target)?;
+            match (inode.is_dir(), target_inode.is_dir()) {
+                (true, true) if !self.is_empty(&target_inode)? => {
+                    return Err(Error::FileSystem("Target directory is not empty!".into()))
+                }
+                (false, true) => return Err(Error::FileSystem("Is a directory!".into())),
+                (true, false) => return Err(Error::FileSystem("Not a directory!".into())),
+                _ => {}
+            }
+
+            self.unlink(new_parent, new_name)?;
+            destination = self.read_inode(new_parent)?;
+        }
+
+        // Link the new name first, so the entry isn't lost if the directory is full.
+        let file_type = if inode.is_dir() {
+            FILE_TYPE_DIRECTORY
+        } else {
+            FILE_TYPE_FILE
+        };
+        self.add_entry(new_parent, &mut destination, new_name, number, file_type)?;
+
+        if parent == new_parent {
+            self.remove_entry(&mut destination, name)?;
+            self.write_inode(new_parent, &destination)?;
+        } else {
+            let mut source = self.read_inode(parent)?;
+            self.remove_entry(&mut source, name)?;
+
+            // A moved directory links its new parent through `..`.
+            if inode.is_dir() {
+                self.relink_entry(&inode, "..", new_parent)?;
+                source.links_count = source.links_count.saturating_sub(1);
+                destination.links_count += 1;
+            }
+
+            self.write_inode(parent, &source)?;
+            self.write_inode(new_parent, &destination)?;
+        }
+
+        inode.ctime = now();
+        self.write_inode(number, &inode)
+    }
+
+    /// Deletes an inode with no links left.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode.
+    /// * `inode` - The contents of the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn release(&mut self, number: u32, inode: &mut InodeData) -> Result<(), Error> {
+        if !inode.is_fast_symlink(self.block_size()) {
+            self.free_blocks_from(inode, 0)?;
+        }
+
+        // Extended attribute blocks are shared, and counted.
+        if inode.file_acl != 0 {
+            let mut data = self.read_block(inode.file_acl)?;
+            let references = u32::from_le_bytes(data[4..8].try_into()?).saturating_sub(1);
+
+            if references == 0 {
+                self.free_block(inode.file_acl)?;
+            } else {
+                data[4..8].copy_from_slice(&references.to_le_bytes());
+                self.write_block(inode.file_acl, &data)?;
+            }
+
+            inode.file_acl = 0;
+            inode.sectors = 0;
+        }
+
+        inode.size = 0;
+        inode.dtime = now();
+        self.write_inode(number, inode)?;
+
+        self.free_inode(number, inode.is_dir())
+    }
+}
+
+/// The bitmaps of a block group.
+///
+/// # Variants
+///
+/// * `Blocks` - The block usage bitmap.
+/// * `Inodes` - The inode usage bitmap.
+#[derive(Debug, Clone, Copy)]
+enum Bitmap {
+    Blocks,
+    Inodes,
+}
+
+/// An ext2 file system mounted in the virtual file system.
+///
+/// # Fields
+///
+/// * `ext2` - The ext2 file system, shared with its inodes.
+pub struct Ext2FileSystem {
+    ext2: Arc<Mutex<Ext2>>,
+}
+
+impl Ext2FileSystem {
+    /// Creates a new mountable ext2 file system.
+    ///
+    /// # Arguments
+    ///
+    /// * `ext2` - The ext2 file system.
+    ///
+    /// # Returns
+    ///
+    /// * The new mountable ext2 file system.
+    #[must_use]
+    pub fn new(ext2: Ext2) -> Self {
+        Self {
+            ext2: Arc::new(Mutex::new(ext2)),
+        }
+    }
+}
+
+impl FileSystem for Ext2FileSystem {
+    fn name(&self) -> &'static str {
+        "ext2"
+    }
+
+    fn root(&self) -> Result<Arc<dyn Inode>, Error> {
+        Ok(Arc::new(Ext2Inode {
+            ext2: self.ext2.clone(),
+            number: ROOT_INODE,
+        }))
+    }
+
+    fn sync(&self) -> Result<(), Error> {
+        self.ext2.lock().sync()
+    }
+}
+
+/// A file or directory of a mounted ext2 file system.
+///
+/// # Fields
+///
+/// * `ext2` - The ext2 file system.
+/// * `number` - The inode.
+struct Ext2Inode {
+    ext2: Arc<Mutex<Ext2>>,
+    number: u32,
+}
+
+impl Ext2Inode {
+    /// Creates the inode of an entry of the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The inode of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<dyn Inode>` - The inode.
+    fn child(&self, number: u32) -> Arc<dyn Inode> {
+        Arc::new(Self {
+            ext2: self.ext2.clone(),
+            number,
+        })
+    }
+}
+
+impl Inode for Ext2Inode {
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let ext2 = self.ext2.lock();
+        let inode = ext2.read_inode(self.number)?;
+
+        Ok(Metadata {
+            kind: inode.kind(),
+            size: usize::try_from(inode.size)?,
+            read_only: ext2.read_only
+                || (!inode.is_dir() && !inode.is_file())
+                || inode.mode & MODE_OWNER_WRITE == 0,
+            mode: inode.mode & MODE_PERMISSIONS_MASK,
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error> {
+        let number = self.ext2.lock().lookup(self.number, name)?;
+
+        Ok(self.child(number))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error> {
+        let ext2 = self.ext2.lock();
+        let directory = ext2.read_inode(self.number)?;
+        if !directory.is_dir() {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+
+        ext2.read_entries(&directory)?
+            .into_iter()
+            .filter(|entry| entry.name != "." && entry.name != "..")
+            .map(|entry| {
+                let inode = ext2.read_inode(entry.inode)?;
+
+                Ok(DirEntry {
+                    name: entry.name,
+                    kind: inode.kind(),
+                    size: usize::try_from(inode.size)?,
+                })
+            })
+            .collect()
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        let ext2 = self.ext2.lock();
+        let inode = ext2.read_inode(self.number)?;
+        if inode.is_dir() {
+            return Err(Error::FileSystem("Is a directory!".into()));
+        }
+
+        ext2.read_data(&inode, offset, buffer)
+    }
+
+    fn write_at(&self, offset: usize, data: &[u8]) -> Result<usize, Error> {
+        let mut ext2 = self.ext2.lock();
+        ext2.check_writable()?;
+
+        let mut inode = ext2.read_inode(self.number)?;
+        if !inode.is_file() {
+            return Err(Error::FileSystem("Is not a regular file!".into()));
+        }
+
+        // Blocks allocated before a failure are still recorded in the inode.
+        let result = ext2.write_data(self.number, &mut inode, offset, data);
+        ext2.write_inode(self.number, &inode)?;
+
+        result
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Error> {
+        let mut ext2 = self.ext2.lock();
+        ext2.check_writable()?;
+
+        let mut inode = ext2.read_inode(self.number)?;
+        if !inode.is_file() {
+            return Err(Error::FileSystem("Is not a regular file!".into()));
+        }
+
+        let result = ext2.resize(&mut inode, size as u64);
+        ext2.write_inode(self.number, &inode)?;
+
+        result
+    }
+
+    fn create(&self, name: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Error> {
+        let number = self.ext2.lock().create(self.number, name, kind)?;
+
+        Ok(self.child(number))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Error> {
+        self.ext2.lock().unlink(self.number, name)
+    }
+
+    fn rename(&self, name: &str, new_parent: &Arc<dyn Inode>, new_name: &str) -> Result<(), Error> {
+        let new_parent = new_parent
+            .as_any()
+            .downcast_ref::<Self>()
+            .filter(|new_parent| Arc::ptr_eq(&new_parent.ext2, &self.ext2))
+            .ok_or_else(|| Error::FileSystem("Can't rename across file systems!".into()))?;
+
+        self.ext2
+            .lock()
+            .rename(self.number, name, new_parent.number, new_name)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Gets the size of a directory entry.
+///
+/// # Arguments
+///
+/// * `name_length` - The length of the name, in bytes.
+///
+/// # Returns
+///
+/// * `usize` - The size of the entry, aligned to 4 bytes.
+fn entry_size(name_length: usize) -> usize {
+    (DIR_ENTRY_HEADER_SIZE + name_length).next_multiple_of(4)
+}
+
+/// Decodes a directory entry.
+///
+/// # Arguments
+///
+/// * `data` - The block holding the entry.
+/// * `offset` - The offset of the entry in the block.
+///
+/// # Returns
+///
+/// * `Result<(u32, usize, &[u8]), Error>` - The inode, the record length and the name of the entry.
+///
+/// # Errors
+///
+/// * If the entry is corrupted.
+fn parse_entry(data: &[u8], offset: usize) -> Result<(u32, usize, &[u8]), Error> {
+    let corrupted = || Error::FileSystem(format!("Corrupted directory entry at offset {offset}!"));
+
+    let header = data
+        .get(offset..offset + DIR_ENTRY_HEADER_SIZE)
+        .ok_or_else(corrupted)?;
+    let inode = u32::from_le_bytes(header[0..4].try_into()?);
+    let length = usize::from(u16::from_le_bytes(header[4..6].try_into()?));
+    let name_length = usize::from(header[6]);
+
+    if length < DIR_ENTRY_HEADER_SIZE
+        || !length.is_multiple_of(4)
+        || offset + length > data.len()
+        || DIR_ENTRY_HEADER_SIZE + name_length > length
+    {
+        return Err(corrupted());
+    }
+
+    let name = &data[offset + DIR_ENTRY_HEADER_SIZE..offset + DIR_ENTRY_HEADER_SIZE + name_length];
+
+    Ok((inode, length, name))
+}
+
+/// Encodes a directory entry.
+///
+/// # Arguments
+///
+/// * `data` - The block to write the entry into.
+/// * `offset` - The offset of the entry in the block.
+/// * `inode` - The inode of the entry.
+/// * `length` - The record length of the entry.
+/// * `name` - The name of the entry.
+/// * `file_type` - The file type of the entry, `0` if the file system doesn't store it.
+#[allow(clippy::cast_possible_truncation)]
+fn write_entry(
+    data: &mut [u8],
+    offset: usize,
+    inode: u32,
+    length: usize,
+    name: &str,
+    file_type: u8,
+) {
+    data[offset..offset + 4].copy_from_slice(&inode.to_le_bytes());
+    data[offset + 4..offset + 6].copy_from_slice(&(length as u16).to_le_bytes());
+    data[offset + 6] = name.len() as u8;
+    data[offset + 7] = file_type;
+    data[offset + DIR_ENTRY_HEADER_SIZE..offset + DIR_ENTRY_HEADER_SIZE + name.len()]
+        .copy_from_slice(name.as_bytes());
+}
+
+/// Checks if a name can be stored in a directory entry.
+///
+/// # Arguments
+///
+/// * `name` - The name.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the name is empty, too long, or holds a `/` or a null byte.
+fn check_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH || name.contains(['/', '\0']) {
+        return Err(Error::FileSystem(format!("Invalid name: '{name}'!")));
+    }
+
+    Ok(())
+}
+
+/// Gets the current time for timestamps.
+///
+/// # Returns
+///
+/// * `u32` - The seconds since the Unix epoch, or 0 if it doesn't fit.
+fn now() -> u32 {
+    u32::try_from(clock::unix_time()).unwrap_or_default()
+}
+
+/// Reads bytes from a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device to read from.
+/// * `offset` - The offset of the bytes.
+/// * `buffer` - The buffer to read into.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+fn read_bytes(device: &dyn BlockDevice, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+    let block_size = device.block_size();
+
+    // Read the whole device blocks covering the bytes.
+    let skip = offset % block_size;
+    let mut data = vec![0; (skip + buffer.len()).next_multiple_of(block_size)];
+    device.read_blocks((offset / block_size) as u64, &mut data)?;
+    buffer.copy_from_slice(&data[skip..skip + buffer.len()]);
+
+    Ok(())
+}
+
+/// Writes bytes to a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device to write to.
+/// * `offset` - The offset of the bytes.
+/// * `bytes` - The bytes to write.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the device fails to read or write.
+fn write_bytes(device: &dyn BlockDevice, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+    let block_size = device.block_size();
+    let first = (offset / block_size) as u64;
+
+    // Partially written device blocks are read first.
+    let skip = offset % block_size;
+    let mut data = vec![0; (skip + bytes.len()).next_multiple_of(block_size)];
+    if skip != 0 || !bytes.len().is_multiple_of(block_size) {
+        device.read_blocks(first, &mut data)?;
+    }
+    data[skip..skip + bytes.len()].copy_from_slice(bytes);
+
+    device.write_blocks(first, &data)
+}
+
+/// Initializes the ext2 file system.
+///
+/// # Arguments
+///
+/// * `device` - The block device to mount.
+///
+/// # Returns
+///
+/// * `Result<Ext2, Error>` - The ext2 file system.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+/// * If the volume is malformed, or uses incompatible features.
+/// * If the device block size doesn't divide the file system block size.
+///
+/// # Notes
+///
+/// * File systems using read-only compatible features this driver doesn't know are mounted read only.
+pub fn init(device: Arc<dyn BlockDevice>) -> Result<Ext2, Error> {
+    // Get the superblock.
+    let mut raw = vec![0; SUPERBLOCK_SIZE];
+    read_bytes(&*device, SUPERBLOCK_OFFSET, &mut raw)?;
+    let superblock = Superblock::parse(&raw)?;
+
+    let block_size = superblock.block_size();
+    if block_size % device.block_size() != 0 {
+        return Err(Error::FileSystem(format!(
+            "Block size {size} isn't supported!",
+            size = device.block_size()
+        )));
+    }
+    if u64::from(superblock.blocks_count) * block_size as u64
+        > device.block_count() * device.block_size() as u64
+    {
+        return Err(Error::FileSystem(
+            "Volume is larger than the device!".into(),
+        ));
+    }
+
+    // Get the block group descriptors, from the block after the superblock.
+    let mut table = vec![0; superblock.group_count() as usize * GROUP_DESCRIPTOR_SIZE];
+    read_bytes(
+        &*device,
+        (superblock.first_data_block as usize + 1) * block_size,
+        &mut table,
+    )?;
+    let groups = table
+        .chunks_exact(GROUP_DESCRIPTOR_SIZE)
+        .map(GroupDescriptor::parse)
+        .collect::<Result<_, _>>()?;
+
+    let ext2 = Ext2 {
+        device,
+        read_only: !superblock.writable(),
+        superblock,
+        groups,
+    };
+
+    // Make sure the root directory is usable.
+    if !ext2.read_inode(ROOT_INODE)?.is_dir() {
+        return Err(Error::FileSystem("Root inode isn't a directory!".into()));
+    }
+
+    // Return the ext2 file system.
+    Ok(ext2)
+}
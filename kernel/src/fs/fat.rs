@@ -1,5 +1,16 @@
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
+use core::any::Any;
+use core::convert::TryInto;
+use spin::Mutex;
+
+use crate::dev::block::BlockDevice;
+use crate::errors::Error;
+use crate::fs::vfs::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+use crate::sys::time::clock;
 
 /// Specifies the file is read only.
 pub const READ_ONLY: u8 = 0x01;
@@ -17,6 +28,7 @@ pub const DIRECTORY: u8 = 0x10;
 /// Specifies the file is an archive.
 ///
 /// # Notes
+///
 /// * Archive files are files that are marked for backup or removal.
 pub const ARCHIVE: u8 = 0x20;
 /// Specifies the file is a long file name.
@@ -27,18 +39,66 @@ pub const ARCHIVE: u8 = 0x20;
 /// * They're defined by having the `READ_ONLY`, `HIDDEN`, `SYSTEM`, or `VOLUME_ID` flags set.
 pub const LFN: u8 = READ_ONLY | HIDDEN | SYSTEM | VOLUME_ID;
 
+/// The size of a sector, in bytes.
+const SECTOR_SIZE: usize = 512;
+
+/// The size of a directory entry, in bytes.
+pub const DIRECTORY_ENTRY_SIZE: usize = 32;
+
+/// The first byte of a directory entry marking the end of the directory.
+const END_OF_DIRECTORY: u8 = 0x00;
+/// The first byte of a directory entry marking it as deleted.
+const DELETED_ENTRY: u8 = 0xE5;
+/// The first byte of a directory entry whose name really starts with `0xE5`.
+const ESCAPED_DELETED_ENTRY: u8 = 0x05;
+
+/// The flag in the sequence number of the last (physically first) long file name entry.
+const LFN_LAST_ENTRY: u8 = 0x40;
+/// The mask of the sequence number of a long file name entry.
+const LFN_SEQUENCE_MASK: u8 = 0x1F;
+/// The number of UCS-2 characters stored in each long file name entry.
+const LFN_CHARACTERS: usize = 13;
+/// The offsets of the UCS-2 characters in a long file name entry.
+const LFN_CHARACTER_OFFSETS: [usize; LFN_CHARACTERS] =
+    [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+/// The maximum length of a long file name, in UCS-2 characters.
+const LFN_MAX_LENGTH: usize = 255;
+
+/// The largest cluster count of a FAT12 volume.
+const FAT12_MAX_CLUSTERS: u32 = 4_084;
+/// The largest cluster count of a FAT16 volume.
+const FAT16_MAX_CLUSTERS: u32 = 65_524;
+/// The largest cluster count of a FAT32 volume.
+const FAT32_MAX_CLUSTERS: u32 = 0x0FFF_FFF4;
+
+/// The lead signature of the FSInfo sector.
+const FS_INFO_LEAD_SIGNATURE: u32 = 0x4161_5252;
+/// The structure signature of the FSInfo sector.
+const FS_INFO_STRUCT_SIGNATURE: u32 = 0x6141_7272;
+/// The trail signature of the FSInfo sector.
+const FS_INFO_TRAIL_SIGNATURE: u32 = 0xAA55_0000;
+/// The value of an FSInfo field whose value is unknown.
+const FS_INFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
 /// A FAT file system.
 ///
 /// # Fields
 ///
+/// * `device` - The block device the file system lives on.
 /// * `boot_sector` - The boot sector.
 /// * `fat` - The file allocation table.
 /// * `root_dir` - The root directory.
-#[derive(Debug, Clone)]
+/// * `fs_info` - The FSInfo sector, if the volume is FAT32.
+///
+/// # Notes
+///
+/// * Directories are identified by their first cluster, where cluster `0` is the root directory, like in `..` entries.
 pub struct Fat {
+    device: Arc<dyn BlockDevice>,
     boot_sector: BootSector,
     fat: FatTable,
     root_dir: RootDirectory,
+    fs_info: Option<FsInfo>,
 }
 
 impl Fat {
@@ -46,51 +106,92 @@ impl Fat {
     ///
     /// # Arguments
     ///
+    /// * `device` - The block device the file system lives on.
     /// * `boot_sector` - The boot sector.
     /// * `fat` - The file allocation table.
     /// * `root_dir` - The root directory.
+    /// * `fs_info` - The FSInfo sector, if the volume is FAT32.
     ///
     /// # Returns
     ///
     /// * The new FAT file system.
     #[must_use]
-    pub const fn new(boot_sector: BootSector, fat: FatTable, root_dir: RootDirectory) -> Self {
+    pub const fn new(
+        device: Arc<dyn BlockDevice>,
+        boot_sector: BootSector,
+        fat: FatTable,
+        root_dir: RootDirectory,
+        fs_info: Option<FsInfo>,
+    ) -> Self {
         Self {
+            device,
             boot_sector,
             fat,
             root_dir,
+            fs_info,
         }
     }
 
-    /// Reads a file from the file system.
+    /// Gets the boot sector of the file system.
     ///
-    /// # Arguments
+    /// # Returns
     ///
-    /// * `path` - The path to the file.
+    /// * `&BootSector` - The boot sector.
+    #[must_use]
+    pub const fn boot_sector(&self) -> &BootSector {
+        &self.boot_sector
+    }
+
+    /// Writes any data cached by the device to its storage.
     ///
     /// # Returns
     ///
-    /// * If the file exists, the file.
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to flush.
+    pub fn sync(&self) -> Result<(), Error> {
+        self.device.flush()
+    }
+
+    /// Gets the FSInfo sector of the file system.
+    ///
+    /// # Returns
+    ///
+    /// * If the volume is FAT32 and has a valid FSInfo sector, the FSInfo sector.
     /// * Otherwise, `None`.
     #[must_use]
-    pub fn read_file(&self, path: &str) -> Option<File> {
-        // Get the file name.
-        let file_name = path.split('/').last()?;
-
-        // Get the directory.
-        let dir = path.trim_end_matches(file_name);
-
-        // Get the directory entry.
-        let dir_entry = self.root_dir.get_entry(dir)?;
+    pub const fn fs_info(&self) -> Option<&FsInfo> {
+        self.fs_info.as_ref()
+    }
 
+    /// Reads a file from the file system.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the file.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<File, Error>` - The file.
+    ///
+    /// # Errors
+    ///
+    /// * If the file doesn't exist.
+    /// * If the path points to a directory.
+    /// * If the device fails to read.
+    pub fn read_file(&self, path: &str) -> Result<File, Error> {
         // Get the file entry.
-        let file_entry = dir_entry.get_entry(file_name)?;
+        let file_entry = self.get_file_entry_from_path(path)?;
 
-        // Get the file.
-        let file = self.root_dir.get_file(&file_entry)?;
+        // Check if the file entry is a directory.
+        if file_entry.is_dir() {
+            return Err(Error::FileSystem(format!("'{path}' is a directory!")));
+        }
 
         // Return the file.
-        Some(file)
+        Ok(File::from(&file_entry))
     }
 
     /// Reads a directory from the file system.
@@ -101,592 +202,2729 @@ impl Fat {
     ///
     /// # Returns
     ///
-    /// * If the directory exists, the directory.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn read_dir(&self, path: &str) -> Option<Vec<File>> {
-        // Get the directory name.
-        let dir_name = path.split('/').last()?;
-
-        // Get the directory.
-        let dir = path.trim_end_matches(dir_name);
-
-        // Get the directory entry.
-        let dir_entry = self.root_dir.get_entry(dir)?;
-
-        // Get the directory entry.
-        let dir_entry = dir_entry.get_entry(dir_name)?;
-
-        // Check if the directory entry is a directory.
-        if dir_entry.attributes & DIRECTORY == 0 {
-            // Return `None`.
-            return None;
-        }
-
-        // Get the first cluster.
-        let first_cluster = dir_entry.first_cluster;
-
-        // Get the files.
-        let files = self.get_files(first_cluster)?;
+    /// * `Result<Vec<File>, Error>` - The files in the directory.
+    ///
+    /// # Errors
+    ///
+    /// * If the directory doesn't exist.
+    /// * If the path doesn't point to a directory.
+    /// * If the device fails to read.
+    pub fn read_dir(&self, path: &str) -> Result<Vec<File>, Error> {
+        let cluster = self.resolve_dir(path)?;
 
-        // Return the files.
-        Some(files)
+        self.get_files(cluster)
     }
 
-    /// Gets the files in the specified cluster.
+    /// Gets the files in the directory starting at the specified cluster.
     ///
     /// # Arguments
     ///
-    /// * `cluster` - The cluster.
+    /// * `cluster` - The first cluster of the directory, or `0` for the root directory.
     ///
     /// # Returns
     ///
-    /// * If the cluster exists, the files.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn get_files(&self, cluster: u32) -> Option<Vec<File>> {
-        // Get the first cluster.
-        let mut cluster = cluster;
-
-        // Create the files vector.
-        let mut files = Vec::new();
-
-        // Loop until the cluster is `None`.
-        loop {
-            // Get the file entry.
-            let file_entry = self.get_file_entry(cluster)?;
-
-            // Get the file.
-            let file = self.root_dir.get_file(&file_entry)?;
+    /// * `Result<Vec<File>, Error>` - The files in the directory.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If a directory entry is malformed.
+    pub fn get_files(&self, cluster: u32) -> Result<Vec<File>, Error> {
+        let entries = self.read_dir_entries(cluster)?;
 
-            // Add the file to the files vector.
-            files.push(file);
+        Ok(entries.iter().map(File::from).collect())
+    }
 
-            // Get the next cluster.
-            let next_cluster = match self.fat.next_cluster(cluster) {
-                Some(next_cluster) => Some(next_cluster),
-                None => {
-                    // Return the files.
-                    return Some(files);
+    /// Gets the file entry for the specified path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DirectoryEntry, Error>` - The file entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the path is empty.
+    /// * If any component of the path doesn't exist.
+    /// * If any intermediate component of the path isn't a directory.
+    /// * If the device fails to read.
+    pub fn get_file_entry_from_path(&self, path: &str) -> Result<DirectoryEntry, Error> {
+        let mut entry: Option<DirectoryEntry> = None;
+
+        // Walk the components of the path, starting at the root directory.
+        for component in path.split('/').filter(|component| !component.is_empty()) {
+            let dir = match &entry {
+                None => 0,
+                Some(entry) if entry.is_dir() => entry.first_cluster,
+                Some(entry) => {
+                    return Err(Error::FileSystem(format!(
+                        "'{name}' is not a directory!",
+                        name = entry.name
+                    )))
                 }
             };
 
-            // Set the cluster to the next cluster.
-            cluster = next_cluster?;
+            entry = Some(
+                self.read_dir_entries(dir)?
+                    .into_iter()
+                    .find(|entry| entry.matches(component))
+                    .ok_or_else(|| {
+                        Error::FileSystem(format!("No such file or directory: '{path}'!"))
+                    })?,
+            );
         }
+
+        entry.ok_or_else(|| Error::FileSystem("Path must not be empty!".into()))
     }
 
-    /// Gets the file entry for the specified cluster.
+    /// Reads the contents of a file.
     ///
     /// # Arguments
     ///
-    /// * `cluster` - The cluster.
+    /// * `file` - The file to read.
     ///
     /// # Returns
     ///
-    /// * If the cluster exists, the file entry.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub const fn get_file_entry(&self, cluster: u32) -> Option<DirectoryEntry> {
-        // Get the sector.
-        let sector = self.boot_sector.reserved_sectors as u32
-            + self.boot_sector.fat_count as u32 * self.boot_sector.sectors_per_fat as u32
-            + (cluster - 2) * self.boot_sector.sectors_per_cluster as u32;
-
-        // Get the sector.
-        let sector = sector as usize;
-
-        // Get the sector.
-        let sector = unsafe { &*(sector as *const [u8; 512]) };
-
-        // Get the file entry.
-        let file_entry = DirectoryEntry::new(
-            "",
-            DIRECTORY,
-            [0; 10],
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            sector[0x1A] as u16 | ((sector[0x1B] as u16) << 8),
-            sector[0x1C] as u32
-                | ((sector[0x1D] as u32) << 8)
-                | ((sector[0x1E] as u32) << 16)
-                | ((sector[0x1F] as u32) << 24),
-            cluster,
-        );
+    /// * `Result<Vec<u8>, Error>` - The contents of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the cluster chain is shorter than the file size.
+    pub fn read(&self, file: &File) -> Result<Vec<u8>, Error> {
+        let mut data = vec![0; file.size as usize];
+        let read = self.read_at(file, 0, &mut data)?;
+
+        if read < data.len() {
+            return Err(Error::FileSystem(format!(
+                "Cluster chain of '{name}' is shorter than its size!",
+                name = file.name
+            )));
+        }
 
-        // Return the file entry.
-        Some(file_entry)
+        Ok(data)
     }
 
-    /// Gets the file entry for the specified path.
+    /// Reads part of a file.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path.
+    /// * `file` - The file to read.
+    /// * `offset` - The offset to start reading at.
+    /// * `buffer` - The buffer to read into.
     ///
     /// # Returns
     ///
-    /// * If the file entry exists, the file entry.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn get_file_entry_from_path(&self, path: &str) -> Option<DirectoryEntry> {
-        // Get the file name.
-        let file_name = path.split('/').last()?;
+    /// * `Result<usize, Error>` - The number of bytes read, which is `0` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the cluster chain is invalid.
+    pub fn read_at(&self, file: &File, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        let size = file.size as usize;
+        if offset >= size || file.first_cluster == 0 {
+            return Ok(0);
+        }
 
-        // Get the directory.
-        let dir = path.trim_end_matches(file_name);
+        let cluster_size = self.boot_sector.cluster_size();
+        let end = size.min(offset + buffer.len());
 
-        // Get the directory entry.
-        let dir_entry = self.root_dir.get_entry(dir)?;
+        // Skip the clusters before the offset.
+        let mut cluster = file.first_cluster;
+        for _ in 0..offset / cluster_size {
+            match self.fat.next_cluster(cluster)? {
+                Some(next_cluster) => cluster = next_cluster,
+                None => return Ok(0),
+            }
+        }
 
-        // Get the file entry.
-        let file_entry = dir_entry.get_entry(file_name)?;
+        let mut position = offset;
+        while position < end {
+            self.check_cluster(cluster)?;
+
+            // Read the sector holding the position.
+            let within = position % cluster_size;
+            let sector =
+                self.boot_sector.cluster_to_sector(cluster) + u32::try_from(within / SECTOR_SIZE)?;
+            let data = read_sectors(&*self.device, sector, 1)?;
+
+            let start = within % SECTOR_SIZE;
+            let count = (SECTOR_SIZE - start).min(end - position);
+            buffer[position - offset..position - offset + count]
+                .copy_from_slice(&data[start..start + count]);
+            position += count;
+
+            // Move on to the next cluster.
+            if position < end && position.is_multiple_of(cluster_size) {
+                match self.fat.next_cluster(cluster)? {
+                    Some(next_cluster) => cluster = next_cluster,
+                    None => break,
+                }
+            }
+        }
 
-        // Return the file entry.
-        Some(file_entry)
+        Ok(position - offset)
     }
-}
-
-/// A FAT file system boot sector.
-///
-/// # Fields
-///
-/// * `bytes_per_sector` - The number of bytes per sector.
-/// * `sectors_per_cluster` - The number of sectors per cluster.
-/// * `reserved_sectors` - The number of reserved sectors.
-/// * `fat_count` - The number of FAT tables.
-/// * `root_dir_entries` - The number of root directory entries.
-/// * `total_sectors` - The total number of sectors.
-/// * `sectors_per_fat` - The number of sectors per FAT.
-/// * `sectors_per_track` - The number of sectors per track.
-/// * `head_count` - The number of heads.
-/// * `hidden_sectors` - The number of hidden sectors.
-/// * `total_sectors_long` - The total number of sectors.
-#[derive(Debug, Clone, Copy)]
-pub struct BootSector {
-    pub bytes_per_sector: u16,
-    pub sectors_per_cluster: u8,
-    pub reserved_sectors: u16,
-    pub fat_count: u8,
-    pub root_dir_entries: u16,
-    pub total_sectors: u16,
-    pub sectors_per_fat: u16,
-    pub sectors_per_track: u16,
-    pub head_count: u16,
-    pub hidden_sectors: u32,
-    pub total_sectors_long: u32,
-}
 
-impl BootSector {
-    /// Creates a new FAT file system boot sector.
+    /// Creates an empty file.
     ///
     /// # Arguments
     ///
-    /// * `bytes_per_sector` - The number of bytes per sector.
-    /// * `sectors_per_cluster` - The number of sectors per cluster.
-    /// * `reserved_sectors` - The number of reserved sectors.
-    /// * `fat_count` - The number of FAT tables.
-    /// * `root_dir_entries` - The number of root directory entries.
-    /// * `total_sectors` - The total number of sectors.
-    /// * `sectors_per_fat` - The number of sectors per FAT.
-    /// * `sectors_per_track` - The number of sectors per track.
-    /// * `head_count` - The number of heads.
-    /// * `hidden_sectors` - The number of hidden sectors.
-    /// * `total_sectors_long` - The total number of sectors.
+    /// * `path` - The path of the file.
     ///
     /// # Returns
     ///
-    /// * The new FAT file system boot sector.
-    #[must_use]
-    pub const fn new(
-        bytes_per_sector: u16,
-        sectors_per_cluster: u8,
-        reserved_sectors: u16,
-        fat_count: u8,
-        root_dir_entries: u16,
-        total_sectors: u16,
-        sectors_per_fat: u16,
-        sectors_per_track: u16,
-        head_count: u16,
-        hidden_sectors: u32,
-        total_sectors_long: u32,
-    ) -> Self {
-        Self {
-            bytes_per_sector,
-            sectors_per_cluster,
-            reserved_sectors,
-            fat_count,
-            root_dir_entries,
-            total_sectors,
-            sectors_per_fat,
-            sectors_per_track,
-            head_count,
-            hidden_sectors,
-            total_sectors_long,
-        }
+    /// * `Result<File, Error>` - The new file.
+    ///
+    /// # Errors
+    ///
+    /// * If the parent directory doesn't exist.
+    /// * If the file already exists.
+    /// * If the name isn't a valid 8.3 name.
+    /// * If the directory is full.
+    /// * If the device fails to read or write.
+    pub fn create(&mut self, path: &str) -> Result<File, Error> {
+        let entry = self.create_entry(path, ARCHIVE, 0)?;
+
+        Ok(File::from(&entry))
     }
-}
-
-/// A FAT file system file allocation table.
-///
-/// # Fields
-///
-/// * `entries` - The entries.
-#[derive(Debug, Clone, Copy)]
-pub struct FatTable {
-    entries: [u32; 128],
-}
 
-impl FatTable {
-    /// Creates a new FAT file system file allocation table.
+    /// Creates an empty directory.
     ///
     /// # Arguments
     ///
-    /// * `entries` - The entries.
+    /// * `path` - The path of the directory.
     ///
     /// # Returns
     ///
-    /// * The new FAT file system file allocation table.
-    #[must_use]
-    pub const fn new(entries: [u32; 128]) -> Self {
-        Self { entries }
+    /// * `Result<File, Error>` - The new directory.
+    ///
+    /// # Errors
+    ///
+    /// * If the parent directory doesn't exist.
+    /// * If the directory already exists.
+    /// * If the name isn't a valid 8.3 name.
+    /// * If the volume or the parent directory is full.
+    /// * If the device fails to read or write.
+    pub fn mkdir(&mut self, path: &str) -> Result<File, Error> {
+        let (parent_path, _) = split_path(path)?;
+        let parent = self.resolve_dir(parent_path)?;
+
+        // Allocate the directory and add the `.` and `..` entries to it.
+        let cluster = self.allocate_cluster()?;
+        let mut dot = DirectoryEntry::new(*b".          ", DIRECTORY, cluster);
+        dot.location = EntryLocation::new(self.boot_sector.cluster_to_sector(cluster), 0);
+        let mut dot_dot = DirectoryEntry::new(*b"..         ", DIRECTORY, parent);
+        dot_dot.location = EntryLocation::new(dot.location.sector, DIRECTORY_ENTRY_SIZE);
+        self.write_entry(&dot)?;
+        self.write_entry(&dot_dot)?;
+
+        // Link the directory into its parent.
+        match self.create_entry(path, DIRECTORY, cluster) {
+            Ok(entry) => Ok(File::from(&entry)),
+            Err(why) => {
+                self.free_chain(cluster)?;
+
+                Err(why)
+            }
+        }
     }
 
-    /// Gets the next cluster in the chain.
+    /// Writes to a file, growing it if needed.
     ///
     /// # Arguments
     ///
-    /// * `cluster` - The current cluster.
+    /// * `path` - The path of the file.
+    /// * `offset` - The offset to start writing at.
+    /// * `data` - The data to write.
     ///
     /// # Returns
     ///
-    /// * The next cluster in the chain.
-    #[must_use]
-    pub const fn next_cluster(&self, cluster: u32) -> Option<u32> {
-        // Get the entry.
-        let entry = self.entries[cluster as usize];
-
-        // Check if the entry is valid.
-        if entry >= 0x0FFF_FFF8 {
-            // Return `None`.
-            return None;
+    /// * `Result<usize, Error>` - The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// * If the file doesn't exist or is a directory.
+    /// * If the file would grow beyond 4 GiB.
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * Writing past the end of the file fills the gap with zeros.
+    pub fn write_at(&mut self, path: &str, offset: usize, data: &[u8]) -> Result<usize, Error> {
+        let mut entry = self.get_file_entry_from_path(path)?;
+        if entry.is_dir() {
+            return Err(Error::FileSystem(format!("'{path}' is a directory!")));
         }
 
-        // Return the entry.
-        Some(entry)
-    }
-}
+        self.write_data(&mut entry, offset, data)?;
 
-/// A FAT file system root directory.
-///
-/// # Fields
-///
-/// * `entries` - The entries.
-#[derive(Debug, Clone)]
-pub struct RootDirectory {
-    entries: [DirectoryEntry; 512],
-}
+        entry.touch();
+        self.write_entry(&entry)?;
+
+        Ok(data.len())
+    }
 
-impl RootDirectory {
-    /// Creates a new FAT file system root directory.
+    /// Appends to a file.
     ///
     /// # Arguments
     ///
-    /// * `entries` - The entries.
+    /// * `path` - The path of the file.
+    /// * `data` - The data to append.
     ///
     /// # Returns
     ///
-    /// * The new FAT file system root directory.
-    #[must_use]
-    pub const fn new(entries: [DirectoryEntry; 512]) -> Self {
-        Self { entries }
+    /// * `Result<usize, Error>` - The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// * If the file doesn't exist or is a directory.
+    /// * If the file would grow beyond 4 GiB.
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    pub fn append(&mut self, path: &str, data: &[u8]) -> Result<usize, Error> {
+        let size = self.get_file_entry_from_path(path)?.file_size as usize;
+
+        self.write_at(path, size, data)
     }
 
-    /// Gets the directory entry for the specified path.
+    /// Truncates or extends a file to the specified size.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path.
+    /// * `path` - The path of the file.
+    /// * `size` - The new size of the file.
     ///
     /// # Returns
     ///
-    /// * If the directory entry exists, the directory entry.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn get_entry(&self, path: &str) -> Option<DirectoryEntry> {
-        // Check if the path is empty.
-        if path.is_empty() {
-            // Return the root directory.
-            return Some(DirectoryEntry::new(
-                "",
-                DIRECTORY,
-                [0; 10],
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-            ));
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file doesn't exist or is a directory.
+    /// * If the size is beyond 4 GiB.
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * Extending the file fills the new space with zeros.
+    pub fn truncate(&mut self, path: &str, size: usize) -> Result<(), Error> {
+        let mut entry = self.get_file_entry_from_path(path)?;
+        if entry.is_dir() {
+            return Err(Error::FileSystem(format!("'{path}' is a directory!")));
         }
 
-        // Get the directory name.
-        let dir_name = path.split('/').last()?;
+        if size >= entry.file_size as usize {
+            // Extending is writing nothing past the end.
+            self.write_data(&mut entry, size, &[])?;
+        } else {
+            let keep = size.div_ceil(self.boot_sector.cluster_size());
 
-        // Get the directory.
-        let dir = path.trim_end_matches(dir_name);
+            if keep == 0 {
+                // Release the whole chain.
+                if entry.first_cluster != 0 {
+                    self.free_chain(entry.first_cluster)?;
+                }
+
+                entry.set_first_cluster(0);
+            } else {
+                // Find the last cluster to keep, then cut the chain after it.
+                let mut last = entry.first_cluster;
+                for _ in 1..keep {
+                    last = self.fat.next_cluster(last)?.ok_or_else(|| {
+                        Error::FileSystem(format!("Cluster chain of '{path}' is too short!"))
+                    })?;
+                }
 
-        // Get the directory entry.
-        let dir_entry = self.get_entry(dir)?;
+                let rest = self.fat.next_cluster(last)?;
+                self.fat.set_entry(last, self.fat.end_of_chain())?;
+                if let Some(rest) = rest {
+                    self.free_chain(rest)?;
+                }
+            }
 
-        // Get the directory entry.
-        let dir_entry = dir_entry.get_entry(dir_name)?;
+            entry.file_size = u32::try_from(size)?;
+        }
 
-        // Return the directory entry.
-        Some(dir_entry)
+        entry.touch();
+        self.write_entry(&entry)
     }
 
-    /// Gets the file for the specified file entry.
+    /// Deletes a file or an empty directory.
     ///
     /// # Arguments
     ///
-    /// * `file_entry` - The file entry.
+    /// * `path` - The path of the file or directory.
     ///
     /// # Returns
     ///
-    /// * If the file exists, the file.
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn get_file(&self, file_entry: &DirectoryEntry) -> Option<File> {
-        // Check if the file is a directory.
-        if file_entry.attributes & DIRECTORY != 0 {
-            // Return `None`.
-            return None;
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file doesn't exist.
+    /// * If the directory isn't empty.
+    /// * If the file is read only.
+    /// * If the device fails to read or write.
+    pub fn delete(&mut self, path: &str) -> Result<(), Error> {
+        let entry = self.get_file_entry_from_path(path)?;
+
+        if entry.attributes & READ_ONLY != 0 {
+            return Err(Error::FileSystem(format!("'{path}' is read only!")));
         }
 
-        // Get the file name.
-        let file_name = file_entry.name.trim_end_matches(' ');
+        if entry.is_dir() && !self.read_dir_entries(entry.first_cluster)?.is_empty() {
+            return Err(Error::FileSystem(format!("'{path}' is not empty!")));
+        }
 
-        // Get the file size.
-        let file_size = file_entry.file_size;
+        // Mark the entry and its long file name as deleted before releasing its clusters.
+        for location in entry
+            .long_name_locations
+            .iter()
+            .chain(core::iter::once(&entry.location))
+        {
+            let mut sector = read_sectors(&*self.device, location.sector, 1)?;
+            sector[location.offset] = DELETED_ENTRY;
+            write_sectors(&*self.device, location.sector, &sector)?;
+        }
 
-        // Get the first cluster.
-        let first_cluster = file_entry.first_cluster;
+        if entry.first_cluster != 0 {
+            self.free_chain(entry.first_cluster)?;
+        }
 
-        // Return the file.
-        Some(File::new(file_name, file_size, first_cluster))
+        Ok(())
     }
-}
-
-/// A FAT file system directory entry.
-///
-/// # Fields
-///
-/// * `name` - The name.
-/// * `attributes` - The attributes.
-/// * `reserved` - The reserved bytes.
-/// * `creation_time_tenths` - The creation time tenths of a second.
-/// * `creation_time` - The creation time.
-/// * `creation_date` - The creation date.
-/// * `last_accessed` - The last accessed date.
-/// * `first_cluster_high` - The high 16 bits of the first cluster.
-/// * `last_modified_time` - The last modified time.
-/// * `last_modified_date` - The last modified date.
-/// * `first_cluster_low` - The low 16 bits of the first cluster.
-/// * `file_size` - The file size.
-/// * `first_cluster` - The first cluster.
-#[derive(Debug, Clone, Copy, Default)]
-pub struct DirectoryEntry {
-    pub name: &'static str,
-    pub attributes: u8,
-    pub reserved: [u8; 10],
-    pub creation_time_tenths: u8,
-    pub creation_time: u16,
-    pub creation_date: u16,
-    pub last_accessed: u16,
-    pub first_cluster_high: u16,
-    pub last_modified_time: u16,
-    pub last_modified_date: u16,
-    pub first_cluster_low: u16,
-    pub file_size: u32,
-    pub first_cluster: u32,
-}
 
-impl DirectoryEntry {
-    /// Creates a new FAT file system directory entry.
+    /// Gets the first cluster of the directory at the specified path.
     ///
     /// # Arguments
     ///
-    /// * `name` - The name.
-    /// * `attributes` - The attributes.
-    /// * `reserved` - The reserved bytes.
-    /// * `creation_time_tenths` - The creation time tenths of a second.
-    /// * `creation_time` - The creation time.
-    /// * `creation_date` - The creation date.
-    /// * `last_accessed` - The last accessed date.
-    /// * `first_cluster_high` - The high 16 bits of the first cluster.
-    /// * `last_modified_time` - The last modified time.
-    /// * `last_modified_date` - The last modified date.
-    /// * `first_cluster_low` - The low 16 bits of the first cluster.
-    /// * `file_size` - The file size.
-    /// * `first_cluster` - The first cluster.
+    /// * `path` - The path of the directory.
     ///
     /// # Returns
     ///
-    /// * The new FAT file system directory entry.
-    #[must_use]
-    pub const fn new(
-        name: &'static str,
-        attributes: u8,
-        reserved: [u8; 10],
-        creation_time_tenths: u8,
-        creation_time: u16,
-        creation_date: u16,
-        last_accessed: u16,
-        first_cluster_high: u16,
-        last_modified_time: u16,
-        last_modified_date: u16,
-        first_cluster_low: u16,
-        file_size: u32,
-        first_cluster: u32,
-    ) -> Self {
-        Self {
-            name,
-            attributes,
-            reserved,
-            creation_time_tenths,
-            creation_time,
-            creation_date,
-            last_accessed,
-            first_cluster_high,
-            last_modified_time,
-            last_modified_date,
-            first_cluster_low,
-            file_size,
-            first_cluster,
+    /// * `Result<u32, Error>` - The first cluster of the directory, or `0` for the root directory.
+    ///
+    /// # Errors
+    ///
+    /// * If the directory doesn't exist.
+    /// * If the path doesn't point to a directory.
+    /// * If the device fails to read.
+    fn resolve_dir(&self, path: &str) -> Result<u32, Error> {
+        // Check if the path is the root directory.
+        if path.split('/').all(str::is_empty) {
+            return Ok(0);
+        }
+
+        let entry = self.get_file_entry_from_path(path)?;
+        if !entry.is_dir() {
+            return Err(Error::FileSystem(format!("'{path}' is not a directory!")));
         }
+
+        Ok(entry.first_cluster)
     }
 
-    /// Gets the directory entry for the specified path.
+    /// Creates a directory entry.
     ///
     /// # Arguments
     ///
-    /// * `path` - The path.
+    /// * `path` - The path of the entry.
+    /// * `attributes` - The attributes of the entry.
+    /// * `first_cluster` - The first cluster of the entry.
     ///
     /// # Returns
     ///
-    /// * If the directory entry exists, the directory entry
-    /// * Otherwise, `None`.
-    #[must_use]
-    pub fn get_entry(&self, path: &str) -> Option<Self> {
-        // Check if the path is empty.
-        if path.is_empty() {
-            // Return the directory entry.
-            return Some(*self);
-        }
-
-        // Get the directory name.
-        let dir_name = path.split('/').last()?;
-
-        // Get the directory.
-        let dir = path.trim_end_matches(dir_name);
+    /// * `Result<DirectoryEntry, Error>` - The new entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the parent directory doesn't exist.
+    /// * If the entry already exists.
+    /// * If the name isn't a valid long file name.
+    /// * If the directory is full.
+    /// * If the device fails to read or write.
+    ///
+    /// # Notes
+    ///
+    /// * Names that aren't stored exactly by their 8.3 short name get long file name entries.
+    fn create_entry(
+        &mut self,
+        path: &str,
+        attributes: u8,
+        first_cluster: u32,
+    ) -> Result<DirectoryEntry, Error> {
+        let (parent_path, name) = split_path(path)?;
+        let parent = self.resolve_dir(parent_path)?;
 
-        // Check if the directory name is `.`.
-        if dir_name == "." {
-            // Return the directory entry.
-            return Some(*self);
+        let entries = self.read_dir_entries(parent)?;
+        if entries.iter().any(|entry| entry.matches(name)) {
+            return Err(Error::FileSystem(format!("'{path}' already exists!")));
         }
 
-        // Check if the directory name is `..`.
-        if dir_name == ".." {
-            // Return the directory entry.
-            return Some(*self);
+        // Pick a short name that's unique in the directory.
+        let short_name = match encode_short_name(name) {
+            Ok(short_name) if !entries.iter().any(|entry| entry.short_name == short_name) => {
+                short_name
+            }
+            _ => generate_short_name(name, &entries)?,
+        };
+
+        // Only store a long file name if the short name loses information.
+        let long_name_entries = if decode_short_name(&short_name) == name {
+            Vec::new()
+        } else {
+            encode_long_name(name, lfn_checksum(&short_name))?
+        };
+
+        let mut slots = self.find_free_slots(parent, long_name_entries.len() + 1)?;
+        let mut entry = DirectoryEntry::new(short_name, attributes, first_cluster);
+        entry.name = name.into();
+        entry.location = slots.pop().unwrap_or_default();
+
+        // The long file name entries come first, followed by the short entry.
+        for (location, bytes) in slots.iter().zip(&long_name_entries) {
+            self.write_raw_entry(*location, bytes)?;
         }
+        self.write_entry(&entry)?;
+        entry.long_name_locations = slots;
 
-        // Check if the directory name is `LFN`.
-        if self.attributes & LFN != 0 {
-            // Return `None`.
-            return None;
+        Ok(entry)
+    }
+
+    /// Writes a directory entry back to its location.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn write_entry(&self, entry: &DirectoryEntry) -> Result<(), Error> {
+        self.write_raw_entry(entry.location, &entry.to_bytes())
+    }
+
+    /// Writes a raw directory entry to the specified location.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The location of the entry.
+    /// * `bytes` - The raw entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn write_raw_entry(
+        &self,
+        location: EntryLocation,
+        bytes: &[u8; DIRECTORY_ENTRY_SIZE],
+    ) -> Result<(), Error> {
+        let mut sector = read_sectors(&*self.device, location.sector, 1)?;
+
+        sector[location.offset..location.offset + DIRECTORY_ENTRY_SIZE].copy_from_slice(bytes);
+
+        write_sectors(&*self.device, location.sector, &sector)
+    }
+
+    /// Finds consecutive free slots in a directory, growing it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The first cluster of the directory, or `0` for the root directory.
+    /// * `count` - The number of consecutive slots needed.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<EntryLocation>, Error>` - The locations of the slots.
+    ///
+    /// # Errors
+    ///
+    /// * If the directory is the FAT12/16 root directory and is full.
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    fn find_free_slots(&mut self, dir: u32, count: usize) -> Result<Vec<EntryLocation>, Error> {
+        let mut slots = Vec::new();
+
+        for sector in self.dir_sectors(dir)? {
+            let data = read_sectors(&*self.device, sector, 1)?;
+
+            for (i, bytes) in data.chunks_exact(DIRECTORY_ENTRY_SIZE).enumerate() {
+                if bytes[0] == END_OF_DIRECTORY || bytes[0] == DELETED_ENTRY {
+                    slots.push(EntryLocation::new(sector, i * DIRECTORY_ENTRY_SIZE));
+
+                    if slots.len() == count {
+                        return Ok(slots);
+                    }
+                } else {
+                    slots.clear();
+                }
+            }
+        }
+
+        // The FAT12/16 root directory can't grow.
+        let mut last = match (dir, self.root_dir) {
+            (0, RootDirectory::Region { .. }) => {
+                return Err(Error::FileSystem("Root directory is full!".into()))
+            }
+            (0, RootDirectory::Cluster(cluster)) | (cluster, _) => cluster,
+        };
+        while let Some(next_cluster) = self.fat.next_cluster(last)? {
+            last = next_cluster;
+        }
+
+        // Grow the directory one zeroed cluster at a time.
+        while slots.len() < count {
+            let cluster = self.allocate_cluster()?;
+            self.fat.set_entry(last, cluster)?;
+            last = cluster;
+
+            let first_sector = self.boot_sector.cluster_to_sector(cluster);
+            for sector in
+                first_sector..first_sector + u32::from(self.boot_sector.sectors_per_cluster)
+            {
+                for offset in (0..SECTOR_SIZE).step_by(DIRECTORY_ENTRY_SIZE) {
+                    if slots.len() < count {
+                        slots.push(EntryLocation::new(sector, offset));
+                    }
+                }
+            }
+        }
+
+        Ok(slots)
+    }
+
+    /// Writes data to a file, allocating clusters as needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The entry of the file, whose size and first cluster are updated.
+    /// * `offset` - The offset to start writing at.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the file would grow beyond 4 GiB.
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    fn write_data(
+        &mut self,
+        entry: &mut DirectoryEntry,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        let size = entry.file_size as usize;
+        let end = offset + data.len();
+        let new_size = u32::try_from(size.max(end))
+            .map_err(|_| Error::FileSystem("Files can't grow beyond 4 GiB!".into()))?;
+
+        // Anything between the old end of the file and the offset is zeroed.
+        let start = offset.min(size);
+        if start < end {
+            let cluster_size = self.boot_sector.cluster_size();
+
+            // Freshly allocated clusters are already zeroed.
+            let mut fresh = entry.first_cluster == 0;
+            let mut cluster = if fresh {
+                let cluster = self.allocate_cluster()?;
+                entry.set_first_cluster(cluster);
+
+                cluster
+            } else {
+                entry.first_cluster
+            };
+            let mut index = 0;
+
+            let mut position = start;
+            while position < end {
+                // Walk to the cluster holding the position, growing the chain if needed.
+                while index < position / cluster_size {
+                    cluster = if let Some(next_cluster) = self.fat.next_cluster(cluster)? {
+                        fresh = false;
+
+                        next_cluster
+                    } else {
+                        let next_cluster = self.allocate_cluster()?;
+                        self.fat.set_entry(cluster, next_cluster)?;
+                        fresh = true;
+
+                        next_cluster
+                    };
+                    index += 1;
+                }
+                self.check_cluster(cluster)?;
+
+                let within = position % cluster_size;
+                let sector = self.boot_sector.cluster_to_sector(cluster)
+                    + u32::try_from(within / SECTOR_SIZE)?;
+                let sector_offset = within % SECTOR_SIZE;
+                let count = (SECTOR_SIZE - sector_offset).min(end - position);
+
+                if !fresh || position + count > offset {
+                    // Only read the sector if it's partially overwritten.
+                    let mut buffer = if count == SECTOR_SIZE {
+                        vec![0; SECTOR_SIZE]
+                    } else {
+                        read_sectors(&*self.device, sector, 1)?
+                    };
+
+                    for (i, byte) in buffer[sector_offset..sector_offset + count]
+                        .iter_mut()
+                        .enumerate()
+                    {
+                        *byte = (position + i)
+                            .checked_sub(offset)
+                            .map_or(0, |index| data[index]);
+                    }
+
+                    write_sectors(&*self.device, sector, &buffer)?;
+                }
+
+                position += count;
+            }
+        }
+
+        entry.file_size = new_size;
+
+        Ok(())
+    }
+
+    /// Allocates a zeroed cluster, marking it as the end of a chain.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The allocated cluster.
+    ///
+    /// # Errors
+    ///
+    /// * If the volume is full.
+    /// * If the device fails to read or write.
+    fn allocate_cluster(&mut self) -> Result<u32, Error> {
+        let cluster_count = self.boot_sector.cluster_count();
+
+        // Start searching at the FSInfo hint, if there is one.
+        let hint = self
+            .fs_info
+            .and_then(|fs_info| fs_info.next_free)
+            .filter(|&hint| hint >= 2 && hint < cluster_count + 2)
+            .unwrap_or(2);
+
+        for i in 0..cluster_count {
+            let cluster = (hint - 2 + i) % cluster_count + 2;
+            if self.fat.entry(cluster)? != 0 {
+                continue;
+            }
+
+            self.fat.set_entry(cluster, self.fat.end_of_chain())?;
+
+            // Zero the cluster.
+            let zeros = vec![0; SECTOR_SIZE];
+            let first_sector = self.boot_sector.cluster_to_sector(cluster);
+            for sector in
+                first_sector..first_sector + u32::from(self.boot_sector.sectors_per_cluster)
+            {
+                write_sectors(&*self.device, sector, &zeros)?;
+            }
+
+            if let Some(fs_info) = self.fs_info.as_mut() {
+                fs_info.next_free = Some(cluster + 1);
+                fs_info.free_count = fs_info.free_count.map(|count| count.saturating_sub(1));
+            }
+            self.write_fs_info()?;
+
+            return Ok(cluster);
+        }
+
+        Err(Error::FileSystem("Volume is full!".into()))
+    }
+
+    /// Frees every cluster in a chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The first cluster of the chain.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the chain points outside of the volume or loops.
+    /// * If the device fails to read or write.
+    fn free_chain(&mut self, cluster: u32) -> Result<(), Error> {
+        let mut cluster = cluster;
+
+        for freed in 1..=self.boot_sector.cluster_count() {
+            self.check_cluster(cluster)?;
+
+            let next_cluster = self.fat.next_cluster(cluster)?;
+            self.fat.set_entry(cluster, 0)?;
+
+            match next_cluster {
+                Some(next) => cluster = next,
+                None => {
+                    if let Some(fs_info) = self.fs_info.as_mut() {
+                        fs_info.free_count = fs_info.free_count.map(|count| count + freed);
+                    }
+
+                    return self.write_fs_info();
+                }
+            }
+        }
+
+        Err(Error::FileSystem("Cluster chain contains a loop!".into()))
+    }
+
+    /// Writes the FSInfo sector back to the device, if there is one.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read or write.
+    fn write_fs_info(&self) -> Result<(), Error> {
+        let Some(fs_info) = self.fs_info else {
+            return Ok(());
+        };
+
+        let mut sector = read_sectors(&*self.device, fs_info.sector, 1)?;
+        sector[488..492]
+            .copy_from_slice(&fs_info.free_count.unwrap_or(FS_INFO_UNKNOWN).to_le_bytes());
+        sector[492..496]
+            .copy_from_slice(&fs_info.next_free.unwrap_or(FS_INFO_UNKNOWN).to_le_bytes());
+
+        write_sectors(&*self.device, fs_info.sector, &sector)
+    }
+
+    /// Reads the directory entries of the directory starting at the specified cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The first cluster of the directory, or `0` for the root directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<DirectoryEntry>, Error>` - The directory entries.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If a directory entry is malformed.
+    fn read_dir_entries(&self, cluster: u32) -> Result<Vec<DirectoryEntry>, Error> {
+        let mut entries = Vec::new();
+        let mut long_name = None;
+
+        for sector in self.dir_sectors(cluster)? {
+            let data = read_sectors(&*self.device, sector, 1)?;
+
+            if !parse_directory(sector, &data, &mut entries, &mut long_name)? {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Gets the sectors of the directory starting at the specified cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The first cluster of the directory, or `0` for the root directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u32>, Error>` - The sectors of the directory, in order.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the chain points outside of the volume or loops.
+    fn dir_sectors(&self, cluster: u32) -> Result<Vec<u32>, Error> {
+        let cluster = match (cluster, self.root_dir) {
+            (0, RootDirectory::Region { sector, sectors }) => {
+                return Ok((sector..sector + sectors).collect())
+            }
+            (0, RootDirectory::Cluster(cluster)) | (cluster, _) => cluster,
+        };
+
+        let sectors_per_cluster = u32::from(self.boot_sector.sectors_per_cluster);
+        let sectors = self
+            .cluster_chain(cluster)?
+            .into_iter()
+            .flat_map(|cluster| {
+                let first_sector = self.boot_sector.cluster_to_sector(cluster);
+
+                first_sector..first_sector + sectors_per_cluster
+            })
+            .collect();
+
+        Ok(sectors)
+    }
+
+    /// Gets every cluster in the chain starting at the specified cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The first cluster of the chain.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u32>, Error>` - The clusters of the chain.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the chain points outside of the volume or loops.
+    fn cluster_chain(&self, cluster: u32) -> Result<Vec<u32>, Error> {
+        let mut clusters = Vec::new();
+        let mut cluster = cluster;
+
+        for _ in 0..self.boot_sector.cluster_count() {
+            self.check_cluster(cluster)?;
+            clusters.push(cluster);
+
+            // Get the next cluster.
+            match self.fat.next_cluster(cluster)? {
+                Some(next_cluster) => cluster = next_cluster,
+                None => return Ok(clusters),
+            }
+        }
+
+        Err(Error::FileSystem("Cluster chain contains a loop!".into()))
+    }
+
+    /// Checks that a cluster is a data cluster of the volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The cluster.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the cluster is outside of the data region.
+    fn check_cluster(&self, cluster: u32) -> Result<(), Error> {
+        if cluster < 2 || cluster >= self.boot_sector.cluster_count() + 2 {
+            return Err(Error::FileSystem(format!("Invalid cluster {cluster}!")));
+        }
+
+        Ok(())
+    }
+}
+
+/// The type of a FAT file system, determined by its cluster count.
+///
+/// # Variants
+///
+/// * `Fat12` - 12-bit FAT entries.
+/// * `Fat16` - 16-bit FAT entries.
+/// * `Fat32` - 28-bit FAT entries, stored in 32 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// A FAT file system boot sector.
+///
+/// # Fields
+///
+/// * `bytes_per_sector` - The number of bytes per sector.
+/// * `sectors_per_cluster` - The number of sectors per cluster.
+/// * `reserved_sectors` - The number of reserved sectors.
+/// * `fat_count` - The number of FAT tables.
+/// * `root_dir_entries` - The number of root directory entries.
+/// * `total_sectors` - The total number of sectors.
+/// * `sectors_per_fat` - The number of sectors per FAT.
+/// * `sectors_per_track` - The number of sectors per track.
+/// * `head_count` - The number of heads.
+/// * `hidden_sectors` - The number of hidden sectors.
+/// * `total_sectors_long` - The total number of sectors.
+///
+/// * `sectors_per_fat_long` - The number of sectors per FAT, on FAT32.
+/// * `extended_flags` - The FAT mirroring flags, on FAT32.
+/// * `root_cluster` - The first cluster of the root directory, on FAT32.
+/// * `fs_info_sector` - The sector of the FSInfo structure, on FAT32.
+#[derive(Debug, Clone, Copy)]
+pub struct BootSector {
+    pub bytes_per_sector: u16,
+    pub sectors_per_cluster: u8,
+    pub reserved_sectors: u16,
+    pub fat_count: u8,
+    pub root_dir_entries: u16,
+    pub total_sectors: u16,
+    pub sectors_per_fat: u16,
+    pub sectors_per_track: u16,
+    pub head_count: u16,
+    pub hidden_sectors: u32,
+    pub total_sectors_long: u32,
+
+    pub sectors_per_fat_long: u32,
+    pub extended_flags: u16,
+    pub root_cluster: u32,
+    pub fs_info_sector: u16,
+}
+
+impl BootSector {
+    /// Decodes the BIOS parameter block of a boot sector.
+    ///
+    /// # Arguments
+    ///
+    /// * `sector` - The raw boot sector.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The decoded boot sector.
+    ///
+    /// # Errors
+    ///
+    /// * If the boot signature is missing.
+    /// * If any of the BPB fields are invalid or unsupported.
+    ///
+    /// # Notes
+    ///
+    /// * The FAT32 extended BPB fields are only decoded if the 16-bit sectors per FAT field is zero.
+    pub fn parse(sector: &[u8]) -> Result<Self, Error> {
+        if sector.len() < SECTOR_SIZE {
+            return Err(Error::FileSystem("Boot sector is truncated!".into()));
+        }
+
+        // Check the boot signature.
+        if sector[510..512] != [0x55, 0xAA] {
+            return Err(Error::FileSystem("Missing boot sector signature!".into()));
+        }
+
+        let mut boot_sector = Self {
+            bytes_per_sector: u16::from_le_bytes(sector[11..13].try_into()?),
+            sectors_per_cluster: sector[13],
+            reserved_sectors: u16::from_le_bytes(sector[14..16].try_into()?),
+            fat_count: sector[16],
+            root_dir_entries: u16::from_le_bytes(sector[17..19].try_into()?),
+            total_sectors: u16::from_le_bytes(sector[19..21].try_into()?),
+            sectors_per_fat: u16::from_le_bytes(sector[22..24].try_into()?),
+            sectors_per_track: u16::from_le_bytes(sector[24..26].try_into()?),
+            head_count: u16::from_le_bytes(sector[26..28].try_into()?),
+            hidden_sectors: u32::from_le_bytes(sector[28..32].try_into()?),
+            total_sectors_long: u32::from_le_bytes(sector[32..36].try_into()?),
+
+            sectors_per_fat_long: 0,
+            extended_flags: 0,
+            root_cluster: 0,
+            fs_info_sector: 0,
+        };
+
+        // Decode the FAT32 extended BPB.
+        if boot_sector.sectors_per_fat == 0 {
+            boot_sector.sectors_per_fat_long = u32::from_le_bytes(sector[36..40].try_into()?);
+            boot_sector.extended_flags = u16::from_le_bytes(sector[40..42].try_into()?);
+            boot_sector.root_cluster = u32::from_le_bytes(sector[44..48].try_into()?);
+            boot_sector.fs_info_sector = u16::from_le_bytes(sector[48..50].try_into()?);
+
+            let version = u16::from_le_bytes(sector[42..44].try_into()?);
+            if version != 0 {
+                return Err(Error::FileSystem(format!(
+                    "Unsupported FAT32 version {version:#06X}!"
+                )));
+            }
+        }
+
+        boot_sector.validate()?;
+
+        Ok(boot_sector)
+    }
+
+    /// Checks that the boot sector describes a volume we can mount.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If any of the BPB fields are invalid or unsupported.
+    fn validate(&self) -> Result<(), Error> {
+        if usize::from(self.bytes_per_sector) != SECTOR_SIZE {
+            return Err(Error::FileSystem(format!(
+                "Unsupported sector size of {size} bytes!",
+                size = self.bytes_per_sector
+            )));
+        }
+
+        if !self.sectors_per_cluster.is_power_of_two() {
+            return Err(Error::FileSystem(format!(
+                "Invalid sectors per cluster: {count}!",
+                count = self.sectors_per_cluster
+            )));
+        }
+
+        if self.reserved_sectors == 0 || self.fat_count == 0 || self.sectors_per_fat() == 0 {
+            return Err(Error::FileSystem(
+                "Volume has no reserved sectors or no FATs!".into(),
+            ));
+        }
+
+        if self.total_sectors() <= self.first_data_sector() {
+            return Err(Error::FileSystem("Volume has no data region!".into()));
+        }
+
+        // Check that the FAT is large enough to describe every cluster.
+        let fat_bytes = u64::from(self.sectors_per_fat()) * u64::from(self.bytes_per_sector);
+        let needed_bytes = match self.fat_type() {
+            FatType::Fat12 => (u64::from(self.cluster_count()) + 2) * 3 / 2,
+            FatType::Fat16 => (u64::from(self.cluster_count()) + 2) * 2,
+            FatType::Fat32 => (u64::from(self.cluster_count()) + 2) * 4,
+        };
+        if fat_bytes < needed_bytes {
+            return Err(Error::FileSystem("FAT is too small for the volume!".into()));
+        }
+
+        match self.fat_type() {
+            FatType::Fat12 | FatType::Fat16 if self.root_dir_entries == 0 => Err(
+                Error::FileSystem("FAT12/16 volume has no root directory!".into()),
+            ),
+            FatType::Fat32 if self.root_dir_entries != 0 || self.sectors_per_fat != 0 => {
+                Err(Error::FileSystem("FAT32 volume has a FAT12/16 BPB!".into()))
+            }
+            FatType::Fat32 if self.cluster_count() > FAT32_MAX_CLUSTERS => Err(Error::FileSystem(
+                "Volume has too many clusters for FAT32!".into(),
+            )),
+            FatType::Fat32
+                if self.root_cluster < 2 || self.root_cluster >= self.cluster_count() + 2 =>
+            {
+                Err(Error::FileSystem(format!(
+                    "Invalid root cluster {cluster}!",
+                    cluster = self.root_cluster
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Gets the total number of sectors in the volume.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The total number of sectors.
+    #[must_use]
+    pub const fn total_sectors(&self) -> u32 {
+        if self.total_sectors == 0 {
+            self.total_sectors_long
+        } else {
+            self.total_sectors as u32
+        }
+    }
+
+    /// Gets the number of sectors per FAT.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of sectors per FAT.
+    #[must_use]
+    pub const fn sectors_per_fat(&self) -> u32 {
+        if self.sectors_per_fat == 0 {
+            self.sectors_per_fat_long
+        } else {
+            self.sectors_per_fat as u32
+        }
+    }
+
+    /// Gets the first sector of the root directory region.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The first sector of the root directory region.
+    ///
+    /// # Notes
+    ///
+    /// * On FAT32 the region is empty, as the root directory is a cluster chain.
+    #[must_use]
+    pub const fn root_dir_sector(&self) -> u32 {
+        self.reserved_sectors as u32 + self.fat_count as u32 * self.sectors_per_fat()
+    }
+
+    /// Gets the number of sectors occupied by the root directory region.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of root directory sectors.
+    #[must_use]
+    pub const fn root_dir_sectors(&self) -> u32 {
+        let bytes = self.root_dir_entries as u32 * DIRECTORY_ENTRY_SIZE as u32;
+
+        bytes.div_ceil(self.bytes_per_sector as u32)
+    }
+
+    /// Gets the first sector of the data region.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The first data sector.
+    #[must_use]
+    pub const fn first_data_sector(&self) -> u32 {
+        self.root_dir_sector() + self.root_dir_sectors()
+    }
+
+    /// Gets the number of data clusters in the volume.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The number of data clusters.
+    #[must_use]
+    pub const fn cluster_count(&self) -> u32 {
+        (self.total_sectors() - self.first_data_sector()) / self.sectors_per_cluster as u32
+    }
+
+    /// Gets the type of the file system.
+    ///
+    /// # Returns
+    ///
+    /// * `FatType` - The type of the file system.
+    ///
+    /// # Notes
+    ///
+    /// * The type is determined solely by the cluster count, as the specification mandates.
+    #[must_use]
+    pub const fn fat_type(&self) -> FatType {
+        let cluster_count = self.cluster_count();
+
+        if cluster_count <= FAT12_MAX_CLUSTERS {
+            FatType::Fat12
+        } else if cluster_count <= FAT16_MAX_CLUSTERS {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        }
+    }
+
+    /// Gets the index of the FAT that is in use.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The index of the active FAT.
+    ///
+    /// # Notes
+    ///
+    /// * FAT32 volumes may disable mirroring, in which case only one FAT is kept up to date.
+    #[must_use]
+    pub const fn active_fat(&self) -> u32 {
+        if self.mirroring_disabled() {
+            (self.extended_flags & 0x0F) as u32
+        } else {
+            0
+        }
+    }
+
+    /// Gets whether or not FAT mirroring is disabled.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not only the active FAT is kept up to date.
+    #[must_use]
+    pub const fn mirroring_disabled(&self) -> bool {
+        self.extended_flags & 0x80 != 0
+    }
+
+    /// Gets the size of a cluster, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The size of a cluster.
+    #[must_use]
+    pub const fn cluster_size(&self) -> usize {
+        self.sectors_per_cluster as usize * self.bytes_per_sector as usize
+    }
+
+    /// Gets the first sector of the specified cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The cluster.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The first sector of the cluster.
+    #[must_use]
+    pub const fn cluster_to_sector(&self, cluster: u32) -> u32 {
+        self.first_data_sector() + (cluster - 2) * self.sectors_per_cluster as u32
+    }
+}
+
+/// The FAT32 FSInfo sector, which caches allocation hints.
+///
+/// # Fields
+///
+/// * `sector` - The sector holding the structure.
+/// * `free_count` - The last known number of free clusters, if known.
+/// * `next_free` - The cluster to start searching for free clusters at, if known.
+#[derive(Debug, Clone, Copy)]
+pub struct FsInfo {
+    pub sector: u32,
+    pub free_count: Option<u32>,
+    pub next_free: Option<u32>,
+}
+
+impl FsInfo {
+    /// Decodes an FSInfo sector.
+    ///
+    /// # Arguments
+    ///
+    /// * `sector` - The sector number of the structure.
+    /// * `data` - The raw sector.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The decoded FSInfo sector.
+    ///
+    /// # Errors
+    ///
+    /// * If any of the signatures is missing.
+    pub fn parse(sector: u32, data: &[u8]) -> Result<Self, Error> {
+        if data.len() < SECTOR_SIZE {
+            return Err(Error::FileSystem("FSInfo sector is truncated!".into()));
+        }
+
+        let lead_signature = u32::from_le_bytes(data[0..4].try_into()?);
+        let struct_signature = u32::from_le_bytes(data[484..488].try_into()?);
+        let trail_signature = u32::from_le_bytes(data[508..512].try_into()?);
+        if lead_signature != FS_INFO_LEAD_SIGNATURE
+            || struct_signature != FS_INFO_STRUCT_SIGNATURE
+            || trail_signature != FS_INFO_TRAIL_SIGNATURE
+        {
+            return Err(Error::FileSystem("Invalid FSInfo signature!".into()));
+        }
+
+        let free_count = u32::from_le_bytes(data[488..492].try_into()?);
+        let next_free = u32::from_le_bytes(data[492..496].try_into()?);
+
+        Ok(Self {
+            sector,
+            free_count: (free_count != FS_INFO_UNKNOWN).then_some(free_count),
+            next_free: (next_free != FS_INFO_UNKNOWN).then_some(next_free),
+        })
+    }
+}
+
+/// A FAT file system file allocation table.
+///
+/// # Fields
+///
+/// * `device` - The block device holding the table.
+/// * `fat_type` - The type of the entries.
+/// * `copies` - The first sector of every copy of the table that is kept up to date.
+/// * `sectors` - The number of sectors in the table.
+/// * `cache` - The most recently accessed sector of the table.
+///
+/// # Notes
+///
+/// * Sectors are read from the device on demand, so the table never has to fit in memory.
+/// * Reads are served from the first copy, while writes go through to every copy.
+pub struct FatTable {
+    device: Arc<dyn BlockDevice>,
+    fat_type: FatType,
+    copies: Vec<u32>,
+    sectors: u32,
+    cache: Mutex<Option<(u32, Vec<u8>)>>,
+}
+
+impl FatTable {
+    /// Creates a new FAT file system file allocation table.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The block device holding the table.
+    /// * `fat_type` - The type of the entries.
+    /// * `copies` - The first sector of every copy of the table that is kept up to date.
+    /// * `sectors` - The number of sectors in the table.
+    ///
+    /// # Returns
+    ///
+    /// * The new FAT file system file allocation table.
+    #[must_use]
+    pub const fn new(
+        device: Arc<dyn BlockDevice>,
+        fat_type: FatType,
+        copies: Vec<u32>,
+        sectors: u32,
+    ) -> Self {
+        Self {
+            device,
+            fat_type,
+            copies,
+            sectors,
+            cache: Mutex::new(None),
+        }
+    }
+
+    /// Gets the raw FAT entry of the specified cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The cluster.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u32, Error>` - The entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the cluster is outside of the table.
+    /// * If the device fails to read.
+    pub fn entry(&self, cluster: u32) -> Result<u32, Error> {
+        let cluster = cluster as usize;
+
+        match self.fat_type {
+            FatType::Fat12 => {
+                // Entries are 1.5 bytes wide and packed in pairs.
+                let mut bytes = [0; 2];
+                self.read_bytes(cluster + cluster / 2, &mut bytes)?;
+                let value = u16::from_le_bytes(bytes);
+
+                Ok(u32::from(if cluster & 1 == 0 {
+                    value & 0x0FFF
+                } else {
+                    value >> 4
+                }))
+            }
+            FatType::Fat16 => {
+                let mut bytes = [0; 2];
+                self.read_bytes(cluster * 2, &mut bytes)?;
+
+                Ok(u32::from(u16::from_le_bytes(bytes)))
+            }
+            FatType::Fat32 => {
+                let mut bytes = [0; 4];
+                self.read_bytes(cluster * 4, &mut bytes)?;
+
+                // The top 4 bits are reserved.
+                Ok(u32::from_le_bytes(bytes) & 0x0FFF_FFFF)
+            }
+        }
+    }
+
+    /// Sets the raw FAT entry of the specified cluster in every copy of the table.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The cluster.
+    /// * `value` - The new entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the cluster is outside of the table.
+    /// * If the device fails to read or write.
+    pub fn set_entry(&self, cluster: u32, value: u32) -> Result<(), Error> {
+        let cluster = cluster as usize;
+
+        match self.fat_type {
+            FatType::Fat12 => {
+                // Keep the nibble shared with the neighbouring entry.
+                let offset = cluster + cluster / 2;
+                let mut bytes = [0; 2];
+                self.read_bytes(offset, &mut bytes)?;
+                let old = u16::from_le_bytes(bytes);
+                let value = u16::try_from(value & 0x0FFF)?;
+
+                let new = if cluster & 1 == 0 {
+                    (old & 0xF000) | value
+                } else {
+                    (old & 0x000F) | (value << 4)
+                };
+
+                self.write_bytes(offset, &new.to_le_bytes())
+            }
+            FatType::Fat16 => {
+                let value = u16::try_from(value & 0xFFFF)?;
+
+                self.write_bytes(cluster * 2, &value.to_le_bytes())
+            }
+            FatType::Fat32 => {
+                // Keep the reserved top 4 bits.
+                let mut bytes = [0; 4];
+                self.read_bytes(cluster * 4, &mut bytes)?;
+                let old = u32::from_le_bytes(bytes);
+                let new = (old & 0xF000_0000) | (value & 0x0FFF_FFFF);
+
+                self.write_bytes(cluster * 4, &new.to_le_bytes())
+            }
+        }
+    }
+
+    /// Gets the entry value marking the end of a chain.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The end of chain marker.
+    #[must_use]
+    pub const fn end_of_chain(&self) -> u32 {
+        match self.fat_type {
+            FatType::Fat12 => 0x0FFF,
+            FatType::Fat16 => 0xFFFF,
+            FatType::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+
+    /// Gets the next cluster in the chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The current cluster.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<u32>, Error>` - The next cluster in the chain, if the chain continues.
+    ///
+    /// # Errors
+    ///
+    /// * If the cluster is outside of the table.
+    /// * If the device fails to read.
+    pub fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, Error> {
+        // Get the entry.
+        let entry = self.entry(cluster)?;
+
+        // Check if the entry marks a free, bad or final cluster.
+        if entry < 2 || entry >= self.bad_cluster() {
+            return Ok(None);
+        }
+
+        // Return the entry.
+        Ok(Some(entry))
+    }
+
+    /// Gets the entry value marking a bad cluster, above which every value ends a chain.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The bad cluster marker.
+    const fn bad_cluster(&self) -> u32 {
+        match self.fat_type {
+            FatType::Fat12 => 0x0FF7,
+            FatType::Fat16 => 0xFFF7,
+            FatType::Fat32 => 0x0FFF_FFF7,
+        }
+    }
+
+    /// Reads bytes from the table, crossing sector boundaries if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset into the table.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the bytes are outside of the table.
+    /// * If the device fails to read.
+    fn read_bytes(&self, offset: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        let mut cache = self.cache.lock();
+
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            let (sector, index) = self.locate(offset + i)?;
+            let data = self.load(&mut cache, sector)?;
+
+            *byte = data[index];
+        }
+
+        Ok(())
+    }
+
+    /// Writes bytes to every copy of the table, crossing sector boundaries if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset into the table.
+    /// * `bytes` - The bytes to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the bytes are outside of the table.
+    /// * If the device fails to read or write.
+    fn write_bytes(&self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        let mut cache = self.cache.lock();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let (sector, _) = self.locate(offset + i)?;
+            let data = self.load(&mut cache, sector)?;
+
+            // Patch every byte falling into this sector.
+            while i < bytes.len() {
+                let (byte_sector, index) = self.locate(offset + i)?;
+                if byte_sector != sector {
+                    break;
+                }
+
+                data[index] = bytes[i];
+                i += 1;
+            }
+
+            // Write the sector through to every copy.
+            for start in &self.copies {
+                write_sectors(&*self.device, start + sector, data)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the sector of the table holding a byte, and the index of the byte in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset into the table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(u32, usize), Error>` - The sector relative to the start of the table, and the index.
+    ///
+    /// # Errors
+    ///
+    /// * If the byte is outside of the table.
+    fn locate(&self, offset: usize) -> Result<(u32, usize), Error> {
+        let sector = u32::try_from(offset / SECTOR_SIZE)?;
+        if sector >= self.sectors {
+            return Err(Error::FileSystem(format!(
+                "FAT offset {offset} is out of bounds!"
+            )));
+        }
+
+        Ok((sector, offset % SECTOR_SIZE))
+    }
+
+    /// Loads a sector of the table into the cache, unless it's already cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache` - The locked cache.
+    /// * `sector` - The sector relative to the start of the table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<&mut Vec<u8>, Error>` - The contents of the sector.
+    ///
+    /// # Errors
+    ///
+    /// * If the table has no copies.
+    /// * If the device fails to read.
+    fn load<'a>(
+        &self,
+        cache: &'a mut Option<(u32, Vec<u8>)>,
+        sector: u32,
+    ) -> Result<&'a mut Vec<u8>, Error> {
+        let data = match cache.take() {
+            Some((cached, data)) if cached == sector => data,
+            _ => {
+                let Some(start) = self.copies.first() else {
+                    return Err(Error::FileSystem("FAT has no copies!".into()));
+                };
+
+                read_sectors(&*self.device, start + sector, 1)?
+            }
+        };
+
+        Ok(&mut cache.insert((sector, data)).1)
+    }
+}
+
+/// The location of a FAT file system root directory.
+///
+/// # Variants
+///
+/// * `Region` - A fixed region of sectors before the data region, on FAT12/16.
+/// * `Cluster` - A cluster chain starting at the given cluster, on FAT32.
+#[derive(Debug, Clone, Copy)]
+pub enum RootDirectory {
+    Region { sector: u32, sectors: u32 },
+    Cluster(u32),
+}
+
+impl From<&BootSector> for RootDirectory {
+    /// Gets the root directory location described by a boot sector.
+    ///
+    /// # Arguments
+    ///
+    /// * `boot_sector` - The boot sector.
+    ///
+    /// # Returns
+    ///
+    /// * `RootDirectory` - The root directory location.
+    fn from(boot_sector: &BootSector) -> Self {
+        match boot_sector.fat_type() {
+            FatType::Fat12 | FatType::Fat16 => Self::Region {
+                sector: boot_sector.root_dir_sector(),
+                sectors: boot_sector.root_dir_sectors(),
+            },
+            FatType::Fat32 => Self::Cluster(boot_sector.root_cluster),
+        }
+    }
+}
+
+/// The location of a directory entry on the device.
+///
+/// # Fields
+///
+/// * `sector` - The sector holding the entry.
+/// * `offset` - The offset of the entry in the sector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryLocation {
+    pub sector: u32,
+    pub offset: usize,
+}
+
+impl EntryLocation {
+    /// Creates a new directory entry location.
+    ///
+    /// # Arguments
+    ///
+    /// * `sector` - The sector holding the entry.
+    /// * `offset` - The offset of the entry in the sector.
+    ///
+    /// # Returns
+    ///
+    /// * The new directory entry location.
+    #[must_use]
+    pub const fn new(sector: u32, offset: usize) -> Self {
+        Self { sector, offset }
+    }
+}
+
+/// A FAT file system directory entry.
+///
+/// # Fields
+///
+/// * `name` - The name.
+/// * `short_name` - The raw, space padded 8.3 name.
+/// * `attributes` - The attributes.
+/// * `reserved` - The reserved byte.
+/// * `creation_time_tenths` - The creation time tenths of a second.
+/// * `creation_time` - The creation time.
+/// * `creation_date` - The creation date.
+/// * `last_accessed` - The last accessed date.
+/// * `first_cluster_high` - The high 16 bits of the first cluster.
+/// * `last_modified_time` - The last modified time.
+/// * `last_modified_date` - The last modified date.
+/// * `first_cluster_low` - The low 16 bits of the first cluster.
+/// * `file_size` - The file size.
+/// * `first_cluster` - The first cluster.
+///
+/// * `location` - Where the entry is stored on the device.
+/// * `long_name_locations` - Where the long file name entries of the entry are stored on the device.
+///
+/// # Notes
+///
+/// * `name` is the long file name if the entry has one, and the decoded short name otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryEntry {
+    pub name: String,
+    pub short_name: [u8; 11],
+    pub attributes: u8,
+    pub reserved: u8,
+    pub creation_time_tenths: u8,
+    pub creation_time: u16,
+    pub creation_date: u16,
+    pub last_accessed: u16,
+    pub first_cluster_high: u16,
+    pub last_modified_time: u16,
+    pub last_modified_date: u16,
+    pub first_cluster_low: u16,
+    pub file_size: u32,
+    pub first_cluster: u32,
+
+    pub location: EntryLocation,
+    pub long_name_locations: Vec<EntryLocation>,
+}
+
+impl DirectoryEntry {
+    /// Creates a new, empty directory entry stamped with the current time.
+    ///
+    /// # Arguments
+    ///
+    /// * `short_name` - The raw, space padded 8.3 name.
+    /// * `attributes` - The attributes.
+    /// * `first_cluster` - The first cluster.
+    ///
+    /// # Returns
+    ///
+    /// * The new directory entry.
+    #[must_use]
+    pub fn new(short_name: [u8; 11], attributes: u8, first_cluster: u32) -> Self {
+        let (date, time, tenths) = timestamp();
+
+        let mut entry = Self {
+            name: decode_short_name(&short_name),
+            short_name,
+            attributes,
+            creation_time_tenths: tenths,
+            creation_time: time,
+            creation_date: date,
+            last_accessed: date,
+            last_modified_time: time,
+            last_modified_date: date,
+            ..Self::default()
+        };
+        entry.set_first_cluster(first_cluster);
+
+        entry
+    }
+
+    /// Decodes a raw 32-byte directory entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw directory entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The decoded directory entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry is shorter than [`DIRECTORY_ENTRY_SIZE`].
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < DIRECTORY_ENTRY_SIZE {
+            return Err(Error::FileSystem("Directory entry is truncated!".into()));
+        }
+
+        let short_name: [u8; 11] = bytes[0..11].try_into()?;
+        let first_cluster_high = u16::from_le_bytes(bytes[20..22].try_into()?);
+        let first_cluster_low = u16::from_le_bytes(bytes[26..28].try_into()?);
+
+        Ok(Self {
+            name: decode_short_name(&short_name),
+            short_name,
+            attributes: bytes[11],
+            reserved: bytes[12],
+            creation_time_tenths: bytes[13],
+            creation_time: u16::from_le_bytes(bytes[14..16].try_into()?),
+            creation_date: u16::from_le_bytes(bytes[16..18].try_into()?),
+            last_accessed: u16::from_le_bytes(bytes[18..20].try_into()?),
+            first_cluster_high,
+            last_modified_time: u16::from_le_bytes(bytes[22..24].try_into()?),
+            last_modified_date: u16::from_le_bytes(bytes[24..26].try_into()?),
+            first_cluster_low,
+            file_size: u32::from_le_bytes(bytes[28..32].try_into()?),
+            first_cluster: u32::from(first_cluster_high) << 16 | u32::from(first_cluster_low),
+
+            location: EntryLocation::default(),
+            long_name_locations: Vec::new(),
+        })
+    }
+
+    /// Encodes the entry into its raw 32-byte form.
+    ///
+    /// # Returns
+    ///
+    /// * `[u8; DIRECTORY_ENTRY_SIZE]` - The raw directory entry.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; DIRECTORY_ENTRY_SIZE] {
+        let mut bytes = [0; DIRECTORY_ENTRY_SIZE];
+
+        bytes[0..11].copy_from_slice(&self.short_name);
+        bytes[11] = self.attributes;
+        bytes[12] = self.reserved;
+        bytes[13] = self.creation_time_tenths;
+        bytes[14..16].copy_from_slice(&self.creation_time.to_le_bytes());
+        bytes[16..18].copy_from_slice(&self.creation_date.to_le_bytes());
+        bytes[18..20].copy_from_slice(&self.last_accessed.to_le_bytes());
+        bytes[20..22].copy_from_slice(&self.first_cluster_high.to_le_bytes());
+        bytes[22..24].copy_from_slice(&self.last_modified_time.to_le_bytes());
+        bytes[24..26].copy_from_slice(&self.last_modified_date.to_le_bytes());
+        bytes[26..28].copy_from_slice(&self.first_cluster_low.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.file_size.to_le_bytes());
+
+        bytes
+    }
+
+    /// Sets the first cluster of the entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `cluster` - The first cluster.
+    pub fn set_first_cluster(&mut self, cluster: u32) {
+        let [low_0, low_1, high_0, high_1] = cluster.to_le_bytes();
+
+        self.first_cluster = cluster;
+        self.first_cluster_low = u16::from_le_bytes([low_0, low_1]);
+        self.first_cluster_high = u16::from_le_bytes([high_0, high_1]);
+    }
+
+    /// Stamps the entry as modified at the current time.
+    pub fn touch(&mut self) {
+        let (date, time, _) = timestamp();
+
+        self.attributes |= ARCHIVE;
+        self.last_accessed = date;
+        self.last_modified_time = time;
+        self.last_modified_date = date;
+    }
+
+    /// Checks if the entry is a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the entry is a directory.
+    #[must_use]
+    pub const fn is_dir(&self) -> bool {
+        self.attributes & DIRECTORY != 0
+    }
+
+    /// Checks if the entry has the specified name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, compared case-insensitively.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the entry has the name, either as its long or its short name.
+    #[must_use]
+    pub fn matches(&self, name: &str) -> bool {
+        let lowercase = |name: &str| {
+            name.chars()
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        };
+
+        lowercase(&self.name) == lowercase(name)
+            || decode_short_name(&self.short_name).eq_ignore_ascii_case(name)
+    }
+}
+
+/// A FAT file system file.
+///
+/// # Fields
+///
+/// * `name` - The name.
+/// * `size` - The size.
+/// * `first_cluster` - The first cluster.
+/// * `attributes` - The attributes.
+#[derive(Debug, Clone)]
+pub struct File {
+    pub name: String,
+    pub size: u32,
+    pub first_cluster: u32,
+    pub attributes: u8,
+}
+
+impl File {
+    /// Creates a new FAT file system file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name.
+    /// * `size` - The size.
+    /// * `first_cluster` - The first cluster.
+    /// * `attributes` - The attributes.
+    ///
+    /// # Returns
+    ///
+    /// * The new FAT file system file.
+    #[must_use]
+    pub fn new(name: &str, size: u32, first_cluster: u32, attributes: u8) -> Self {
+        Self {
+            name: name.to_string(),
+            size,
+            first_cluster,
+            attributes,
+        }
+    }
+
+    /// Checks if the file is a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the file is a directory.
+    #[must_use]
+    pub const fn is_dir(&self) -> bool {
+        self.attributes & DIRECTORY != 0
+    }
+}
+
+impl From<&DirectoryEntry> for File {
+    /// Converts a directory entry into a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The directory entry.
+    ///
+    /// # Returns
+    ///
+    /// * `File` - The file.
+    fn from(entry: &DirectoryEntry) -> Self {
+        Self::new(
+            &entry.name,
+            entry.file_size,
+            entry.first_cluster,
+            entry.attributes,
+        )
+    }
+}
+
+/// A FAT file system mounted in the virtual file system.
+///
+/// # Fields
+///
+/// * `fat` - The FAT file system, shared with its inodes.
+pub struct FatFileSystem {
+    fat: Arc<Mutex<Fat>>,
+}
+
+impl FatFileSystem {
+    /// Creates a new mountable FAT file system.
+    ///
+    /// # Arguments
+    ///
+    /// * `fat` - The FAT file system.
+    ///
+    /// # Returns
+    ///
+    /// * The new mountable FAT file system.
+    #[must_use]
+    pub fn new(fat: Fat) -> Self {
+        Self {
+            fat: Arc::new(Mutex::new(fat)),
         }
+    }
+}
+
+impl FileSystem for FatFileSystem {
+    fn name(&self) -> &'static str {
+        match self.fat.lock().boot_sector().fat_type() {
+            FatType::Fat12 => "fat12",
+            FatType::Fat16 => "fat16",
+            FatType::Fat32 => "fat32",
+        }
+    }
+
+    fn root(&self) -> Result<Arc<dyn Inode>, Error> {
+        Ok(Arc::new(FatInode {
+            fat: self.fat.clone(),
+            path: "/".into(),
+        }))
+    }
 
-        // Return `None`.
-        None
+    fn sync(&self) -> Result<(), Error> {
+        self.fat.lock().sync()
     }
 }
 
-/// A FAT file system file.
+/// A file or directory of a mounted FAT file system.
 ///
 /// # Fields
 ///
+/// * `fat` - The FAT file system.
+/// * `path` - The path of the entry inside the file system.
+///
+/// # Notes
+///
+/// * Entries are addressed by path since FAT has no inode numbers.
+struct FatInode {
+    fat: Arc<Mutex<Fat>>,
+    path: String,
+}
+
+impl FatInode {
+    /// Gets the path of an entry of the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The path of the entry.
+    fn child_path(&self, name: &str) -> String {
+        format!("{parent}/{name}", parent = self.path.trim_end_matches('/'))
+    }
+
+    /// Creates the inode of an entry of the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<dyn Inode>` - The inode of the entry.
+    fn child(&self, name: &str) -> Arc<dyn Inode> {
+        Arc::new(Self {
+            fat: self.fat.clone(),
+            path: self.child_path(name),
+        })
+    }
+}
+
+impl Inode for FatInode {
+    fn metadata(&self) -> Result<Metadata, Error> {
+        if self.path == "/" {
+            return Ok(Metadata {
+                kind: InodeKind::Directory,
+                size: 0,
+                read_only: false,
+                mode: InodeKind::Directory.default_mode(false),
+            });
+        }
+
+        let entry = self.fat.lock().get_file_entry_from_path(&self.path)?;
+
+        let kind = if entry.is_dir() {
+            InodeKind::Directory
+        } else {
+            InodeKind::File
+        };
+        let read_only = entry.attributes & READ_ONLY != 0;
+
+        Ok(Metadata {
+            kind,
+            size: entry.file_size as usize,
+            read_only,
+            mode: kind.default_mode(read_only),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error> {
+        self.fat
+            .lock()
+            .get_file_entry_from_path(&self.child_path(name))?;
+
+        Ok(self.child(name))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error> {
+        let files = self.fat.lock().read_dir(&self.path)?;
+
+        Ok(files
+            .into_iter()
+            .map(|file| DirEntry {
+                kind: if file.is_dir() {
+                    InodeKind::Directory
+                } else {
+                    InodeKind::File
+                },
+                size: file.size as usize,
+                name: file.name,
+            })
+            .collect())
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        let fat = self.fat.lock();
+        let file = fat.read_file(&self.path)?;
+
+        fat.read_at(&file, offset, buffer)
+    }
+
+    fn write_at(&self, offset: usize, data: &[u8]) -> Result<usize, Error> {
+        self.fat.lock().write_at(&self.path, offset, data)
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Error> {
+        self.fat.lock().truncate(&self.path, size)
+    }
+
+    fn create(&self, name: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Error> {
+        let path = self.child_path(name);
+
+        match kind {
+            InodeKind::File => self.fat.lock().create(&path)?,
+            InodeKind::Directory => self.fat.lock().mkdir(&path)?,
+            InodeKind::Symlink => {
+                return Err(Error::FileSystem("FAT has no symbolic links!".into()))
+            }
+        };
+
+        Ok(self.child(name))
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Error> {
+        self.fat.lock().delete(&self.child_path(name))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Decodes an 8.3 short name into its `NAME.EXT` form.
+///
+/// # Arguments
+///
+/// * `raw` - The raw, space padded short name.
+///
+/// # Returns
+///
+/// * `String` - The decoded name.
+fn decode_short_name(raw: &[u8; 11]) -> String {
+    let mut raw = *raw;
+    if raw[0] == ESCAPED_DELETED_ENTRY {
+        raw[0] = DELETED_ENTRY;
+    }
+
+    let base = String::from_utf8_lossy(&raw[0..8]);
+    let extension = String::from_utf8_lossy(&raw[8..11]);
+    let (base, extension) = (base.trim_end(), extension.trim_end());
+
+    if extension.is_empty() {
+        base.into()
+    } else {
+        format!("{base}.{extension}")
+    }
+}
+
+/// Encodes a `NAME.EXT` name into a raw 8.3 short name.
+///
+/// # Arguments
+///
 /// * `name` - The name.
-/// * `size` - The size.
-/// * `first_cluster` - The first cluster.
-#[derive(Debug, Clone)]
-pub struct File {
-    pub name: String,
-    pub size: u32,
-    pub first_cluster: u32,
+///
+/// # Returns
+///
+/// * `Result<[u8; 11], Error>` - The raw, space padded short name.
+///
+/// # Errors
+///
+/// * If the name doesn't fit in 8.3 characters or contains invalid characters.
+fn encode_short_name(name: &str) -> Result<[u8; 11], Error> {
+    let invalid = || Error::FileSystem(format!("'{name}' is not a valid 8.3 name!"));
+
+    let (base, extension) = name.rsplit_once('.').unwrap_or((name, ""));
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut raw = [b' '; 11];
+    for (slot, character) in raw[0..8].iter_mut().zip(base.bytes()) {
+        *slot = short_name_character(character).ok_or_else(invalid)?;
+    }
+    for (slot, character) in raw[8..11].iter_mut().zip(extension.bytes()) {
+        *slot = short_name_character(character).ok_or_else(invalid)?;
+    }
+
+    // A leading `0xE5` would mark the entry as deleted.
+    if raw[0] == DELETED_ENTRY {
+        raw[0] = ESCAPED_DELETED_ENTRY;
+    }
+
+    Ok(raw)
 }
 
-impl File {
-    /// Creates a new FAT file system file.
+/// Converts a character into its short name form.
+///
+/// # Arguments
+///
+/// * `character` - The character.
+///
+/// # Returns
+///
+/// * If the character is allowed in short names, its upper case form.
+/// * Otherwise, `None`.
+const fn short_name_character(character: u8) -> Option<u8> {
+    match character {
+        b'a'..=b'z' => Some(character.to_ascii_uppercase()),
+        b'A'..=b'Z'
+        | b'0'..=b'9'
+        | b'!'
+        | b'#'
+        | b'$'
+        | b'%'
+        | b'&'
+        | b'\''
+        | b'('
+        | b')'
+        | b'-'
+        | b'@'
+        | b'^'
+        | b'_'
+        | b'`'
+        | b'{'
+        | b'}'
+        | b'~' => Some(character),
+        _ => None,
+    }
+}
+
+/// Splits a path into the path of its parent and its final component.
+///
+/// # Arguments
+///
+/// * `path` - The path.
+///
+/// # Returns
+///
+/// * `Result<(&str, &str), Error>` - The parent path and the name.
+///
+/// # Errors
+///
+/// * If the path has no final component.
+fn split_path(path: &str) -> Result<(&str, &str), Error> {
+    let path = path.trim_end_matches('/');
+    let (parent, name) = path.rsplit_once('/').unwrap_or(("", path));
+
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(Error::FileSystem(format!("Invalid path: '{path}'!")));
+    }
+
+    Ok((parent, name))
+}
+
+/// Gets the current time in the FAT format.
+///
+/// # Returns
+///
+/// * `(u16, u16, u8)` - The date, the time and the tenths of a second.
+///
+/// # Notes
+///
+/// * Dates are stored as `YYYYYYYMMMMDDDDD`, with years counted from 1980.
+/// * Years outside of 1980 to 2107 are clamped, since they don't fit in 7 bits.
+/// * Times are stored as `HHHHHMMMMMMSSSSS`, with seconds counted in steps of 2.
+fn timestamp() -> (u16, u16, u8) {
+    let now = clock::now();
+
+    let year = now.year.clamp(1980, 2107) - 1980;
+    let date = year << 9 | u16::from(now.month) << 5 | u16::from(now.day);
+    let time = u16::from(now.hour) << 11 | u16::from(now.minute) << 5 | u16::from(now.second / 2);
+    let tenths = (now.second % 2) * 100;
+
+    (date, time, tenths)
+}
+
+/// A long file name being assembled from its directory entries.
+///
+/// # Fields
+///
+/// * `checksum` - The checksum of the short name the long file name belongs to.
+/// * `sequence` - The sequence number of the last entry read.
+/// * `characters` - The UCS-2 characters of the name.
+/// * `locations` - Where the entries are stored on the device.
+///
+/// # Notes
+///
+/// * The entries are stored in reverse order, directly in front of the short entry.
+#[derive(Debug)]
+struct LongName {
+    checksum: u8,
+    sequence: u8,
+    characters: Vec<u16>,
+    locations: Vec<EntryLocation>,
+}
+
+impl LongName {
+    /// Adds a long file name entry to a long file name.
     ///
     /// # Arguments
     ///
-    /// * `name` - The name.
-    /// * `size` - The size.
-    /// * `first_cluster` - The first cluster.
+    /// * `long_name` - The long file name so far, if any.
+    /// * `bytes` - The raw long file name entry.
+    /// * `location` - Where the entry is stored on the device.
     ///
     /// # Returns
     ///
-    /// * The new FAT file system file.
-    #[must_use]
-    pub fn new(name: &str, size: u32, first_cluster: u32) -> Self {
-        Self {
-            name: name.to_string(),
-            size,
-            first_cluster,
+    /// * If the entry continues or starts a long file name, the long file name.
+    /// * Otherwise, `None`.
+    fn push(long_name: Option<Self>, bytes: &[u8], location: EntryLocation) -> Option<Self> {
+        let sequence = bytes[0] & LFN_SEQUENCE_MASK;
+        let checksum = bytes[13];
+
+        // The physically first entry holds the end of the name and starts a new long file name.
+        let mut long_name = if bytes[0] & LFN_LAST_ENTRY != 0 {
+            Self {
+                checksum,
+                sequence: sequence + 1,
+                characters: vec![0xFFFF; usize::from(sequence) * LFN_CHARACTERS],
+                locations: Vec::new(),
+            }
+        } else {
+            long_name?
+        };
+
+        if sequence == 0 || sequence + 1 != long_name.sequence || checksum != long_name.checksum {
+            return None;
+        }
+
+        let start = usize::from(sequence - 1) * LFN_CHARACTERS;
+        for (slot, offset) in long_name.characters[start..start + LFN_CHARACTERS]
+            .iter_mut()
+            .zip(LFN_CHARACTER_OFFSETS)
+        {
+            *slot = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+        }
+        long_name.sequence = sequence;
+        long_name.locations.push(location);
+
+        Some(long_name)
+    }
+
+    /// Completes a long file name.
+    ///
+    /// # Arguments
+    ///
+    /// * `short_name` - The raw short name of the entry following the long file name.
+    ///
+    /// # Returns
+    ///
+    /// * If the long file name is complete and belongs to the short name, the name and the locations of its entries.
+    /// * Otherwise, `None`.
+    fn finish(self, short_name: &[u8; 11]) -> Option<(String, Vec<EntryLocation>)> {
+        if self.sequence != 1 || self.checksum != lfn_checksum(short_name) {
+            return None;
+        }
+
+        // The name is terminated by a null character if it doesn't fill the last entry.
+        let length = self
+            .characters
+            .iter()
+            .position(|&character| character == 0)
+            .unwrap_or(self.characters.len());
+        let name = char::decode_utf16(self.characters[..length].iter().copied())
+            .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+
+        Some((name, self.locations))
+    }
+}
+
+/// Calculates the checksum of a short name stored in its long file name entries.
+///
+/// # Arguments
+///
+/// * `short_name` - The raw, space padded short name.
+///
+/// # Returns
+///
+/// * `u8` - The checksum.
+fn lfn_checksum(short_name: &[u8; 11]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, &byte| sum.rotate_right(1).wrapping_add(byte))
+}
+
+/// Encodes a name into long file name entries.
+///
+/// # Arguments
+///
+/// * `name` - The name.
+/// * `checksum` - The checksum of the short name the entries belong to.
+///
+/// # Returns
+///
+/// * `Result<Vec<[u8; DIRECTORY_ENTRY_SIZE]>, Error>` - The raw entries, in the order they're stored.
+///
+/// # Errors
+///
+/// * If the name is longer than 255 characters.
+/// * If the name contains characters that aren't allowed in long file names.
+fn encode_long_name(name: &str, checksum: u8) -> Result<Vec<[u8; DIRECTORY_ENTRY_SIZE]>, Error> {
+    let invalid = || Error::FileSystem(format!("'{name}' is not a valid long file name!"));
+
+    if name.ends_with(['.', ' '])
+        || name
+            .chars()
+            .any(|character| character < ' ' || "\"*/:<>?\\|".contains(character))
+    {
+        return Err(invalid());
+    }
+
+    let mut characters: Vec<u16> = name.encode_utf16().collect();
+    if characters.len() > LFN_MAX_LENGTH {
+        return Err(invalid());
+    }
+
+    // Terminate the name and pad it to fill the last entry.
+    if !characters.len().is_multiple_of(LFN_CHARACTERS) {
+        characters.push(0);
+    }
+    while !characters.len().is_multiple_of(LFN_CHARACTERS) {
+        characters.push(0xFFFF);
+    }
+
+    let count = characters.len() / LFN_CHARACTERS;
+    let mut entries = Vec::with_capacity(count);
+    for (index, chunk) in characters.chunks(LFN_CHARACTERS).enumerate().rev() {
+        let mut bytes = [0; DIRECTORY_ENTRY_SIZE];
+
+        bytes[0] = u8::try_from(index + 1)?;
+        if index + 1 == count {
+            bytes[0] |= LFN_LAST_ENTRY;
+        }
+        bytes[11] = LFN;
+        bytes[13] = checksum;
+        for (character, offset) in chunk.iter().zip(LFN_CHARACTER_OFFSETS) {
+            bytes[offset..offset + 2].copy_from_slice(&character.to_le_bytes());
+        }
+
+        entries.push(bytes);
+    }
+
+    Ok(entries)
+}
+
+/// Generates a unique `NAME~N.EXT` short name for a long file name.
+///
+/// # Arguments
+///
+/// * `name` - The long file name.
+/// * `entries` - The entries already in the directory.
+///
+/// # Returns
+///
+/// * `Result<[u8; 11], Error>` - The raw, space padded short name.
+///
+/// # Errors
+///
+/// * If every numeric tail is taken.
+fn generate_short_name(name: &str, entries: &[DirectoryEntry]) -> Result<[u8; 11], Error> {
+    // Characters that can't be stored in a short name become underscores.
+    let convert = |part: &str| -> Vec<u8> {
+        part.chars()
+            .filter(|&character| character != ' ' && character != '.')
+            .map(|character| {
+                u8::try_from(character)
+                    .ok()
+                    .and_then(short_name_character)
+                    .unwrap_or(b'_')
+            })
+            .collect()
+    };
+
+    let trimmed = name.trim_start_matches('.');
+    let (base, extension) = trimmed.rsplit_once('.').unwrap_or((trimmed, ""));
+    let base = convert(base);
+    let extension = convert(extension);
+
+    let mut raw = [b' '; 11];
+    for (slot, character) in raw[8..11].iter_mut().zip(&extension) {
+        *slot = *character;
+    }
+
+    for tail in 1..1_000_000u32 {
+        let tail = format!("~{tail}");
+        let length = base.len().min(8 - tail.len());
+
+        raw[0..8].fill(b' ');
+        raw[0..length].copy_from_slice(&base[..length]);
+        raw[length..length + tail.len()].copy_from_slice(tail.as_bytes());
+
+        // A leading `0xE5` would mark the entry as deleted.
+        if raw[0] == DELETED_ENTRY {
+            raw[0] = ESCAPED_DELETED_ENTRY;
+        }
+
+        if !entries.iter().any(|entry| entry.short_name == raw) {
+            return Ok(raw);
+        }
+    }
+
+    Err(Error::FileSystem(format!(
+        "No short name available for '{name}'!"
+    )))
+}
+
+/// Decodes the entries of a directory sector.
+///
+/// # Arguments
+///
+/// * `sector` - The sector number.
+/// * `data` - The raw sector.
+/// * `entries` - The entries to append to.
+///
+/// * `long_name` - The long file name being assembled, carried over between sectors.
+///
+/// # Returns
+///
+/// * `Result<bool, Error>` - Whether or not the directory continues past the sector.
+///
+/// # Errors
+///
+/// * If a directory entry is malformed.
+///
+/// # Notes
+///
+/// * Deleted entries, volume labels and the `.` and `..` entries are skipped.
+/// * Long file names that are out of sequence or whose checksum doesn't match the short name are ignored.
+fn parse_directory(
+    sector: u32,
+    data: &[u8],
+    entries: &mut Vec<DirectoryEntry>,
+    long_name: &mut Option<LongName>,
+) -> Result<bool, Error> {
+    for (i, bytes) in data.chunks_exact(DIRECTORY_ENTRY_SIZE).enumerate() {
+        let location = EntryLocation::new(sector, i * DIRECTORY_ENTRY_SIZE);
+
+        match bytes[0] {
+            END_OF_DIRECTORY => return Ok(false),
+            DELETED_ENTRY => {
+                *long_name = None;
+                continue;
+            }
+            _ => {}
+        }
+
+        let attributes = bytes[11];
+        if attributes & LFN == LFN {
+            *long_name = LongName::push(long_name.take(), bytes, location);
+            continue;
+        }
+
+        let pending = long_name.take();
+        if bytes[0] == b'.' || attributes & VOLUME_ID != 0 {
+            continue;
+        }
+
+        let mut entry = DirectoryEntry::parse(bytes)?;
+        entry.location = location;
+        if let Some((name, locations)) =
+            pending.and_then(|pending| pending.finish(&entry.short_name))
+        {
+            entry.name = name;
+            entry.long_name_locations = locations;
         }
+        entries.push(entry);
     }
+
+    Ok(true)
+}
+
+/// Reads consecutive sectors from a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device to read from.
+/// * `sector` - The first sector to read.
+/// * `count` - The number of sectors to read.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The contents of the sectors.
+///
+/// # Errors
+///
+/// * If the device fails to read.
+fn read_sectors(device: &dyn BlockDevice, sector: u32, count: u32) -> Result<Vec<u8>, Error> {
+    let mut data = vec![0; count as usize * SECTOR_SIZE];
+    device.read_blocks(u64::from(sector), &mut data)?;
+
+    Ok(data)
+}
+
+/// Writes consecutive sectors to a block device.
+///
+/// # Arguments
+///
+/// * `device` - The device to write to.
+/// * `sector` - The first sector to write.
+/// * `data` - The data to write, a multiple of the sector size long.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the device fails to write.
+fn write_sectors(device: &dyn BlockDevice, sector: u32, data: &[u8]) -> Result<(), Error> {
+    device.write_blocks(u64::from(sector), data)
 }
 
 /// Initializes the FAT file system.
 ///
+/// # Arguments
+///
+/// * `device` - The block device to mount.
+///
 /// # Returns
 ///
-/// * The FAT file system.
-#[must_use]
-pub fn init() -> Fat {
+/// * `Result<Fat, Error>` - The FAT file system.
+///
+/// # Errors
+///
+/// * If the device doesn't have 512-byte blocks.
+/// * If the device fails to read.
+/// * If the volume is malformed.
+pub fn init(device: Arc<dyn BlockDevice>) -> Result<Fat, Error> {
+    if device.block_size() != SECTOR_SIZE {
+        return Err(Error::FileSystem(format!(
+            "Block size {size} isn't supported!",
+            size = device.block_size()
+        )));
+    }
+
     // Get the boot sector.
-    let boot_sector = BootSector::new(
-        512,
-        1,
-        1,
-        2,
-        512,
-        0,
-        0,
-        0,
-        0,
-        0,
-        0,
-    );
+    let boot_sector = BootSector::parse(&read_sectors(&*device, 0, 1)?)?;
 
-    // Get the FAT table.
-    let fat = FatTable::new([0; 128]);
+    if u64::from(boot_sector.total_sectors()) > device.block_count() {
+        return Err(Error::FileSystem(
+            "Volume is larger than the device!".into(),
+        ));
+    }
+
+    // Get the FAT table, writing to every copy unless mirroring is disabled.
+    let fat_start = |index: u32| {
+        u32::from(boot_sector.reserved_sectors) + index * boot_sector.sectors_per_fat()
+    };
+    let mut copies = vec![fat_start(boot_sector.active_fat())];
+    if !boot_sector.mirroring_disabled() {
+        copies.extend(
+            (0..u32::from(boot_sector.fat_count))
+                .filter(|&index| index != boot_sector.active_fat())
+                .map(fat_start),
+        );
+    }
+    let fat = FatTable::new(
+        device.clone(),
+        boot_sector.fat_type(),
+        copies,
+        boot_sector.sectors_per_fat(),
+    );
 
     // Get the root directory.
-    let root_dir = RootDirectory::new([DirectoryEntry::default(); 512]);
+    let root_dir = RootDirectory::from(&boot_sector);
+
+    // Get the FSInfo sector, ignoring it if it's invalid since it only holds hints.
+    let fs_info = match boot_sector.fat_type() {
+        FatType::Fat32 if boot_sector.fs_info_sector != 0 => {
+            let sector = u32::from(boot_sector.fs_info_sector);
+
+            FsInfo::parse(sector, &read_sectors(&*device, sector, 1)?).ok()
+        }
+        _ => None,
+    };
 
     // Return the FAT file system.
-    Fat::new(boot_sector, fat, root_dir)
+    Ok(Fat::new(device, boot_sector, fat, root_dir, fs_info))
 }
@@ -0,0 +1,464 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+use crate::errors::Error;
+use crate::fs::vfs::{self, DirEntry, FileSystem, Inode, InodeKind, Metadata};
+use crate::println;
+
+/// The initial ramdisk packed by the build script.
+static ARCHIVE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/initrd.tar"));
+
+/// The size of a USTAR block, in bytes.
+const USTAR_BLOCK_SIZE: usize = 512;
+/// The magic of a USTAR header.
+const USTAR_MAGIC: &[u8] = b"ustar";
+/// The magic of a newc cpio header.
+const CPIO_MAGIC: &[u8] = b"070701";
+/// The size of a newc cpio header, in bytes.
+const CPIO_HEADER_SIZE: usize = 110;
+/// The name of the entry ending a newc cpio archive.
+const CPIO_TRAILER: &str = "TRAILER!!!";
+/// The mask of the file type bits of a cpio mode.
+const CPIO_TYPE_MASK: u32 = 0o170_000;
+/// The file type bits of a cpio directory.
+const CPIO_DIRECTORY: u32 = 0o040_000;
+/// The file type bits of a cpio regular file.
+const CPIO_FILE: u32 = 0o100_000;
+
+/// A read-only file system unpacked from a USTAR or newc cpio archive.
+///
+/// # Fields
+///
+/// * `root` - The root directory.
+///
+/// # Notes
+///
+/// * File contents are borrowed from the archive instead of being copied.
+pub struct Initrd {
+    root: Arc<InitrdInode>,
+}
+
+impl Initrd {
+    /// Unpacks an archive.
+    ///
+    /// # Arguments
+    ///
+    /// * `archive` - The USTAR or newc cpio archive.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The unpacked file system.
+    ///
+    /// # Errors
+    ///
+    /// * If the archive format isn't recognized.
+    /// * If the archive is malformed.
+    pub fn new(archive: &'static [u8]) -> Result<Self, Error> {
+        let mut root = Node::Directory(BTreeMap::new());
+
+        if archive.starts_with(CPIO_MAGIC) {
+            parse_cpio(archive, &mut root)?;
+        } else if archive.get(257..262) == Some(USTAR_MAGIC) {
+            parse_ustar(archive, &mut root)?;
+        } else {
+            return Err(Error::FileSystem(
+                "Initial ramdisk is neither a USTAR nor a cpio archive!".into(),
+            ));
+        }
+
+        Ok(Self {
+            root: root.freeze(),
+        })
+    }
+}
+
+impl FileSystem for Initrd {
+    fn name(&self) -> &'static str {
+        "initrd"
+    }
+
+    fn root(&self) -> Result<Arc<dyn Inode>, Error> {
+        Ok(self.root.clone())
+    }
+}
+
+/// A file or directory being unpacked.
+///
+/// # Variants
+///
+/// * `File` - The contents of a file.
+/// * `Directory` - The entries of a directory, by name.
+enum Node {
+    File(&'static [u8]),
+    Directory(BTreeMap<String, Node>),
+}
+
+impl Node {
+    /// Adds an entry to the tree, creating any missing parent directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the entry inside the archive.
+    /// * `node` - The entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If a parent of the entry is a file.
+    fn insert(&mut self, path: &str, node: Self) -> Result<(), Error> {
+        let mut components: Vec<_> = path
+            .split('/')
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect();
+
+        // The archive root itself carries no information.
+        let Some(name) = components.pop() else {
+            return Ok(());
+        };
+
+        let mut dir = self;
+        for component in components {
+            let Self::Directory(entries) = dir else {
+                return Err(Error::FileSystem(format!("'{path}' is inside a file!")));
+            };
+
+            dir = entries
+                .entry(component.into())
+                .or_insert_with(|| Self::Directory(BTreeMap::new()));
+        }
+
+        let Self::Directory(entries) = dir else {
+            return Err(Error::FileSystem(format!("'{path}' is inside a file!")));
+        };
+
+        // Directories may be listed after their contents, so they never replace existing ones.
+        match (entries.get(name), &node) {
+            (Some(Self::Directory(_)), Self::Directory(_)) => {}
+            _ => {
+                entries.insert(name.into(), node);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Turns the tree into inodes.
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<InitrdInode>` - The inode of the node.
+    fn freeze(self) -> Arc<InitrdInode> {
+        let inode = match self {
+            Self::File(data) => InitrdInode::File(data),
+            Self::Directory(entries) => InitrdInode::Directory(
+                entries
+                    .into_iter()
+                    .map(|(name, node)| (name, node.freeze()))
+                    .collect(),
+            ),
+        };
+
+        Arc::new(inode)
+    }
+}
+
+/// A file or directory of an initial ramdisk.
+///
+/// # Variants
+///
+/// * `File` - The contents of a file.
+/// * `Directory` - The entries of a directory, by name.
+enum InitrdInode {
+    File(&'static [u8]),
+    Directory(BTreeMap<String, Arc<InitrdInode>>),
+}
+
+impl Inode for InitrdInode {
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let (kind, size) = match self {
+            Self::File(data) => (InodeKind::File, data.len()),
+            Self::Directory(entries) => (InodeKind::Directory, entries.len()),
+        };
+
+        Ok(Metadata {
+            kind,
+            size,
+            read_only: true,
+            mode: kind.default_mode(true),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error> {
+        match self {
+            Self::File(_) => Err(Error::FileSystem("Not a directory!".into())),
+            Self::Directory(entries) => entries
+                .get(name)
+                .map(|entry| entry.clone() as Arc<dyn Inode>)
+                .ok_or_else(|| Error::FileSystem(format!("No such file or directory: '{name}'!"))),
+        }
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error> {
+        match self {
+            Self::File(_) => Err(Error::FileSystem("Not a directory!".into())),
+            Self::Directory(entries) => entries
+                .iter()
+                .map(|(name, entry)| {
+                    let metadata = entry.metadata()?;
+
+                    Ok(DirEntry {
+                        name: name.clone(),
+                        kind: metadata.kind,
+                        size: metadata.size,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        match self {
+            Self::Directory(_) => Err(Error::FileSystem("Is a directory!".into())),
+            Self::File(data) => {
+                let available = data.get(offset..).unwrap_or_default();
+                let count = available.len().min(buffer.len());
+                buffer[..count].copy_from_slice(&available[..count]);
+
+                Ok(count)
+            }
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Unpacks a USTAR archive.
+///
+/// # Arguments
+///
+/// * `archive` - The archive.
+/// * `root` - The root directory to unpack into.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If a header is truncated or its checksum doesn't match.
+/// * If the contents of an entry are truncated.
+///
+/// # Notes
+///
+/// * Entries other than regular files and directories, such as links, are skipped.
+fn parse_ustar(archive: &'static [u8], root: &mut Node) -> Result<(), Error> {
+    let mut offset = 0;
+
+    while let Some(header) = archive.get(offset..offset + USTAR_BLOCK_SIZE) {
+        // The archive ends with empty blocks.
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+
+        // The checksum is calculated with the checksum field filled with spaces.
+        let checksum = parse_octal(&header[148..156])?;
+        let sum: usize = header
+            .iter()
+            .enumerate()
+            .map(|(i, &byte)| {
+                if (148..156).contains(&i) {
+                    32
+                } else {
+                    usize::from(byte)
+                }
+            })
+            .sum();
+        if sum != checksum {
+            return Err(Error::FileSystem(format!(
+                "Bad USTAR header checksum at offset {offset}!"
+            )));
+        }
+
+        // Long paths are split between the name and the prefix fields.
+        let name = parse_string(&header[0..100]);
+        let prefix = parse_string(&header[345..500]);
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+
+        let size = parse_octal(&header[124..136])?;
+        let start = offset + USTAR_BLOCK_SIZE;
+        let data = archive
+            .get(start..start + size)
+            .ok_or_else(|| Error::FileSystem(format!("'{path}' is truncated!")))?;
+
+        match header[156] {
+            b'0' | 0 => root.insert(&path, Node::File(data))?,
+            b'5' => root.insert(&path, Node::Directory(BTreeMap::new()))?,
+            kind => println!(
+                "[WARN]: Skipping '{path}' of unsupported type '{kind}'",
+                kind = char::from(kind)
+            ),
+        }
+
+        offset = start + size.next_multiple_of(USTAR_BLOCK_SIZE);
+    }
+
+    Ok(())
+}
+
+/// Unpacks a newc cpio archive.
+///
+/// # Arguments
+///
+/// * `archive` - The archive.
+/// * `root` - The root directory to unpack into.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If a header is truncated or has the wrong magic.
+/// * If the name or contents of an entry are truncated.
+///
+/// # Notes
+///
+/// * Entries other than regular files and directories, such as links, are skipped.
+fn parse_cpio(archive: &'static [u8], root: &mut Node) -> Result<(), Error> {
+    let mut offset = 0;
+
+    loop {
+        let header = archive
+            .get(offset..offset + CPIO_HEADER_SIZE)
+            .ok_or_else(|| Error::FileSystem("cpio archive has no trailer!".into()))?;
+        if !header.starts_with(CPIO_MAGIC) {
+            return Err(Error::FileSystem(format!(
+                "Bad cpio header magic at offset {offset}!"
+            )));
+        }
+
+        // Every field is 8 hexadecimal digits, following the magic.
+        let field = |index: usize| parse_hex(&header[6 + index * 8..14 + index * 8]);
+        let mode = field(1)?;
+        let size = field(6)? as usize;
+        let name_size = field(11)? as usize;
+
+        // The name and the contents are both padded to 4 bytes.
+        let name_start = offset + CPIO_HEADER_SIZE;
+        let name = archive
+            .get(name_start..name_start + name_size)
+            .map(parse_string)
+            .ok_or_else(|| Error::FileSystem("cpio entry name is truncated!".into()))?;
+        let start = (name_start + name_size).next_multiple_of(4);
+        let data = archive
+            .get(start..start + size)
+            .ok_or_else(|| Error::FileSystem(format!("'{name}' is truncated!")))?;
+
+        if name == CPIO_TRAILER {
+            return Ok(());
+        }
+
+        match mode & CPIO_TYPE_MASK {
+            CPIO_FILE => root.insert(&name, Node::File(data))?,
+            CPIO_DIRECTORY => root.insert(&name, Node::Directory(BTreeMap::new()))?,
+            kind => println!("[WARN]: Skipping '{name}' of unsupported type {kind:o}"),
+        }
+
+        offset = (start + size).next_multiple_of(4);
+    }
+}
+
+/// Parses a null-terminated string field.
+///
+/// # Arguments
+///
+/// * `field` - The field.
+///
+/// # Returns
+///
+/// * `String` - The string, up to the first null byte.
+fn parse_string(field: &[u8]) -> String {
+    let length = field
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(field.len());
+
+    String::from_utf8_lossy(&field[..length]).into()
+}
+
+/// Parses an octal number field, padded with spaces or null bytes.
+///
+/// # Arguments
+///
+/// * `field` - The field.
+///
+/// # Returns
+///
+/// * `Result<usize, Error>` - The number.
+///
+/// # Errors
+///
+/// * If the field isn't a valid octal number.
+fn parse_octal(field: &[u8]) -> Result<usize, Error> {
+    let digits = core::str::from_utf8(field)
+        .map_err(|_| Error::FileSystem("Invalid octal field!".into()))?
+        .trim_matches(|character| character == ' ' || character == '\0');
+
+    usize::from_str_radix(digits, 8)
+        .map_err(|_| Error::FileSystem(format!("Invalid octal field: '{digits}'!")))
+}
+
+/// Parses a hexadecimal number field.
+///
+/// # Arguments
+///
+/// * `field` - The field.
+///
+/// # Returns
+///
+/// * `Result<u32, Error>` - The number.
+///
+/// # Errors
+///
+/// * If the field isn't a valid hexadecimal number.
+fn parse_hex(field: &[u8]) -> Result<u32, Error> {
+    let digits = core::str::from_utf8(field)
+        .map_err(|_| Error::FileSystem("Invalid hexadecimal field!".into()))?;
+
+    u32::from_str_radix(digits, 16)
+        .map_err(|_| Error::FileSystem(format!("Invalid hexadecimal field: '{digits}'!")))
+}
+
+/// Unpacks the initial ramdisk embedded into the kernel and mounts it at `/`.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the archive is malformed.
+/// * If a file system is already mounted at `/`.
+pub fn init() -> Result<(), Error> {
+    let initrd = Initrd::new(ARCHIVE)?;
+
+    println!(
+        "[INFO]: => initrd ({size} KiB)",
+        size = ARCHIVE.len() / 1024
+    );
+
+    vfs::mount("/", Arc::new(initrd))
+}
@@ -0,0 +1,687 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::convert::TryInto;
+
+use crate::dev::block::BlockDevice;
+use crate::errors::Error;
+use crate::fs::vfs::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+
+/// The sector holding the first volume descriptor.
+const FIRST_DESCRIPTOR_SECTOR: u64 = 16;
+/// The size of a volume descriptor, which is also the size of a sector.
+const DESCRIPTOR_SIZE: usize = 2048;
+/// The identifier of every volume descriptor.
+const STANDARD_IDENTIFIER: &[u8] = b"CD001";
+/// The type of the primary volume descriptor.
+const PRIMARY_DESCRIPTOR: u8 = 1;
+/// The type of the volume descriptor set terminator.
+const TERMINATOR_DESCRIPTOR: u8 = 255;
+/// The maximum number of volume descriptors read before giving up.
+const MAX_DESCRIPTORS: u64 = 32;
+/// The largest directory or path table read into memory, so a corrupt size can't exhaust the heap.
+const MAX_METADATA_SIZE: u32 = 128 * 1024;
+
+/// The offset of the root directory record in the primary volume descriptor.
+const ROOT_RECORD_OFFSET: usize = 156;
+/// The size of a directory record without its name.
+const RECORD_HEADER_SIZE: usize = 33;
+
+/// The directory flag of a directory record.
+const FLAG_DIRECTORY: u8 = 1 << 1;
+
+/// The check bytes of the SUSP `SP` entry.
+const SUSP_CHECK: [u8; 2] = [0xBE, 0xEF];
+/// The maximum number of SUSP continuation areas followed for a single record.
+const MAX_CONTINUATIONS: usize = 8;
+/// The flags of a Rock Ridge `NM` entry naming `.` or `..`.
+const NM_CURRENT_OR_PARENT: u8 = 0b110;
+
+/// A read-only ISO 9660 file system, as found on CD-ROMs.
+///
+/// # Fields
+///
+/// * `volume` - The mounted volume.
+///
+/// # Notes
+///
+/// * Rock Ridge names are used if the volume has them, and lowercased ISO 9660 names otherwise.
+/// * Files recorded in multiple extents are not supported.
+pub struct Iso9660 {
+    volume: Arc<Volume>,
+}
+
+impl Iso9660 {
+    /// Mounts an ISO 9660 volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - The device holding the volume.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The file system.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    /// * If the device has no primary volume descriptor.
+    /// * If the volume descriptor or path table is malformed.
+    pub fn new(device: Arc<dyn BlockDevice>) -> Result<Self, Error> {
+        let mut volume = Volume {
+            device,
+            block_size: DESCRIPTOR_SIZE,
+            volume_id: String::new(),
+            root: Record::default(),
+            path_table: Vec::new(),
+            susp_skip: None,
+        };
+
+        // Find the primary volume descriptor.
+        let mut descriptor = vec![0; DESCRIPTOR_SIZE];
+        for sector in FIRST_DESCRIPTOR_SECTOR..FIRST_DESCRIPTOR_SECTOR + MAX_DESCRIPTORS {
+            volume.read(sector * DESCRIPTOR_SIZE as u64, &mut descriptor)?;
+
+            if &descriptor[1..6] != STANDARD_IDENTIFIER {
+                return Err(Error::FileSystem("Not an ISO 9660 volume!".into()));
+            }
+
+            match descriptor[0] {
+                PRIMARY_DESCRIPTOR => break,
+                TERMINATOR_DESCRIPTOR => {
+                    return Err(Error::FileSystem(
+                        "ISO 9660 volume has no primary volume descriptor!".into(),
+                    ))
+                }
+                _ => {}
+            }
+        }
+        if descriptor[0] != PRIMARY_DESCRIPTOR {
+            return Err(Error::FileSystem(
+                "ISO 9660 volume has too many volume descriptors!".into(),
+            ));
+        }
+
+        // Both-endian fields are read from their little-endian half.
+        volume.block_size = usize::from(u16::from_le_bytes(descriptor[128..130].try_into()?));
+        if !volume.block_size.is_power_of_two() || volume.block_size < 512 {
+            return Err(Error::FileSystem(format!(
+                "Invalid ISO 9660 logical block size {size}!",
+                size = volume.block_size
+            )));
+        }
+
+        volume.volume_id = String::from_utf8_lossy(&descriptor[40..72])
+            .trim_end()
+            .to_string();
+        volume.root = Record::parse(&descriptor[ROOT_RECORD_OFFSET..ROOT_RECORD_OFFSET + 34])
+            .ok_or_else(|| Error::FileSystem("Invalid ISO 9660 root directory!".into()))?;
+
+        let path_table_size = u32::from_le_bytes(descriptor[132..136].try_into()?);
+        let path_table_block = u32::from_le_bytes(descriptor[140..144].try_into()?);
+        volume.path_table = volume.read_path_table(path_table_block, path_table_size)?;
+
+        volume.susp_skip = volume.detect_susp()?;
+
+        Ok(Self {
+            volume: Arc::new(volume),
+        })
+    }
+
+    /// Gets the volume identifier.
+    ///
+    /// # Returns
+    ///
+    /// * `&str` - The volume identifier.
+    #[must_use]
+    pub fn volume_id(&self) -> &str {
+        &self.volume.volume_id
+    }
+
+    /// Checks if the volume has Rock Ridge extensions.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the volume uses the System Use Sharing Protocol.
+    #[must_use]
+    pub fn rock_ridge(&self) -> bool {
+        self.volume.susp_skip.is_some()
+    }
+}
+
+impl FileSystem for Iso9660 {
+    fn name(&self) -> &'static str {
+        "iso9660"
+    }
+
+    fn root(&self) -> Result<Arc<dyn Inode>, Error> {
+        Ok(Arc::new(IsoInode {
+            volume: self.volume.clone(),
+            record: self.volume.root.clone(),
+        }))
+    }
+}
+
+/// A mounted ISO 9660 volume.
+///
+/// # Fields
+///
+/// * `device` - The device holding the volume.
+/// * `block_size` - The logical block size, in bytes.
+/// * `volume_id` - The volume identifier.
+/// * `root` - The record of the root directory.
+/// * `path_table` - The directories of the volume, from the little-endian path table.
+/// * `susp_skip` - The bytes skipped at the start of each system use area, if the volume uses SUSP.
+struct Volume {
+    device: Arc<dyn BlockDevice>,
+    block_size: usize,
+    volume_id: String,
+    root: Record,
+    path_table: Vec<PathTableEntry>,
+    susp_skip: Option<usize>,
+}
+
+/// An entry of a path table.
+///
+/// # Fields
+///
+/// * `name` - The ISO 9660 name of the directory.
+/// * `extent` - The first logical block of the directory.
+/// * `parent` - The number of the parent directory, starting at 1 for the root.
+#[derive(Debug, Clone)]
+struct PathTableEntry {
+    name: String,
+    extent: u32,
+    parent: u16,
+}
+
+/// A directory record.
+///
+/// # Fields
+///
+/// * `name` - The name of the file or directory, empty for `.` and `..`.
+/// * `extent` - The first logical block of the data.
+/// * `size` - The size of the data, in bytes.
+/// * `directory` - Whether or not the record is a directory.
+#[derive(Debug, Clone, Default)]
+struct Record {
+    name: String,
+    extent: u32,
+    size: u32,
+    directory: bool,
+}
+
+impl Record {
+    /// Decodes a directory record.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw record.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>` - The record, if it isn't truncated.
+    ///
+    /// # Notes
+    ///
+    /// * The name is the ISO 9660 name without its version, lowercased.
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let name_length = usize::from(*bytes.get(32)?);
+        let name = bytes.get(RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + name_length)?;
+
+        // The names of `.` and `..` are a single 0 or 1 byte.
+        let name = if name == [0] || name == [1] {
+            String::new()
+        } else {
+            let name = String::from_utf8_lossy(name);
+            let name = name.split(';').next().unwrap_or_default();
+
+            name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+        };
+
+        Some(Self {
+            name,
+            extent: u32::from_le_bytes(bytes.get(2..6)?.try_into().ok()?),
+            size: u32::from_le_bytes(bytes.get(10..14)?.try_into().ok()?),
+            directory: bytes.get(25)? & FLAG_DIRECTORY != 0,
+        })
+    }
+
+    /// Gets the system use area of a raw directory record.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw record.
+    /// * `skip` - The bytes skipped at the start of the area.
+    ///
+    /// # Returns
+    ///
+    /// * `&[u8]` - The system use area, empty if there's none.
+    fn system_use(bytes: &[u8], skip: usize) -> &[u8] {
+        let name_length = usize::from(bytes.get(32).copied().unwrap_or_default());
+
+        // The name is padded to an even length.
+        let start = RECORD_HEADER_SIZE + name_length + (1 - name_length % 2) + skip;
+
+        bytes.get(start..).unwrap_or_default()
+    }
+}
+
+impl Volume {
+    /// Reads bytes from the volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset of the bytes.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<(), Error> {
+        let block_size = self.device.block_size() as u64;
+
+        // Read the whole blocks covering the bytes.
+        let first = offset / block_size;
+        let skip = usize::try_from(offset % block_size)?;
+        let length = (skip + buffer.len()).next_multiple_of(block_size as usize);
+
+        let mut data = vec![0; length];
+        self.device.read_blocks(first, &mut data)?;
+        buffer.copy_from_slice(&data[skip..skip + buffer.len()]);
+
+        Ok(())
+    }
+
+    /// Reads the data of a record.
+    ///
+    /// # Arguments
+    ///
+    /// * `record` - The record.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, Error>` - The data.
+    ///
+    /// # Errors
+    ///
+    /// * If the data is too large, or goes past the end of the device.
+    /// * If the device fails to read.
+    fn read_extent(&self, record: &Record) -> Result<Vec<u8>, Error> {
+        self.read_metadata(record.extent, record.size)
+    }
+
+    /// Reads a directory or path table into memory.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first logical block of the data.
+    /// * `size` - The size of the data, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<u8>, Error>` - The data.
+    ///
+    /// # Errors
+    ///
+    /// * If the data is larger than [`MAX_METADATA_SIZE`], or goes past the end of the device.
+    /// * If the device fails to read.
+    fn read_metadata(&self, block: u32, size: u32) -> Result<Vec<u8>, Error> {
+        let offset = u64::from(block) * self.block_size as u64;
+        let device_size = self
+            .device
+            .block_count()
+            .saturating_mul(self.device.block_size() as u64);
+        if size > MAX_METADATA_SIZE || offset + u64::from(size) > device_size {
+            return Err(Error::FileSystem(format!(
+                "ISO 9660 metadata of {size} bytes at block {block} is out of range!"
+            )));
+        }
+
+        let mut data = vec![0; size as usize];
+        self.read(offset, &mut data)?;
+
+        Ok(data)
+    }
+
+    /// Reads the little-endian path table.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - The first logical block of the path table.
+    /// * `size` - The size of the path table, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<PathTableEntry>, Error>` - The directories, in order of their numbers.
+    ///
+    /// # Errors
+    ///
+    /// * If the path table is too large, or goes past the end of the device.
+    /// * If the device fails to read.
+    /// * If the path table is truncated.
+    fn read_path_table(&self, block: u32, size: u32) -> Result<Vec<PathTableEntry>, Error> {
+        let data = self.read_metadata(block, size)?;
+
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while offset + 8 <= data.len() {
+            let name_length = usize::from(data[offset]);
+            if name_length == 0 {
+                break;
+            }
+
+            let Some(name) = data.get(offset + 8..offset + 8 + name_length) else {
+                return Err(Error::FileSystem(
+                    "ISO 9660 path table is truncated!".into(),
+                ));
+            };
+
+            entries.push(PathTableEntry {
+                name: String::from_utf8_lossy(name).to_ascii_lowercase(),
+                extent: u32::from_le_bytes(data[offset + 2..offset + 6].try_into()?),
+                parent: u16::from_le_bytes(data[offset + 6..offset + 8].try_into()?),
+            });
+
+            // The name is padded to an even length.
+            offset += 8 + name_length + name_length % 2;
+        }
+
+        Ok(entries)
+    }
+
+    /// Checks if the volume uses the System Use Sharing Protocol, which Rock Ridge is built on.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<usize>, Error>` - The bytes skipped at the start of each system use area, if the volume uses SUSP.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    ///
+    /// # Notes
+    ///
+    /// * SUSP is announced by an `SP` entry in the `.` record of the root directory.
+    fn detect_susp(&self) -> Result<Option<usize>, Error> {
+        let mut block = vec![0; self.block_size];
+        self.read(
+            u64::from(self.root.extent) * self.block_size as u64,
+            &mut block,
+        )?;
+
+        let length = usize::from(block[0]);
+        let area = Record::system_use(block.get(..length).unwrap_or_default(), 0);
+
+        Ok(match area {
+            [b'S', b'P', _, _, check_0, check_1, skip, ..]
+                if [*check_0, *check_1] == SUSP_CHECK =>
+            {
+                Some(usize::from(*skip))
+            }
+            _ => None,
+        })
+    }
+
+    /// Gets the Rock Ridge name of a raw directory record.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The raw record.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<String>, Error>` - The name, if the record has `NM` entries.
+    ///
+    /// # Errors
+    ///
+    /// * If a continuation area fails to read.
+    fn rock_ridge_name(&self, bytes: &[u8]) -> Result<Option<String>, Error> {
+        let Some(skip) = self.susp_skip else {
+            return Ok(None);
+        };
+
+        let mut name: Option<Vec<u8>> = None;
+        let mut area = Record::system_use(bytes, skip).to_vec();
+
+        for _ in 0..MAX_CONTINUATIONS {
+            let mut continuation = None;
+
+            let mut offset = 0;
+            while offset + 4 <= area.len() {
+                let length = usize::from(area[offset + 2]);
+                let Some(entry) = area.get(offset..offset + length).filter(|_| length >= 4) else {
+                    break;
+                };
+
+                match &entry[0..2] {
+                    b"NM" if entry.len() >= 5 && entry[4] & NM_CURRENT_OR_PARENT == 0 => {
+                        name.get_or_insert_with(Vec::new)
+                            .extend_from_slice(&entry[5..]);
+                    }
+                    b"CE" if entry.len() >= 28 => {
+                        let block = u32::from_le_bytes(entry[4..8].try_into()?);
+                        let offset = u32::from_le_bytes(entry[12..16].try_into()?);
+                        let length = u32::from_le_bytes(entry[20..24].try_into()?);
+                        continuation = Some((block, offset, length));
+                    }
+                    b"ST" => break,
+                    _ => {}
+                }
+
+                offset += length;
+            }
+
+            // The area may continue in another block.
+            let Some((block, offset, length)) = continuation else {
+                break;
+            };
+
+            area = vec![0; length as usize];
+            self.read(
+                u64::from(block) * self.block_size as u64 + u64::from(offset),
+                &mut area,
+            )?;
+        }
+
+        Ok(name.map(|name| String::from_utf8_lossy(&name).into_owned()))
+    }
+
+    /// Reads the records of a directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory` - The record of the directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<Record>, Error>` - The records, without `.` and `..`.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    fn read_dir(&self, directory: &Record) -> Result<Vec<Record>, Error> {
+        let data = self.read_extent(directory)?;
+
+        let mut records = Vec::new();
+        for block in data.chunks(self.block_size) {
+            // Records never cross blocks, and the rest of a block is zeroed.
+            let mut offset = 0;
+            while let Some(&length) = block.get(offset).filter(|length| **length != 0) {
+                let Some(bytes) = block.get(offset..offset + usize::from(length)) else {
+                    break;
+                };
+
+                if let Some(mut record) =
+                    Record::parse(bytes).filter(|record| !record.name.is_empty())
+                {
+                    if let Some(name) = self.rock_ridge_name(bytes)? {
+                        record.name = name;
+                    }
+                    records.push(record);
+                }
+
+                offset += usize::from(length);
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Finds a subdirectory through the path table.
+    ///
+    /// # Arguments
+    ///
+    /// * `parent` - The first logical block of the parent directory.
+    /// * `name` - The ISO 9660 name of the subdirectory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Record>, Error>` - The record of the subdirectory, if it's in the path table.
+    ///
+    /// # Errors
+    ///
+    /// * If the device fails to read.
+    ///
+    /// # Notes
+    ///
+    /// * This avoids scanning the parent directory, but only works with ISO 9660 names.
+    fn find_directory(&self, parent: u32, name: &str) -> Result<Option<Record>, Error> {
+        let Some(number) = self
+            .path_table
+            .iter()
+            .position(|entry| entry.extent == parent)
+            .and_then(|index| u16::try_from(index + 1).ok())
+        else {
+            return Ok(None);
+        };
+
+        let Some(entry) = self
+            .path_table
+            .iter()
+            .find(|entry| entry.parent == number && entry.name == name.to_ascii_lowercase())
+        else {
+            return Ok(None);
+        };
+
+        // The size of a directory is only in its own `.` record.
+        let mut block = vec![0; self.block_size];
+        self.read(u64::from(entry.extent) * self.block_size as u64, &mut block)?;
+        let length = usize::from(block[0]);
+
+        Ok(block
+            .get(..length)
+            .and_then(Record::parse)
+            .map(|record| Record {
+                name: entry.name.clone(),
+                ..record
+            }))
+    }
+}
+
+/// A file or directory of an ISO 9660 volume.
+///
+/// # Fields
+///
+/// * `volume` - The volume.
+/// * `record` - The directory record of the file or directory.
+struct IsoInode {
+    volume: Arc<Volume>,
+    record: Record,
+}
+
+impl Inode for IsoInode {
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let kind = if self.record.directory {
+            InodeKind::Directory
+        } else {
+            InodeKind::File
+        };
+
+        Ok(Metadata {
+            kind,
+            size: self.record.size as usize,
+            read_only: true,
+            mode: kind.default_mode(true),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error> {
+        if !self.record.directory {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+
+        // Rock Ridge names aren't in the path table.
+        let record = match self.volume.susp_skip {
+            None => self.volume.find_directory(self.record.extent, name)?,
+            Some(_) => None,
+        };
+
+        let record = match record {
+            Some(record) => record,
+            None => self
+                .volume
+                .read_dir(&self.record)?
+                .into_iter()
+                .find(|record| record.name == name)
+                .ok_or_else(|| {
+                    Error::FileSystem(format!("No such file or directory: '{name}'!"))
+                })?,
+        };
+
+        Ok(Arc::new(Self {
+            volume: self.volume.clone(),
+            record,
+        }))
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error> {
+        if !self.record.directory {
+            return Err(Error::FileSystem("Not a directory!".into()));
+        }
+
+        Ok(self
+            .volume
+            .read_dir(&self.record)?
+            .into_iter()
+            .map(|record| DirEntry {
+                kind: if record.directory {
+                    InodeKind::Directory
+                } else {
+                    InodeKind::File
+                },
+                size: record.size as usize,
+                name: record.name,
+            })
+            .collect())
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        if self.record.directory {
+            return Err(Error::FileSystem("Is a directory!".into()));
+        }
+
+        let size = self.record.size as usize;
+        let count = size.saturating_sub(offset).min(buffer.len());
+        if count == 0 {
+            return Ok(0);
+        }
+
+        let start = u64::from(self.record.extent) * self.volume.block_size as u64;
+        self.volume
+            .read(start + offset as u64, &mut buffer[..count])?;
+
+        Ok(count)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
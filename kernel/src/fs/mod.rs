@@ -1,15 +1,220 @@
-use crate::fs::fat::Fat;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use crate::dev::block;
+use crate::errors::Error;
+use crate::fs::ext2::Ext2FileSystem;
+use crate::fs::fat::FatFileSystem;
+use crate::fs::iso9660::Iso9660;
+use crate::fs::tmpfs::TmpFs;
 use crate::println;
 
+pub mod ext2;
 pub mod fat;
+pub mod initrd;
+pub mod iso9660;
+pub mod tmpfs;
+pub mod vfs;
+
+pub use vfs::{
+    create, lookup, metadata, mount, mounts, normalize, open, read, read_dir, remove, rename, root,
+    sync, unmount, DirEntry, FileSystem, Inode, InodeKind, Metadata, OpenFile, OpenFlags, SeekFrom,
+};
+
+/// The directories other file systems are mounted on.
+const MOUNT_POINTS: [&str; 3] = ["/cdrom", "/mnt", "/tmp"];
+
+/// Mounts the root file system.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the root file system fails to mount.
+///
+/// # Notes
+///
+/// * The first partition or unpartitioned block device holding an ext2 file system is mounted at `/`.
+/// * Otherwise, the initial ramdisk is, see [`initrd::init`].
+pub fn init_root() -> Result<(), Error> {
+    println!("[INFO]: Looking for an ext2 root file system...");
+    match init_ext2() {
+        Ok(fs) => mount("/", Arc::new(fs)),
+        Err(why) => {
+            println!("[WARN]: {why}");
+            println!("[INFO]: Unpacking the initial ramdisk...");
+
+            initrd::init()
+        }
+    }
+}
 
 /// Initializes the file system.
-/// 
+///
 /// # Returns
-/// 
-/// * The FAT file system.
-#[must_use]
-pub fn init() -> Fat {
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If nothing is mounted at `/`, and a tmpfs fails to mount there.
+///
+/// # Notes
+///
+/// * The root file system is normally mounted by [`init_root`] first.
+/// * If nothing is mounted at `/`, a tmpfs is mounted there instead.
+/// * Missing mount points are created on the root file system, and skipped if it's read only.
+/// * The first FAT partition, or unpartitioned block device holding a FAT file system, is mounted at `/mnt`.
+/// * The first block device holding an ISO 9660 file system is mounted at `/cdrom`.
+/// * A tmpfs is mounted at `/tmp`.
+pub fn init() -> Result<(), Error> {
+    if metadata("/").is_err() {
+        println!("[WARN]: No root file system, mounting a tmpfs at / instead.");
+        mount("/", Arc::new(TmpFs::new()))?;
+    }
+
+    for mount_point in MOUNT_POINTS {
+        if metadata(mount_point).is_err() {
+            if let Err(why) = create(mount_point, InodeKind::Directory) {
+                println!("[WARN]: Failed to create the mount point {mount_point}: {why}");
+            }
+        }
+    }
+
     println!("[INFO]: Initializing the FAT file system...");
-    fat::init()
+    match init_fat() {
+        Ok(fs) => mount_or_warn("/mnt", Arc::new(fs)),
+        Err(why) => println!("[WARN]: {why}"),
+    }
+
+    println!("[INFO]: Initializing the ISO 9660 file system...");
+    match init_iso9660() {
+        Ok(fs) => mount_or_warn("/cdrom", Arc::new(fs)),
+        Err(why) => println!("[WARN]: {why}"),
+    }
+
+    println!("[INFO]: => tmpfs (/tmp)");
+    mount_or_warn("/tmp", Arc::new(TmpFs::new()));
+
+    Ok(())
+}
+
+/// Mounts a file system, warning if it fails, so one missing mount point doesn't prevent the others.
+///
+/// # Arguments
+///
+/// * `path` - The mount point.
+/// * `fs` - The file system.
+fn mount_or_warn(path: &str, fs: Arc<dyn FileSystem>) {
+    if let Err(why) = mount(path, fs) {
+        println!("[WARN]: Failed to mount {path}: {why}");
+    }
+}
+
+/// Finds the first partition or unpartitioned block device holding an ext2 file system.
+///
+/// # Returns
+///
+/// * `Result<Ext2FileSystem, Error>` - The ext2 file system.
+///
+/// # Errors
+///
+/// * If no block device holds a valid ext2 file system.
+fn init_ext2() -> Result<Ext2FileSystem, Error> {
+    let devices = block::devices();
+
+    for (name, device) in &devices {
+        if is_partitioned(name, &devices) {
+            continue;
+        }
+
+        // Most devices hold something else, so failures aren't worth a warning.
+        if let Ok(fs) = ext2::init(device.clone()) {
+            let mode = if fs.read_only() { ", read only" } else { "" };
+            println!("[INFO]: => ext2 ({name}{mode})");
+
+            return Ok(Ext2FileSystem::new(fs));
+        }
+    }
+
+    Err(Error::FileSystem("No ext2 file system found!".into()))
+}
+
+/// Finds the first partition or unpartitioned block device holding a FAT file system.
+///
+/// # Returns
+///
+/// * `Result<FatFileSystem, Error>` - The FAT file system.
+///
+/// # Errors
+///
+/// * If no block device holds a valid FAT file system.
+fn init_fat() -> Result<FatFileSystem, Error> {
+    let devices = block::devices();
+
+    for (name, device) in &devices {
+        if is_partitioned(name, &devices) {
+            continue;
+        }
+
+        match fat::init(device.clone()) {
+            Ok(fs) => {
+                println!("[INFO]: => FAT ({name})");
+
+                return Ok(FatFileSystem::new(fs));
+            }
+            Err(why) => println!("[WARN]: Skipping {name}: {why}"),
+        }
+    }
+
+    Err(Error::FileSystem("No FAT file system found!".into()))
+}
+
+/// Finds the first block device holding an ISO 9660 file system.
+///
+/// # Returns
+///
+/// * `Result<Iso9660, Error>` - The ISO 9660 file system.
+///
+/// # Errors
+///
+/// * If no block device holds a valid ISO 9660 file system.
+fn init_iso9660() -> Result<Iso9660, Error> {
+    for (name, device) in block::devices() {
+        // Most devices aren't CD-ROMs, so failures aren't worth a warning.
+        if let Ok(fs) = Iso9660::new(device) {
+            let extensions = if fs.rock_ridge() { ", Rock Ridge" } else { "" };
+            println!(
+                "[INFO]: => ISO 9660 ({name}, '{id}'{extensions})",
+                id = fs.volume_id()
+            );
+
+            return Ok(fs);
+        }
+    }
+
+    Err(Error::FileSystem("No ISO 9660 file system found!".into()))
+}
+
+/// Checks if a block device has partitions registered.
+///
+/// # Arguments
+///
+/// * `name` - The name of the device.
+/// * `devices` - The registered block devices.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the device is partitioned.
+///
+/// # Notes
+///
+/// * A partitioned disk starts with its partition table, not a file system.
+fn is_partitioned(name: &str, devices: &[(String, Arc<dyn block::BlockDevice>)]) -> bool {
+    let prefix = format!("{name}p");
+
+    devices.iter().any(|(other, _)| other.starts_with(&prefix))
 }
@@ -0,0 +1,379 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+use spin::Mutex;
+
+use crate::errors::Error;
+use crate::fs::vfs::{DirEntry, FileSystem, Inode, InodeKind, Metadata};
+
+/// A file system living entirely in the kernel heap.
+///
+/// # Fields
+///
+/// * `root` - The root directory.
+///
+/// # Notes
+///
+/// * Its contents are lost when it's dropped or the machine powers off.
+pub struct TmpFs {
+    root: Arc<TmpInode>,
+}
+
+impl TmpFs {
+    /// Creates a new, empty tmpfs.
+    ///
+    /// # Returns
+    ///
+    /// * The new tmpfs.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            root: TmpInode::new(InodeKind::Directory),
+        }
+    }
+}
+
+impl Default for TmpFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn name(&self) -> &'static str {
+        "tmpfs"
+    }
+
+    fn root(&self) -> Result<Arc<dyn Inode>, Error> {
+        Ok(self.root.clone())
+    }
+}
+
+/// The contents of a tmpfs inode.
+///
+/// # Variants
+///
+/// * `File` - The bytes of a file.
+/// * `Directory` - The entries of a directory, by name.
+enum Contents {
+    File(Vec<u8>),
+    Directory(BTreeMap<String, Arc<TmpInode>>),
+}
+
+/// A file or directory of a tmpfs.
+///
+/// # Fields
+///
+/// * `contents` - The contents of the inode.
+struct TmpInode {
+    contents: Mutex<Contents>,
+}
+
+impl TmpInode {
+    /// Creates a new, empty inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Arc<Self>` - The new inode.
+    fn new(kind: InodeKind) -> Arc<Self> {
+        // Symbolic links are rejected by `create`.
+        let contents = match kind {
+            InodeKind::File | InodeKind::Symlink => Contents::File(Vec::new()),
+            InodeKind::Directory => Contents::Directory(BTreeMap::new()),
+        };
+
+        Arc::new(Self {
+            contents: Mutex::new(contents),
+        })
+    }
+
+    /// Gets an entry of the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<Self>, Error>` - The entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    /// * If the entry doesn't exist.
+    fn entry(&self, name: &str) -> Result<Arc<Self>, Error> {
+        match &*self.contents.lock() {
+            Contents::File(_) => Err(not_a_directory()),
+            Contents::Directory(entries) => entries
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::FileSystem(format!("No such file or directory: '{name}'!"))),
+        }
+    }
+
+    /// Checks if the inode is, or contains, the specified inode.
+    ///
+    /// # Arguments
+    ///
+    /// * `inode` - The inode to look for.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the inode is or contains the inode.
+    fn contains(&self, inode: &Self) -> bool {
+        if core::ptr::eq(self, inode) {
+            return true;
+        }
+
+        match &*self.contents.lock() {
+            Contents::File(_) => false,
+            Contents::Directory(entries) => entries.values().any(|entry| entry.contains(inode)),
+        }
+    }
+}
+
+impl Inode for TmpInode {
+    fn metadata(&self) -> Result<Metadata, Error> {
+        let (kind, size) = match &*self.contents.lock() {
+            Contents::File(data) => (InodeKind::File, data.len()),
+            Contents::Directory(entries) => (InodeKind::Directory, entries.len()),
+        };
+
+        Ok(Metadata {
+            kind,
+            size,
+            read_only: false,
+            mode: kind.default_mode(false),
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error> {
+        Ok(self.entry(name)?)
+    }
+
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error> {
+        let entries = match &*self.contents.lock() {
+            Contents::File(_) => return Err(not_a_directory()),
+            Contents::Directory(entries) => entries.clone(),
+        };
+
+        entries
+            .into_iter()
+            .map(|(name, entry)| {
+                let metadata = entry.metadata()?;
+
+                Ok(DirEntry {
+                    name,
+                    kind: metadata.kind,
+                    size: metadata.size,
+                })
+            })
+            .collect()
+    }
+
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error> {
+        match &*self.contents.lock() {
+            Contents::Directory(_) => Err(is_a_directory()),
+            Contents::File(data) => {
+                let available = data.get(offset..).unwrap_or_default();
+                let count = available.len().min(buffer.len());
+                buffer[..count].copy_from_slice(&available[..count]);
+
+                Ok(count)
+            }
+        }
+    }
+
+    fn write_at(&self, offset: usize, bytes: &[u8]) -> Result<usize, Error> {
+        match &mut *self.contents.lock() {
+            Contents::Directory(_) => Err(is_a_directory()),
+            Contents::File(data) => {
+                // Writing past the end of the file fills the gap with zeros.
+                let end = offset + bytes.len();
+                if data.len() < end {
+                    data.resize(end, 0);
+                }
+                data[offset..end].copy_from_slice(bytes);
+
+                Ok(bytes.len())
+            }
+        }
+    }
+
+    fn truncate(&self, size: usize) -> Result<(), Error> {
+        match &mut *self.contents.lock() {
+            Contents::Directory(_) => Err(is_a_directory()),
+            Contents::File(data) => {
+                data.resize(size, 0);
+                data.shrink_to_fit();
+
+                Ok(())
+            }
+        }
+    }
+
+    fn create(&self, name: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Error> {
+        match &mut *self.contents.lock() {
+            Contents::File(_) => Err(not_a_directory()),
+            Contents::Directory(entries) => {
+                if entries.contains_key(name) {
+                    return Err(Error::FileSystem(format!("'{name}' already exists!")));
+                }
+                if kind == InodeKind::Symlink {
+                    return Err(Error::FileSystem("tmpfs has no symbolic links!".into()));
+                }
+
+                let entry = TmpInode::new(kind);
+                entries.insert(name.into(), entry.clone());
+
+                Ok(entry)
+            }
+        }
+    }
+
+    fn unlink(&self, name: &str) -> Result<(), Error> {
+        match &mut *self.contents.lock() {
+            Contents::File(_) => Err(not_a_directory()),
+            Contents::Directory(entries) => {
+                let entry = entries.get(name).ok_or_else(|| {
+                    Error::FileSystem(format!("No such file or directory: '{name}'!"))
+                })?;
+
+                if let Contents::Directory(children) = &*entry.contents.lock() {
+                    if !children.is_empty() {
+                        return Err(Error::FileSystem(format!("'{name}' is not empty!")));
+                    }
+                }
+
+                // Open files keep their inode alive until they're closed.
+                entries.remove(name);
+
+                Ok(())
+            }
+        }
+    }
+
+    fn rename(&self, name: &str, new_parent: &Arc<dyn Inode>, new_name: &str) -> Result<(), Error> {
+        let new_parent = new_parent
+            .as_any()
+            .downcast_ref::<Self>()
+            .ok_or_else(|| Error::FileSystem("Can't rename across file systems!".into()))?;
+
+        // Renaming within a directory only needs one lock.
+        if core::ptr::eq(self, new_parent) {
+            return match &mut *self.contents.lock() {
+                Contents::File(_) => Err(not_a_directory()),
+                Contents::Directory(entries) => {
+                    let entry = entries.get(name).cloned().ok_or_else(|| {
+                        Error::FileSystem(format!("No such file or directory: '{name}'!"))
+                    })?;
+                    if name == new_name {
+                        return Ok(());
+                    }
+
+                    if let Some(target) = entries.get(new_name) {
+                        check_replace(&entry, target)?;
+                    }
+                    entries.remove(name);
+                    entries.insert(new_name.into(), entry);
+
+                    Ok(())
+                }
+            };
+        }
+
+        let entry = self.entry(name)?;
+        if entry.contains(new_parent) {
+            return Err(Error::FileSystem(format!(
+                "Can't move '{name}' into itself!"
+            )));
+        }
+
+        // Lock both directories in a fixed order to avoid deadlocks.
+        let (mut source, mut destination) = if (self as *const Self) < (new_parent as *const Self) {
+            let source = self.contents.lock();
+            (source, new_parent.contents.lock())
+        } else {
+            let destination = new_parent.contents.lock();
+            (self.contents.lock(), destination)
+        };
+
+        match (&mut *source, &mut *destination) {
+            (Contents::Directory(source), Contents::Directory(destination)) => {
+                if let Some(target) = destination.get(new_name) {
+                    // The source directory is locked, and can't be empty anyway.
+                    if core::ptr::eq(&**target, self) {
+                        return Err(Error::FileSystem("Target directory is not empty!".into()));
+                    }
+
+                    check_replace(&entry, target)?;
+                }
+                source.remove(name);
+                destination.insert(new_name.into(), entry);
+
+                Ok(())
+            }
+            _ => Err(not_a_directory()),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Checks if an entry may replace the target of a rename.
+///
+/// # Arguments
+///
+/// * `entry` - The entry being renamed.
+/// * `target` - The entry it would replace.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the check.
+///
+/// # Errors
+///
+/// * If a file would replace a directory or the other way around.
+/// * If the target is a non-empty directory.
+fn check_replace(entry: &TmpInode, target: &TmpInode) -> Result<(), Error> {
+    if core::ptr::eq(entry, target) {
+        return Ok(());
+    }
+
+    match (&*entry.contents.lock(), &*target.contents.lock()) {
+        (Contents::File(_), Contents::File(_)) => Ok(()),
+        (Contents::Directory(_), Contents::Directory(children)) if children.is_empty() => Ok(()),
+        (Contents::Directory(_), Contents::Directory(_)) => {
+            Err(Error::FileSystem("Target directory is not empty!".into()))
+        }
+        (Contents::File(_), Contents::Directory(_)) => Err(is_a_directory()),
+        (Contents::Directory(_), Contents::File(_)) => Err(not_a_directory()),
+    }
+}
+
+/// Creates the error for a file used as a directory.
+///
+/// # Returns
+///
+/// * `Error` - The error.
+fn not_a_directory() -> Error {
+    Error::FileSystem("Not a directory!".into())
+}
+
+/// Creates the error for a directory used as a file.
+///
+/// # Returns
+///
+/// * `Error` - The error.
+fn is_a_directory() -> Error {
+    Error::FileSystem("Is a directory!".into())
+}
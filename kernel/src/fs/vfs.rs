@@ -0,0 +1,984 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::ops::BitOr;
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::errors::Error;
+
+lazy_static! {
+    /// The mounted file systems, ordered by mount point.
+    static ref MOUNTS: Mutex<Vec<Mount>> = Mutex::new(Vec::new());
+}
+
+/// Represents the kind of an inode.
+///
+/// # Variants
+///
+/// * `File` - A regular file.
+/// * `Directory` - A directory.
+/// * `Symlink` - A symbolic link, whose contents are the path it points to.
+///
+/// # Notes
+///
+/// * Paths aren't resolved through symbolic links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InodeKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+impl InodeKind {
+    /// Gets the permissions of an inode on a file system that doesn't store any.
+    ///
+    /// # Arguments
+    ///
+    /// * `read_only` - Whether or not the inode can be written to.
+    ///
+    /// # Returns
+    ///
+    /// * `u16` - The permission bits, such as `0o644`.
+    #[must_use]
+    pub const fn default_mode(self, read_only: bool) -> u16 {
+        let mode = match self {
+            Self::Directory => 0o755,
+            Self::File => 0o644,
+            Self::Symlink => 0o777,
+        };
+
+        if read_only {
+            mode & !0o222
+        } else {
+            mode
+        }
+    }
+}
+
+/// The metadata of an inode.
+///
+/// # Fields
+///
+/// * `kind` - The kind of the inode.
+/// * `size` - The size of the inode, in bytes.
+/// * `read_only` - Whether or not the inode can be written to.
+/// * `mode` - The permission bits of the inode, such as `0o644`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    pub kind: InodeKind,
+    pub size: usize,
+    pub read_only: bool,
+    pub mode: u16,
+}
+
+impl Metadata {
+    /// Checks if the inode is a directory.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the inode is a directory.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.kind == InodeKind::Directory
+    }
+}
+
+/// An entry of a directory listing.
+///
+/// # Fields
+///
+/// * `name` - The name of the entry.
+/// * `kind` - The kind of the entry.
+/// * `size` - The size of the entry, in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub kind: InodeKind,
+    pub size: usize,
+}
+
+/// A mountable file system.
+pub trait FileSystem: Send + Sync {
+    /// Gets the name of the file system driver.
+    ///
+    /// # Returns
+    ///
+    /// * `&'static str` - The name of the driver.
+    fn name(&self) -> &'static str;
+
+    /// Gets the root directory of the file system.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn Inode>, Error>` - The root directory.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system fails to read its root directory.
+    fn root(&self) -> Result<Arc<dyn Inode>, Error>;
+
+    /// Writes any buffered changes to the backing storage.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the backing storage fails to write.
+    fn sync(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A file or directory of a file system.
+///
+/// # Notes
+///
+/// * Operations that the file system doesn't support fail with a `FileSystem` error by default.
+pub trait Inode: Send + Sync {
+    /// Gets the metadata of the inode.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Metadata, Error>` - The metadata.
+    ///
+    /// # Errors
+    ///
+    /// * If the file system fails to read the inode.
+    fn metadata(&self) -> Result<Metadata, Error>;
+
+    /// Looks up an entry of the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry, never `.` or `..`.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn Inode>, Error>` - The entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    /// * If the entry doesn't exist.
+    fn lookup(&self, name: &str) -> Result<Arc<dyn Inode>, Error>;
+
+    /// Lists the entries of the directory.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<DirEntry>, Error>` - The entries, without `.` and `..`.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    fn read_dir(&self) -> Result<Vec<DirEntry>, Error>;
+
+    /// Reads from the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset to start reading at.
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes read, `0` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is a directory.
+    fn read_at(&self, offset: usize, buffer: &mut [u8]) -> Result<usize, Error>;
+
+    /// Writes to the file, growing it if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - The offset to start writing at.
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is a directory.
+    /// * If the file system is read only.
+    fn write_at(&self, offset: usize, data: &[u8]) -> Result<usize, Error> {
+        let _ = (offset, data);
+
+        Err(unsupported("write"))
+    }
+
+    /// Truncates or extends the file to the specified size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new size.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode is a directory.
+    /// * If the file system is read only.
+    fn truncate(&self, size: usize) -> Result<(), Error> {
+        let _ = size;
+
+        Err(unsupported("truncate"))
+    }
+
+    /// Creates an entry in the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    /// * `kind` - The kind of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Arc<dyn Inode>, Error>` - The new entry.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    /// * If the entry already exists.
+    /// * If the file system is read only.
+    /// * If the file system can't create entries of that kind.
+    fn create(&self, name: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Error> {
+        let _ = (name, kind);
+
+        Err(unsupported("create"))
+    }
+
+    /// Removes an entry from the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the inode isn't a directory.
+    /// * If the entry doesn't exist or is a non-empty directory.
+    /// * If the file system is read only.
+    fn unlink(&self, name: &str) -> Result<(), Error> {
+        let _ = name;
+
+        Err(unsupported("unlink"))
+    }
+
+    /// Moves an entry of the directory, replacing any existing target.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    /// * `new_parent` - The directory to move the entry to, on the same file system.
+    /// * `new_name` - The new name of the entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry doesn't exist.
+    /// * If a directory would be moved into itself.
+    /// * If the target can't be replaced.
+    /// * If the file system is read only.
+    fn rename(&self, name: &str, new_parent: &Arc<dyn Inode>, new_name: &str) -> Result<(), Error> {
+        let _ = (name, new_parent, new_name);
+
+        Err(unsupported("rename"))
+    }
+
+    /// Gets the inode as [`Any`], so file systems can recognize their own inodes.
+    ///
+    /// # Returns
+    ///
+    /// * `&dyn Any` - The inode.
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A file opened through the virtual file system.
+pub trait OpenFile: Send {
+    /// Reads from the file at the current position, advancing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - The buffer to read into.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes read, `0` at the end of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the file wasn't opened for reading.
+    /// * If the file system fails to read.
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error>;
+
+    /// Writes to the file at the current position, advancing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The data to write.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// * If the file wasn't opened for writing.
+    /// * If the file system fails to write.
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error>;
+
+    /// Moves the current position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position to move to.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<usize, Error>` - The new position, from the start of the file.
+    ///
+    /// # Errors
+    ///
+    /// * If the position would be before the start of the file.
+    fn seek(&mut self, position: SeekFrom) -> Result<usize, Error>;
+
+    /// Gets the inode of the file.
+    ///
+    /// # Returns
+    ///
+    /// * `&Arc<dyn Inode>` - The inode.
+    fn inode(&self) -> &Arc<dyn Inode>;
+}
+
+/// A position to seek to.
+///
+/// # Variants
+///
+/// * `Start` - An offset from the start of the file.
+/// * `Current` - An offset from the current position.
+/// * `End` - An offset from the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(usize),
+    Current(isize),
+    End(isize),
+}
+
+/// The flags a file is opened with.
+///
+/// # Notes
+///
+/// * Flags are combined with `|`, e.g. `OpenFlags::WRITE | OpenFlags::CREATE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenFlags(u8);
+
+impl OpenFlags {
+    /// Opens the file for reading.
+    pub const READ: Self = Self(1 << 0);
+    /// Opens the file for writing.
+    pub const WRITE: Self = Self(1 << 1);
+    /// Creates the file if it doesn't exist.
+    pub const CREATE: Self = Self(1 << 2);
+    /// Truncates the file to zero bytes.
+    pub const TRUNCATE: Self = Self(1 << 3);
+    /// Moves to the end of the file before every write.
+    pub const APPEND: Self = Self(1 << 4);
+
+    /// Checks if all of the specified flags are set.
+    ///
+    /// # Arguments
+    ///
+    /// * `flags` - The flags.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the flags are set.
+    #[must_use]
+    pub const fn contains(self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+}
+
+impl BitOr for OpenFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A file opened through [`open`].
+///
+/// # Fields
+///
+/// * `inode` - The inode of the file.
+/// * `flags` - The flags the file was opened with.
+/// * `position` - The current position.
+struct FileHandle {
+    inode: Arc<dyn Inode>,
+    flags: OpenFlags,
+    position: usize,
+}
+
+impl OpenFile for FileHandle {
+    fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        if !self.flags.contains(OpenFlags::READ) {
+            return Err(Error::FileSystem("File isn't open for reading!".into()));
+        }
+
+        let count = self.inode.read_at(self.position, buffer)?;
+        self.position += count;
+
+        Ok(count)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize, Error> {
+        if !self.flags.contains(OpenFlags::WRITE) {
+            return Err(Error::FileSystem("File isn't open for writing!".into()));
+        }
+
+        if self.flags.contains(OpenFlags::APPEND) {
+            self.position = self.inode.metadata()?.size;
+        }
+
+        let count = self.inode.write_at(self.position, data)?;
+        self.position += count;
+
+        Ok(count)
+    }
+
+    fn seek(&mut self, position: SeekFrom) -> Result<usize, Error> {
+        let (base, offset) = match position {
+            SeekFrom::Start(offset) => (0, isize::try_from(offset)?),
+            SeekFrom::Current(offset) => (self.position, offset),
+            SeekFrom::End(offset) => (self.inode.metadata()?.size, offset),
+        };
+
+        self.position = base
+            .checked_add_signed(offset)
+            .ok_or_else(|| Error::FileSystem("Can't seek before the start of the file!".into()))?;
+
+        Ok(self.position)
+    }
+
+    fn inode(&self) -> &Arc<dyn Inode> {
+        &self.inode
+    }
+}
+
+/// A file system mounted in the virtual file system.
+///
+/// # Fields
+///
+/// * `path` - The normalized mount point.
+/// * `fs` - The file system.
+struct Mount {
+    path: String,
+    fs: Arc<dyn FileSystem>,
+}
+
+/// Mounts a file system.
+///
+/// # Arguments
+///
+/// * `path` - The mount point, which must be `/` or an existing directory.
+/// * `fs` - The file system.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the mount point isn't a directory.
+/// * If a file system is already mounted there.
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> Result<(), Error> {
+    let path = normalize(path)?;
+
+    // Anything but the root needs a directory to cover.
+    if path != "/" && !lookup(&path)?.metadata()?.is_dir() {
+        return Err(Error::FileSystem(format!("'{path}' is not a directory!")));
+    }
+
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().any(|mount| mount.path == path) {
+        return Err(Error::FileSystem(format!("'{path}' is already mounted!")));
+    }
+
+    mounts.push(Mount { path, fs });
+    mounts.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(())
+}
+
+/// Unmounts a file system, syncing it first.
+///
+/// # Arguments
+///
+/// * `path` - The mount point.
+///
+/// # Returns
+///
+/// * `Result<Arc<dyn FileSystem>, Error>` - The unmounted file system.
+///
+/// # Errors
+///
+/// * If nothing is mounted there.
+/// * If another file system is mounted below it.
+/// * If the file system fails to sync.
+pub fn unmount(path: &str) -> Result<Arc<dyn FileSystem>, Error> {
+    let path = normalize(path)?;
+    let mut mounts = MOUNTS.lock();
+
+    let index = mounts
+        .iter()
+        .position(|mount| mount.path == path)
+        .ok_or_else(|| Error::FileSystem(format!("'{path}' is not mounted!")))?;
+
+    if mounts
+        .iter()
+        .any(|mount| mount.path != path && is_within(&mount.path, &path))
+    {
+        return Err(Error::FileSystem(format!("'{path}' is busy!")));
+    }
+
+    mounts[index].fs.sync()?;
+
+    Ok(mounts.remove(index).fs)
+}
+
+/// Lists the mounted file systems.
+///
+/// # Returns
+///
+/// * `Vec<(String, &'static str)>` - The mount points and the names of their drivers.
+#[must_use]
+pub fn mounts() -> Vec<(String, &'static str)> {
+    MOUNTS
+        .lock()
+        .iter()
+        .map(|mount| (mount.path.clone(), mount.fs.name()))
+        .collect()
+}
+
+/// Syncs every mounted file system.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If any file system fails to sync.
+pub fn sync() -> Result<(), Error> {
+    let filesystems: Vec<_> = MOUNTS.lock().iter().map(|mount| mount.fs.clone()).collect();
+
+    filesystems.iter().try_for_each(|fs| fs.sync())
+}
+
+/// Gets the root directory of the virtual file system.
+///
+/// # Returns
+///
+/// * `Result<Arc<dyn Inode>, Error>` - The root directory.
+///
+/// # Errors
+///
+/// * If nothing is mounted at `/`.
+pub fn root() -> Result<Arc<dyn Inode>, Error> {
+    lookup("/")
+}
+
+/// Looks up the inode at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<Arc<dyn Inode>, Error>` - The inode.
+///
+/// # Errors
+///
+/// * If the path is relative.
+/// * If nothing is mounted at `/`.
+/// * If any component of the path doesn't exist.
+pub fn lookup(path: &str) -> Result<Arc<dyn Inode>, Error> {
+    let path = normalize(path)?;
+    let (mount_path, fs) = find_mount(&path)?;
+
+    // Walk the rest of the path inside the file system.
+    let mut inode = fs.root()?;
+    for component in components(&path[mount_path.len()..]) {
+        inode = inode.lookup(component)?;
+    }
+
+    Ok(inode)
+}
+
+/// Gets the metadata of the inode at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<Metadata, Error>` - The metadata.
+///
+/// # Errors
+///
+/// * If the path doesn't exist.
+pub fn metadata(path: &str) -> Result<Metadata, Error> {
+    lookup(path)?.metadata()
+}
+
+/// Opens the file at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+/// * `flags` - The flags to open the file with.
+///
+/// # Returns
+///
+/// * `Result<Box<dyn OpenFile>, Error>` - The open file.
+///
+/// # Errors
+///
+/// * If the file doesn't exist and `CREATE` isn't set.
+/// * If the file exists, but can't be looked up.
+/// * If the path points to a directory.
+/// * If the file can't be created or truncated.
+pub fn open(path: &str, flags: OpenFlags) -> Result<Box<dyn OpenFile>, Error> {
+    let inode = match lookup(path) {
+        Ok(inode) => inode,
+        Err(why) if flags.contains(OpenFlags::CREATE) => {
+            let (parent, name) = split(path)?;
+            let parent = lookup(&parent)?;
+
+            // Only a missing file is created, any other failure is returned.
+            if parent.read_dir()?.iter().any(|entry| entry.name == name) {
+                return Err(why);
+            }
+
+            parent.create(&name, InodeKind::File)?
+        }
+        Err(why) => return Err(why),
+    };
+
+    let metadata = inode.metadata()?;
+    if metadata.is_dir() {
+        return Err(Error::FileSystem(format!("'{path}' is a directory!")));
+    }
+
+    if flags.contains(OpenFlags::TRUNCATE) && metadata.size != 0 {
+        inode.truncate(0)?;
+    }
+
+    Ok(Box::new(FileHandle {
+        inode,
+        flags,
+        position: 0,
+    }))
+}
+
+/// Reads the whole file at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The contents of the file.
+///
+/// # Errors
+///
+/// * If the file doesn't exist or is a directory.
+/// * If the file system fails to read.
+pub fn read(path: &str) -> Result<Vec<u8>, Error> {
+    let inode = lookup(path)?;
+    let metadata = inode.metadata()?;
+    if metadata.is_dir() {
+        return Err(Error::FileSystem(format!("'{path}' is a directory!")));
+    }
+
+    let mut data = vec![0; metadata.size];
+    let mut position = 0;
+    while position < data.len() {
+        match inode.read_at(position, &mut data[position..])? {
+            0 => break,
+            count => position += count,
+        }
+    }
+    data.truncate(position);
+
+    Ok(data)
+}
+
+/// Lists the entries of the directory at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<Vec<DirEntry>, Error>` - The entries.
+///
+/// # Errors
+///
+/// * If the directory doesn't exist.
+/// * If the path doesn't point to a directory.
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, Error> {
+    lookup(path)?.read_dir()
+}
+
+/// Creates a file or directory at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+/// * `kind` - The kind of the entry.
+///
+/// # Returns
+///
+/// * `Result<Arc<dyn Inode>, Error>` - The new entry.
+///
+/// # Errors
+///
+/// * If the parent directory doesn't exist.
+/// * If the entry already exists.
+/// * If the file system is read only.
+pub fn create(path: &str, kind: InodeKind) -> Result<Arc<dyn Inode>, Error> {
+    let (parent, name) = split(path)?;
+
+    lookup(&parent)?.create(&name, kind)
+}
+
+/// Removes the file or empty directory at the specified path.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the entry doesn't exist or is a non-empty directory.
+/// * If the entry is a mount point.
+/// * If the file system is read only.
+pub fn remove(path: &str) -> Result<(), Error> {
+    let (parent, name) = split(path)?;
+
+    let path = normalize(path)?;
+    if MOUNTS.lock().iter().any(|mount| mount.path == path) {
+        return Err(Error::FileSystem(format!("'{path}' is busy!")));
+    }
+
+    lookup(&parent)?.unlink(&name)
+}
+
+/// Moves a file or directory, replacing any existing target.
+///
+/// # Arguments
+///
+/// * `from` - The absolute path of the entry.
+/// * `to` - The new absolute path of the entry.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the entry doesn't exist.
+/// * If either path is a mount point.
+/// * If the paths are on different file systems.
+/// * If the target can't be replaced.
+/// * If the file system is read only.
+pub fn rename(from: &str, to: &str) -> Result<(), Error> {
+    let (from_parent, from_name) = split(from)?;
+    let (to_parent, to_name) = split(to)?;
+
+    let (from, to) = (normalize(from)?, normalize(to)?);
+    if MOUNTS
+        .lock()
+        .iter()
+        .any(|mount| mount.path == from || mount.path == to)
+    {
+        return Err(Error::FileSystem(format!("'{from}' or '{to}' is busy!")));
+    }
+
+    // Entries can only move within a file system.
+    let (from_mount, _) = find_mount(&from)?;
+    let (to_mount, _) = find_mount(&to)?;
+    if from_mount != to_mount {
+        return Err(Error::FileSystem(format!(
+            "Can't move '{from}' to another file system!"
+        )));
+    }
+
+    lookup(&from_parent)?.rename(&from_name, &lookup(&to_parent)?, &to_name)
+}
+
+/// Normalizes an absolute path, resolving `.` and `..` components.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<String, Error>` - The normalized path, which is `/` or has no trailing slash.
+///
+/// # Errors
+///
+/// * If the path is relative.
+///
+/// # Notes
+///
+/// * `..` at the root stays at the root.
+pub fn normalize(path: &str) -> Result<String, Error> {
+    if !path.starts_with('/') {
+        return Err(Error::FileSystem(format!(
+            "'{path}' is not an absolute path!"
+        )));
+    }
+
+    let mut resolved: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+
+    if resolved.is_empty() {
+        return Ok("/".to_string());
+    }
+
+    Ok(resolved
+        .iter()
+        .fold(String::new(), |path, component| path + "/" + component))
+}
+
+/// Splits a path into its normalized parent and its final component.
+///
+/// # Arguments
+///
+/// * `path` - The absolute path.
+///
+/// # Returns
+///
+/// * `Result<(String, String), Error>` - The parent path and the name.
+///
+/// # Errors
+///
+/// * If the path is relative.
+/// * If the path is the root directory.
+fn split(path: &str) -> Result<(String, String), Error> {
+    let path = normalize(path)?;
+
+    match path.rsplit_once('/') {
+        Some((_, "")) | None => Err(Error::FileSystem(format!("Invalid path: '{path}'!"))),
+        Some(("", name)) => Ok(("/".to_string(), name.to_string())),
+        Some((parent, name)) => Ok((parent.to_string(), name.to_string())),
+    }
+}
+
+/// Finds the file system holding a path.
+///
+/// # Arguments
+///
+/// * `path` - The normalized path.
+///
+/// # Returns
+///
+/// * `Result<(String, Arc<dyn FileSystem>), Error>` - The mount point and the file system.
+///
+/// # Errors
+///
+/// * If nothing is mounted at `/`.
+fn find_mount(path: &str) -> Result<(String, Arc<dyn FileSystem>), Error> {
+    // The mounts are sorted, so the deepest mount point holding the path comes last.
+    MOUNTS
+        .lock()
+        .iter()
+        .rev()
+        .find(|mount| is_within(path, &mount.path))
+        .map(|mount| (mount.path.clone(), mount.fs.clone()))
+        .ok_or_else(|| Error::FileSystem("No file system is mounted at '/'!".into()))
+}
+
+/// Gets the components of a path, ignoring empty ones.
+///
+/// # Arguments
+///
+/// * `path` - The path.
+///
+/// # Returns
+///
+/// * `impl Iterator<Item = &str>` - The components.
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|component| !component.is_empty())
+}
+
+/// Checks if a normalized path is a mount point or lies below it.
+///
+/// # Arguments
+///
+/// * `path` - The normalized path.
+/// * `mount_path` - The normalized mount point.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the path is within the mount point.
+fn is_within(path: &str, mount_path: &str) -> bool {
+    mount_path == "/"
+        || path == mount_path
+        || path
+            .strip_prefix(mount_path)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Creates the error for an operation a file system doesn't support.
+///
+/// # Arguments
+///
+/// * `operation` - The name of the operation.
+///
+/// # Returns
+///
+/// * `Error` - The error.
+fn unsupported(operation: &str) -> Error {
+    Error::FileSystem(format!("Operation not supported: {operation}!"))
+}
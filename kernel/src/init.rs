@@ -2,7 +2,8 @@ use crate::dev::ata;
 use crate::errors::Error;
 use crate::sys::task::executor::Executor;
 use crate::sys::task::{keyboard, Task};
-use crate::sys::{gdt, idt, pic, time};
+use crate::sys::time::clocksource;
+use crate::sys::{acpi, apic, gdt, idt, pic, time};
 use crate::{dev, fs, KERNEL_VERSION};
 use crate::{mem, println};
 use bootloader::BootInfo;
@@ -50,18 +51,45 @@ pub fn start_kernel(boot_info: &'static BootInfo) -> Result<Executor, Error> {
     println!("[INFO]: Configuring memory management...");
     mem::init(boot_info)?;
 
+    // Discover the platform through the ACPI tables.
+    println!("[INFO]: Reading the ACPI tables...");
+    if let Err(why) = acpi::init() {
+        println!("[WARN]: Failed to read the ACPI tables: {why}");
+    }
+
+    // Switch to the APICs, if the platform has them.
+    println!("[INFO]: Configuring APIC...");
+    if let Err(why) = apic::init() {
+        println!("[WARN]: Keeping the PIC: {why}");
+    }
+
+    // Select the most precise clock source.
+    println!("[INFO]: Selecting the clock source...");
+    if let Err(why) = clocksource::init() {
+        println!("[WARN]: Failed to select the clock source: {why}");
+    }
+
     // Initialize the device drivers.
     println!("[INFO]: Initializing device drivers...");
     dev::init();
 
+    // Mount the root file system.
+    println!("[INFO]: Mounting the root file system...");
+    if let Err(why) = fs::init_root() {
+        println!("[WARN]: Failed to mount the root file system: {why}");
+    }
+
     // Initialize the file system.
     println!("[INFO]: Initializing the file system...");
-    let fs = fs::init();
-    
+    if let Err(why) = fs::init() {
+        println!("[WARN]: Failed to initialize the file system: {why}");
+    }
+
     // Initialize the task executor.
     println!("[INFO]: Setting up the task executor...");
     let mut executor = Executor::new();
     executor.spawn(Task::new(keyboard::print_keypress()))?;
+    executor.spawn(Task::new(dev::cache::flush_periodically()))?;
 
     Ok(executor)
 }
@@ -1,12 +1,14 @@
 use crate::allocator::init_heap;
 use crate::errors::Error;
+use alloc::format;
 use bootloader::bootinfo::{MemoryMap, MemoryRegionType};
 use bootloader::BootInfo;
+use spin::Mutex;
 use x86_64::{
     registers::control::Cr3,
     structures::paging::{
         page_table::FrameError, FrameAllocator, Mapper, OffsetPageTable, Page, PageTable,
-        PageTableFlags, PhysFrame, Size4KiB,
+        PageTableFlags, PhysFrame, Size4KiB, Translate,
     },
     PhysAddr, VirtAddr,
 };
@@ -17,6 +19,9 @@ pub static mut PHYSICAL_MEMORY_OFFSET: u64 = 0x0;
 /// The memory map passed from the bootloader.
 pub static mut MEMORY_MAP: Option<&MemoryMap> = None;
 
+/// The frame allocator used after the heap is set up.
+static FRAME_ALLOCATOR: Mutex<Option<BootInfoFrameAllocator>> = Mutex::new(None);
+
 /// A `FrameAllocator` that always returns `None`.
 pub struct EmptyFrameAllocator;
 
@@ -79,6 +84,47 @@ impl BootInfoFrameAllocator {
         // Create `PhysFrame` types from the start addresses.
         frame_addresses.map(|addr| PhysFrame::containing_address(PhysAddr::new(addr)))
     }
+
+    /// Allocates physically contiguous frames.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - The number of frames.
+    /// * `limit` - The physical address the frames must end below.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<PhysFrame>` - The first frame, if enough contiguous frames were found.
+    ///
+    /// # Notes
+    ///
+    /// * Usable frames skipped to find a contiguous range are never handed out.
+    pub fn allocate_contiguous(&mut self, count: usize, limit: u64) -> Option<PhysFrame> {
+        let mut run: Option<(usize, PhysFrame)> = None;
+        let mut length = 0;
+
+        for (index, frame) in self.usable_frames().enumerate().skip(self.next) {
+            if frame.start_address().as_u64() + frame.size() > limit {
+                return None;
+            }
+
+            match run {
+                Some((_, first)) if first + length as u64 == frame => length += 1,
+                _ => {
+                    run = Some((index, frame));
+                    length = 1;
+                }
+            }
+
+            if length == count {
+                self.next = index + 1;
+
+                return run.map(|(_, first)| first);
+            }
+        }
+
+        None
+    }
 }
 
 /// A `FrameAllocator` that returns usable frames from the bootloader's memory map.
@@ -125,6 +171,9 @@ pub fn init(boot_info: &'static BootInfo) -> Result<(), Error> {
 
         // Initialize the heap.
         init_heap(&mut mapper, &mut frame_allocator)?;
+
+        // Keep the frame allocator, so the frames used by the heap aren't handed out again.
+        FRAME_ALLOCATOR.lock().replace(frame_allocator);
     };
 
     Ok(())
@@ -276,12 +325,9 @@ pub fn create_example_mapping(
 pub fn alloc_page(addr: u64, size: u64) -> Result<(), Error> {
     let mut mapper = unsafe { mapper(VirtAddr::new(PHYSICAL_MEMORY_OFFSET)) };
 
-    let mut framealloc = unsafe {
-        let Some(memory_map) = MEMORY_MAP else {
-            return Err(Error::Internal("Memory map isn't initialized!".into()));
-        };
-
-        BootInfoFrameAllocator::init(memory_map)
+    let mut frame_allocator = FRAME_ALLOCATOR.lock();
+    let Some(framealloc) = frame_allocator.as_mut() else {
+        return Err(Error::Internal("Memory map isn't initialized!".into()));
     };
 
     let flags =
@@ -300,7 +346,7 @@ pub fn alloc_page(addr: u64, size: u64) -> Result<(), Error> {
         };
 
         unsafe {
-            if let Ok(mapping) = mapper.map_to(page, frame, flags, &mut framealloc) {
+            if let Ok(mapping) = mapper.map_to(page, frame, flags, framealloc) {
                 mapping.flush();
             } else {
                 return Err(Error::Internal("Unable to map frame!".into()));
@@ -310,3 +356,115 @@ pub fn alloc_page(addr: u64, size: u64) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Allocates physically contiguous frames, such as for DMA.
+///
+/// # Arguments
+///
+/// * `count` - The number of frames.
+/// * `limit` - The physical address the frames must end below, such as 4 GiB for 32-bit DMA.
+///
+/// # Returns
+///
+/// * `Result<PhysAddr, Error>` - The physical address of the first frame.
+///
+/// # Errors
+///
+/// * If the memory map isn't initialized.
+/// * If there aren't enough contiguous frames below the limit.
+///
+/// # Notes
+///
+/// * The frames are never freed, and are zeroed.
+pub fn alloc_contiguous_frames(count: usize, limit: u64) -> Result<PhysAddr, Error> {
+    let mut frame_allocator = FRAME_ALLOCATOR.lock();
+    let Some(frame_allocator) = frame_allocator.as_mut() else {
+        return Err(Error::Internal("Memory map isn't initialized!".into()));
+    };
+
+    let Some(frame) = frame_allocator.allocate_contiguous(count, limit) else {
+        return Err(Error::OutOfMemory(format!(
+            "Unable to allocate {count} contiguous frames!"
+        )));
+    };
+
+    let address = frame.start_address();
+    unsafe {
+        core::ptr::write_bytes(
+            phys_to_virt(address).as_mut_ptr::<u8>(),
+            0,
+            count * frame.size() as usize,
+        );
+    }
+
+    Ok(address)
+}
+
+/// Maps a range of memory-mapped I/O registers.
+///
+/// # Arguments
+///
+/// * `addr` - The physical address of the registers.
+/// * `size` - The size of the registers, in bytes.
+///
+/// # Returns
+///
+/// * `Result<VirtAddr, Error>` - The virtual address of the registers.
+///
+/// # Errors
+///
+/// * If the memory map isn't initialized.
+/// * If the mapper fails to map a frame.
+///
+/// # Notes
+///
+/// * The registers are mapped uncached, where [`phys_to_virt`] expects them.
+/// * Pages the bootloader already mapped, such as those below the end of RAM, are kept as they are.
+pub fn map_mmio(addr: PhysAddr, size: u64) -> Result<VirtAddr, Error> {
+    let mut mapper = unsafe { mapper(VirtAddr::new(PHYSICAL_MEMORY_OFFSET)) };
+
+    let mut frame_allocator = FRAME_ALLOCATOR.lock();
+    let Some(frame_allocator) = frame_allocator.as_mut() else {
+        return Err(Error::Internal("Memory map isn't initialized!".into()));
+    };
+
+    let flags = PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::NO_CACHE
+        | PageTableFlags::WRITE_THROUGH;
+
+    let frames = PhysFrame::<Size4KiB>::range_inclusive(
+        PhysFrame::containing_address(addr),
+        PhysFrame::containing_address(addr + size.max(1) - 1u64),
+    );
+    for frame in frames {
+        let page = Page::<Size4KiB>::containing_address(phys_to_virt(frame.start_address()));
+        if mapper.translate_addr(page.start_address()).is_some() {
+            continue;
+        }
+
+        unsafe {
+            mapper.map_to(page, frame, flags, frame_allocator)?.flush();
+        }
+    }
+
+    Ok(phys_to_virt(addr))
+}
+
+/// Gets the virtual address a physical address is mapped at.
+///
+/// # Arguments
+///
+/// * `addr` - The physical address.
+///
+/// # Returns
+///
+/// * `VirtAddr` - The virtual address.
+///
+/// # Notes
+///
+/// * The bootloader maps all of physical memory at [`PHYSICAL_MEMORY_OFFSET`].
+#[must_use]
+pub fn phys_to_virt(addr: PhysAddr) -> VirtAddr {
+    VirtAddr::new(unsafe { PHYSICAL_MEMORY_OFFSET } + addr.as_u64())
+}
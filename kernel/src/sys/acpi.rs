@@ -0,0 +1,1111 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use conquer_once::spin::OnceCell;
+use x86_64::instructions::port::Port;
+use x86_64::PhysAddr;
+
+use crate::dev::pci;
+use crate::errors::Error;
+use crate::mem::map_mmio;
+use crate::println;
+
+/// The signature of the root system description pointer.
+const RSDP_SIGNATURE: &[u8; 8] = b"RSD PTR ";
+/// The size of the first version of the root system description pointer, in bytes.
+const RSDP_V1_SIZE: usize = 20;
+/// The size of the second version of the root system description pointer, in bytes.
+const RSDP_V2_SIZE: usize = 36;
+
+/// The physical address of the real mode segment of the extended BIOS data area.
+const EBDA_SEGMENT: u64 = 0x40E;
+/// The number of bytes of the extended BIOS data area searched for the RSDP.
+const EBDA_SEARCH_SIZE: usize = 1024;
+/// The physical address of the BIOS read-only memory area searched for the RSDP.
+const BIOS_AREA_START: u64 = 0xE_0000;
+/// The size of the BIOS read-only memory area, in bytes.
+const BIOS_AREA_SIZE: usize = 0x2_0000;
+
+/// The size of a system description table header, in bytes.
+const HEADER_SIZE: usize = 36;
+/// The largest table accepted, in bytes, to guard against corrupt lengths.
+const MAX_TABLE_SIZE: usize = 4 << 20;
+
+/// The signature of the multiple APIC description table.
+const MADT_SIGNATURE: &[u8; 4] = b"APIC";
+/// The signature of the fixed ACPI description table.
+const FADT_SIGNATURE: &[u8; 4] = b"FACP";
+/// The signature of the high precision event timer table.
+const HPET_SIGNATURE: &[u8; 4] = b"HPET";
+/// The signature of the PCI express memory mapped configuration table.
+const MCFG_SIGNATURE: &[u8; 4] = b"MCFG";
+/// The signature of the differentiated system description table.
+const DSDT_SIGNATURE: &[u8; 4] = b"DSDT";
+
+/// The generic address space of system memory.
+pub const SPACE_SYSTEM_MEMORY: u8 = 0;
+/// The generic address space of system I/O ports.
+pub const SPACE_SYSTEM_IO: u8 = 1;
+
+/// The FADT flag set when the reset register is supported.
+const FADT_RESET_REGISTER: u32 = 1 << 10;
+/// The FADT flag set when the power management timer is 32 bits wide.
+const FADT_TIMER_EXTENDED: u32 = 1 << 8;
+/// The FADT boot flag set when the 8042 keyboard controller is present.
+const BOOT_8042: u16 = 1 << 1;
+
+/// The discovered ACPI tables.
+static ACPI: OnceCell<Acpi> = OnceCell::uninit();
+
+/// The ACPI tables of the platform.
+///
+/// # Fields
+///
+/// * `revision` - The ACPI revision of the root system description pointer.
+/// * `oem_id` - The OEM that supplied the tables.
+/// * `tables` - The signature and the physical address of every table the root table lists.
+/// * `madt` - The multiple APIC description table, if any.
+/// * `fadt` - The fixed ACPI description table, if any.
+/// * `hpet` - The high precision event timer table, if any.
+#[derive(Debug)]
+pub struct Acpi {
+    pub revision: u8,
+    pub oem_id: String,
+    pub tables: Vec<([u8; 4], PhysAddr)>,
+    pub madt: Option<Madt>,
+    pub fadt: Option<Fadt>,
+    pub hpet: Option<Hpet>,
+}
+
+/// A system description table.
+///
+/// # Fields
+///
+/// * `signature` - The signature of the table.
+/// * `revision` - The revision of the table.
+/// * `oem_id` - The OEM that supplied the table.
+/// * `oem_table_id` - The OEM's name of the table.
+/// * `address` - The physical address of the table.
+/// * `bytes` - The table, including its header.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub signature: [u8; 4],
+    pub revision: u8,
+    pub oem_id: String,
+    pub oem_table_id: String,
+    pub address: PhysAddr,
+    bytes: Vec<u8>,
+}
+
+/// A register described by a generic address structure.
+///
+/// # Fields
+///
+/// * `space` - The address space of the register, such as [`SPACE_SYSTEM_IO`].
+/// * `bit_width` - The size of the register, in bits.
+/// * `bit_offset` - The offset of the register in the address, in bits.
+/// * `access_size` - The access size, from 1 (byte) to 4 (quad word), or 0 if undefined.
+/// * `address` - The address of the register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericAddress {
+    pub space: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    pub access_size: u8,
+    pub address: u64,
+}
+
+/// The multiple APIC description table.
+///
+/// # Fields
+///
+/// * `local_apic_address` - The physical address of the local APIC of each processor.
+/// * `legacy_pics` - Whether or not the system also has the 8259 PICs, which must be masked to use the APICs.
+/// * `processors` - The processors.
+/// * `io_apics` - The I/O APICs.
+/// * `overrides` - The ISA interrupts that aren't identity mapped to global system interrupts.
+/// * `nmis` - The local APIC pins wired to non-maskable interrupts.
+#[derive(Debug, Clone)]
+pub struct Madt {
+    pub local_apic_address: PhysAddr,
+    pub legacy_pics: bool,
+    pub processors: Vec<Processor>,
+    pub io_apics: Vec<IoApic>,
+    pub overrides: Vec<InterruptOverride>,
+    pub nmis: Vec<LocalNmi>,
+}
+
+/// A processor of the MADT.
+///
+/// # Fields
+///
+/// * `uid` - The ACPI processor UID.
+/// * `apic_id` - The ID of the local APIC.
+/// * `enabled` - Whether or not the processor is usable.
+#[derive(Debug, Clone, Copy)]
+pub struct Processor {
+    pub uid: u32,
+    pub apic_id: u32,
+    pub enabled: bool,
+}
+
+/// An I/O APIC of the MADT.
+///
+/// # Fields
+///
+/// * `id` - The ID of the I/O APIC.
+/// * `address` - The physical address of the registers.
+/// * `gsi_base` - The first global system interrupt handled by the I/O APIC.
+#[derive(Debug, Clone, Copy)]
+pub struct IoApic {
+    pub id: u8,
+    pub address: PhysAddr,
+    pub gsi_base: u32,
+}
+
+/// An interrupt source override of the MADT.
+///
+/// # Fields
+///
+/// * `source` - The ISA interrupt.
+/// * `gsi` - The global system interrupt it's wired to.
+/// * `flags` - The MPS polarity (bits 0-1) and trigger mode (bits 2-3) flags.
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptOverride {
+    pub source: u8,
+    pub gsi: u32,
+    pub flags: u16,
+}
+
+/// A local APIC non-maskable interrupt of the MADT.
+///
+/// # Fields
+///
+/// * `uid` - The ACPI processor UID, or `u32::MAX` for every processor.
+/// * `lint` - The local interrupt pin, either 0 or 1.
+/// * `flags` - The MPS polarity (bits 0-1) and trigger mode (bits 2-3) flags.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalNmi {
+    pub uid: u32,
+    pub lint: u8,
+    pub flags: u16,
+}
+
+/// The fixed ACPI description table.
+///
+/// # Fields
+///
+/// * `dsdt` - The physical address of the differentiated system description table.
+/// * `sci_interrupt` - The ISA interrupt of the system control interrupt.
+/// * `smi_command` - The port used to hand the hardware over from the firmware, or 0 if it's already in ACPI mode.
+/// * `acpi_enable` - The value written to `smi_command` to enable ACPI mode.
+/// * `acpi_disable` - The value written to `smi_command` to disable ACPI mode.
+/// * `pm1a_event` - The PM1a event register block.
+/// * `pm1b_event` - The PM1b event register block, if any.
+/// * `pm1a_control` - The PM1a control register.
+/// * `pm1b_control` - The PM1b control register, if any.
+/// * `pm_timer` - The power management timer, if any.
+/// * `century` - The CMOS register of the century, or 0 if there is none.
+/// * `boot_flags` - The IA-PC boot architecture flags.
+/// * `flags` - The fixed feature flags.
+/// * `reset` - The reset register, if any.
+/// * `reset_value` - The value written to the reset register to reset the system.
+#[derive(Debug, Clone)]
+pub struct Fadt {
+    pub dsdt: PhysAddr,
+    pub sci_interrupt: u16,
+    pub smi_command: u32,
+    pub acpi_enable: u8,
+    pub acpi_disable: u8,
+    pub pm1a_event: Option<GenericAddress>,
+    pub pm1b_event: Option<GenericAddress>,
+    pub pm1a_control: Option<GenericAddress>,
+    pub pm1b_control: Option<GenericAddress>,
+    pub pm_timer: Option<GenericAddress>,
+    pub century: u8,
+    pub boot_flags: u16,
+    pub flags: u32,
+    pub reset: Option<GenericAddress>,
+    pub reset_value: u8,
+}
+
+/// The high precision event timer table.
+///
+/// # Fields
+///
+/// * `address` - The registers of the timer block.
+/// * `number` - The number of the timer block.
+/// * `hardware_revision` - The hardware revision.
+/// * `comparators` - The number of comparators.
+/// * `counter_64bit` - Whether or not the main counter is 64 bits wide.
+/// * `legacy_replacement` - Whether or not the timer can replace the PIT and RTC interrupts.
+/// * `vendor_id` - The PCI vendor ID of the timer.
+/// * `minimum_tick` - The smallest periodic tick that doesn't lose interrupts, in counter ticks.
+#[derive(Debug, Clone, Copy)]
+pub struct Hpet {
+    pub address: GenericAddress,
+    pub number: u8,
+    pub hardware_revision: u8,
+    pub comparators: u8,
+    pub counter_64bit: bool,
+    pub legacy_replacement: bool,
+    pub vendor_id: u16,
+    pub minimum_tick: u16,
+}
+
+impl Table {
+    /// Reads and validates a table.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The physical address of the table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The table.
+    ///
+    /// # Errors
+    ///
+    /// * If the table fails to map.
+    /// * If the length of the table is invalid.
+    /// * If the checksum of the table is wrong.
+    pub fn read(address: PhysAddr) -> Result<Self, Error> {
+        let header = read_physical(address, HEADER_SIZE)?;
+        let length = usize::try_from(u32::from_le_bytes(read_array(&header, 4)?))?;
+        if !(HEADER_SIZE..=MAX_TABLE_SIZE).contains(&length) {
+            return Err(Error::ACPI(format!(
+                "Table at {address:#x} has an invalid length of {length} bytes!",
+                address = address.as_u64()
+            )));
+        }
+
+        let bytes = read_physical(address, length)?;
+        let signature = read_array(&bytes, 0)?;
+        if !checksum(&bytes) {
+            return Err(Error::ACPI(format!(
+                "Table {signature} has an invalid checksum!",
+                signature = String::from_utf8_lossy(&signature)
+            )));
+        }
+
+        Ok(Self {
+            signature,
+            revision: bytes[8],
+            oem_id: text(&bytes[10..16]),
+            oem_table_id: text(&bytes[16..24]),
+            address,
+            bytes,
+        })
+    }
+
+    /// Gets the contents of the table, after its header.
+    ///
+    /// # Returns
+    ///
+    /// * `&[u8]` - The contents of the table.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.bytes[HEADER_SIZE..]
+    }
+
+    /// Gets the whole table, including its header.
+    ///
+    /// # Returns
+    ///
+    /// * `&[u8]` - The table.
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl GenericAddress {
+    /// Parses a generic address structure.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The table the structure is in.
+    /// * `offset` - The offset of the structure.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Self>, Error>` - The register, or `None` if the address is zero.
+    ///
+    /// # Errors
+    ///
+    /// * If the structure is out of bounds.
+    fn parse(bytes: &[u8], offset: usize) -> Result<Option<Self>, Error> {
+        let raw: [u8; 12] = read_array(bytes, offset)?;
+        let address = u64::from_le_bytes(read_array(&raw, 4)?);
+        if address == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            space: raw[0],
+            bit_width: raw[1],
+            bit_offset: raw[2],
+            access_size: raw[3],
+            address,
+        }))
+    }
+
+    /// Creates a register of the I/O space, as described by the original fixed fields of the FADT.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port of the register, or 0 if there is none.
+    /// * `length` - The size of the register, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Self>` - The register, or `None` if there is none.
+    fn io(port: u32, length: u8) -> Option<Self> {
+        (port != 0 && length != 0).then_some(Self {
+            space: SPACE_SYSTEM_IO,
+            bit_width: length.saturating_mul(8),
+            bit_offset: 0,
+            access_size: 0,
+            address: u64::from(port),
+        })
+    }
+
+    /// Gets the width of the accesses to the register.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The width, in bits.
+    #[must_use]
+    pub fn width(&self) -> u8 {
+        match self.access_size {
+            1 => 8,
+            2 => 16,
+            3 => 32,
+            4 => 64,
+            _ => self.bit_width.clamp(8, 64).next_power_of_two(),
+        }
+    }
+
+    /// Reads the register.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<u64, Error>` - The value of the register.
+    ///
+    /// # Errors
+    ///
+    /// * If the address space isn't supported.
+    /// * If the register fails to map.
+    ///
+    /// # Notes
+    ///
+    /// * The bit offset is ignored, as the fixed registers never use one.
+    pub fn read(&self) -> Result<u64, Error> {
+        match self.space {
+            SPACE_SYSTEM_IO => {
+                let port = u16::try_from(self.address)?;
+                Ok(unsafe {
+                    match self.width() {
+                        8 => u64::from(Port::<u8>::new(port).read()),
+                        16 => u64::from(Port::<u16>::new(port).read()),
+                        _ => u64::from(Port::<u32>::new(port).read()),
+                    }
+                })
+            }
+            SPACE_SYSTEM_MEMORY => {
+                let address = map_mmio(PhysAddr::new(self.address), 8)?;
+                Ok(unsafe {
+                    match self.width() {
+                        8 => u64::from(core::ptr::read_volatile(address.as_ptr::<u8>())),
+                        16 => u64::from(core::ptr::read_volatile(address.as_ptr::<u16>())),
+                        32 => u64::from(core::ptr::read_volatile(address.as_ptr::<u32>())),
+                        _ => core::ptr::read_volatile(address.as_ptr::<u64>()),
+                    }
+                })
+            }
+            space => Err(Error::ACPI(format!(
+                "Address space {space} isn't supported!"
+            ))),
+        }
+    }
+
+    /// Writes the register.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to write, truncated to the width of the register.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the address space isn't supported.
+    /// * If the register fails to map.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn write(&self, value: u64) -> Result<(), Error> {
+        match self.space {
+            SPACE_SYSTEM_IO => {
+                let port = u16::try_from(self.address)?;
+                unsafe {
+                    match self.width() {
+                        8 => Port::<u8>::new(port).write(value as u8),
+                        16 => Port::<u16>::new(port).write(value as u16),
+                        _ => Port::<u32>::new(port).write(value as u32),
+                    }
+                }
+            }
+            SPACE_SYSTEM_MEMORY => {
+                let address = map_mmio(PhysAddr::new(self.address), 8)?;
+                unsafe {
+                    match self.width() {
+                        8 => core::ptr::write_volatile(address.as_mut_ptr::<u8>(), value as u8),
+                        16 => core::ptr::write_volatile(address.as_mut_ptr::<u16>(), value as u16),
+                        32 => core::ptr::write_volatile(address.as_mut_ptr::<u32>(), value as u32),
+                        _ => core::ptr::write_volatile(address.as_mut_ptr::<u64>(), value),
+                    }
+                }
+            }
+            space => {
+                return Err(Error::ACPI(format!(
+                    "Address space {space} isn't supported!"
+                )))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Madt {
+    /// Parses a MADT.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The MADT.
+    ///
+    /// # Errors
+    ///
+    /// * If the table is truncated.
+    pub fn parse(table: &Table) -> Result<Self, Error> {
+        let data = table.data();
+
+        let mut madt = Self {
+            local_apic_address: PhysAddr::new(u64::from(u32::from_le_bytes(read_array(data, 0)?))),
+            legacy_pics: u32::from_le_bytes(read_array(data, 4)?) & 1 != 0,
+            processors: Vec::new(),
+            io_apics: Vec::new(),
+            overrides: Vec::new(),
+            nmis: Vec::new(),
+        };
+
+        // The interrupt controller structures follow, each starting with its type and length.
+        let mut offset = 8;
+        while offset + 2 <= data.len() {
+            let length = usize::from(data[offset + 1]);
+            if length < 2 || offset + length > data.len() {
+                return Err(Error::ACPI("MADT entry is truncated!".into()));
+            }
+            let entry = &data[offset..offset + length];
+            let u16_at = |at: usize| -> Result<u16, Error> {
+                Ok(u16::from_le_bytes(read_array(entry, at)?))
+            };
+            let u32_at = |at: usize| -> Result<u32, Error> {
+                Ok(u32::from_le_bytes(read_array(entry, at)?))
+            };
+
+            match entry[0] {
+                // Processor local APIC.
+                0 if length >= 8 => madt.processors.push(Processor {
+                    uid: u32::from(entry[2]),
+                    apic_id: u32::from(entry[3]),
+                    enabled: u32_at(4)? & 0b11 != 0,
+                }),
+                // I/O APIC.
+                1 if length >= 12 => madt.io_apics.push(IoApic {
+                    id: entry[2],
+                    address: PhysAddr::new(u64::from(u32_at(4)?)),
+                    gsi_base: u32_at(8)?,
+                }),
+                // Interrupt source override, only defined for the ISA bus.
+                2 if length >= 10 && entry[2] == 0 => madt.overrides.push(InterruptOverride {
+                    source: entry[3],
+                    gsi: u32_at(4)?,
+                    flags: u16_at(8)?,
+                }),
+                // Local APIC NMI.
+                4 if length >= 6 => madt.nmis.push(LocalNmi {
+                    uid: if entry[2] == 0xFF {
+                        u32::MAX
+                    } else {
+                        u32::from(entry[2])
+                    },
+                    lint: entry[5],
+                    flags: u16_at(3)?,
+                }),
+                // Local APIC address override.
+                5 if length >= 12 => {
+                    madt.local_apic_address =
+                        PhysAddr::new(u64::from_le_bytes(read_array(entry, 4)?));
+                }
+                // Processor local x2APIC.
+                9 if length >= 16 => madt.processors.push(Processor {
+                    uid: u32_at(12)?,
+                    apic_id: u32_at(4)?,
+                    enabled: u32_at(8)? & 0b11 != 0,
+                }),
+                // Local x2APIC NMI.
+                0xA if length >= 12 => madt.nmis.push(LocalNmi {
+                    uid: u32_at(4)?,
+                    lint: entry[8],
+                    flags: u16_at(2)?,
+                }),
+                _ => {}
+            }
+
+            offset += length;
+        }
+
+        Ok(madt)
+    }
+
+    /// Gets the global system interrupt an ISA interrupt is wired to.
+    ///
+    /// # Arguments
+    ///
+    /// * `irq` - The ISA interrupt.
+    ///
+    /// # Returns
+    ///
+    /// * `(u32, bool, bool)` - The global system interrupt, and whether or not it's active low and level triggered.
+    ///
+    /// # Notes
+    ///
+    /// * Interrupts without an override are identity mapped, active high and edge triggered.
+    #[must_use]
+    pub fn isa_interrupt(&self, irq: u8) -> (u32, bool, bool) {
+        self.overrides
+            .iter()
+            .find(|entry| entry.source == irq)
+            .map_or((u32::from(irq), false, false), |entry| {
+                (entry.gsi, entry.active_low(), entry.level_triggered())
+            })
+    }
+}
+
+impl InterruptOverride {
+    /// Checks if the interrupt is active low.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the interrupt is active low.
+    ///
+    /// # Notes
+    ///
+    /// * ISA interrupts conforming to the bus are active high.
+    #[must_use]
+    pub fn active_low(&self) -> bool {
+        self.flags & 0b11 == 0b11
+    }
+
+    /// Checks if the interrupt is level triggered.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the interrupt is level triggered.
+    ///
+    /// # Notes
+    ///
+    /// * ISA interrupts conforming to the bus are edge triggered.
+    #[must_use]
+    pub fn level_triggered(&self) -> bool {
+        (self.flags >> 2) & 0b11 == 0b11
+    }
+}
+
+impl Fadt {
+    /// Parses a FADT.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The FADT.
+    ///
+    /// # Errors
+    ///
+    /// * If the table is truncated.
+    ///
+    /// # Notes
+    ///
+    /// * The extended 64-bit fields are preferred over the original ones, when the table is long enough to have them.
+    pub fn parse(table: &Table) -> Result<Self, Error> {
+        let bytes = table.bytes();
+        let u8_at = |offset: usize| bytes.get(offset).copied().unwrap_or(0);
+        let u16_at =
+            |offset: usize| -> u16 { read_array(bytes, offset).map_or(0, u16::from_le_bytes) };
+        let u32_at =
+            |offset: usize| -> u32 { read_array(bytes, offset).map_or(0, u32::from_le_bytes) };
+        let u64_at =
+            |offset: usize| -> u64 { read_array(bytes, offset).map_or(0, u64::from_le_bytes) };
+        let extended = |offset: usize| -> Option<GenericAddress> {
+            GenericAddress::parse(bytes, offset).ok().flatten()
+        };
+
+        // The original table ends with the power management timer length.
+        if bytes.len() < 92 {
+            return Err(Error::ACPI("FADT is truncated!".into()));
+        }
+
+        let dsdt = match u64_at(140) {
+            0 => u64::from(u32_at(40)),
+            address => address,
+        };
+        let pm1_event_length = u8_at(88);
+        let pm1_control_length = u8_at(89);
+
+        Ok(Self {
+            dsdt: PhysAddr::new(dsdt),
+            sci_interrupt: u16_at(46),
+            smi_command: u32_at(48),
+            acpi_enable: u8_at(52),
+            acpi_disable: u8_at(53),
+            pm1a_event: extended(148).or(GenericAddress::io(u32_at(56), pm1_event_length)),
+            pm1b_event: extended(160).or(GenericAddress::io(u32_at(60), pm1_event_length)),
+            pm1a_control: extended(172).or(GenericAddress::io(u32_at(64), pm1_control_length)),
+            pm1b_control: extended(184).or(GenericAddress::io(u32_at(68), pm1_control_length)),
+            pm_timer: extended(208).or(GenericAddress::io(u32_at(76), u8_at(91))),
+            century: u8_at(108),
+            boot_flags: u16_at(109),
+            flags: u32_at(112),
+            reset: extended(116),
+            reset_value: u8_at(128),
+        })
+    }
+
+    /// Checks if the reset register can be used.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the reset register is supported.
+    #[must_use]
+    pub fn reset_supported(&self) -> bool {
+        self.flags & FADT_RESET_REGISTER != 0 && self.reset.is_some()
+    }
+
+    /// Checks if the power management timer is 32 bits wide, instead of 24.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the timer is 32 bits wide.
+    #[must_use]
+    pub fn pm_timer_extended(&self) -> bool {
+        self.flags & FADT_TIMER_EXTENDED != 0
+    }
+
+    /// Checks if the 8042 keyboard controller is present.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the keyboard controller is present.
+    ///
+    /// # Notes
+    ///
+    /// * Firmware that leaves the boot flags empty is assumed to have one.
+    #[must_use]
+    pub fn has_8042(&self) -> bool {
+        self.boot_flags == 0 || self.boot_flags & BOOT_8042 != 0
+    }
+}
+
+impl Hpet {
+    /// Parses a HPET table.
+    ///
+    /// # Arguments
+    ///
+    /// * `table` - The table.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The HPET table.
+    ///
+    /// # Errors
+    ///
+    /// * If the table is truncated.
+    /// * If the table has no address.
+    pub fn parse(table: &Table) -> Result<Self, Error> {
+        let data = table.data();
+
+        let id = u32::from_le_bytes(read_array(data, 0)?);
+        let address = GenericAddress::parse(data, 4)?
+            .ok_or_else(|| Error::ACPI("HPET has no address!".into()))?;
+
+        Ok(Self {
+            address,
+            number: *data
+                .get(16)
+                .ok_or_else(|| Error::ACPI("HPET is truncated!".into()))?,
+            hardware_revision: id.to_le_bytes()[0],
+            // The field holds the number of the last comparator.
+            comparators: (id.to_le_bytes()[1] & 0x1F) + 1,
+            counter_64bit: id & (1 << 13) != 0,
+            legacy_replacement: id & (1 << 15) != 0,
+            vendor_id: u16::try_from(id >> 16)?,
+            minimum_tick: u16::from_le_bytes(read_array(data, 17)?),
+        })
+    }
+}
+
+/// Gets the ACPI tables.
+///
+/// # Returns
+///
+/// * `Option<&'static Acpi>` - The ACPI tables, or `None` if they weren't found.
+pub fn get() -> Option<&'static Acpi> {
+    ACPI.get()
+}
+
+/// Gets the multiple APIC description table.
+///
+/// # Returns
+///
+/// * `Option<&'static Madt>` - The MADT, or `None` if there is none.
+pub fn madt() -> Option<&'static Madt> {
+    get()?.madt.as_ref()
+}
+
+/// Gets the fixed ACPI description table.
+///
+/// # Returns
+///
+/// * `Option<&'static Fadt>` - The FADT, or `None` if there is none.
+pub fn fadt() -> Option<&'static Fadt> {
+    get()?.fadt.as_ref()
+}
+
+/// Gets the high precision event timer table.
+///
+/// # Returns
+///
+/// * `Option<&'static Hpet>` - The HPET table, or `None` if there is none.
+pub fn hpet() -> Option<&'static Hpet> {
+    get()?.hpet.as_ref()
+}
+
+/// Reads a table by its signature.
+///
+/// # Arguments
+///
+/// * `signature` - The signature of the table.
+///
+/// # Returns
+///
+/// * `Result<Option<Table>, Error>` - The first table with the signature, or `None` if there is none.
+///
+/// # Errors
+///
+/// * If the table fails to read or validate.
+///
+/// # Notes
+///
+/// * The DSDT isn't listed by the root table, so it's found through the FADT.
+pub fn find_table(signature: &[u8; 4]) -> Result<Option<Table>, Error> {
+    let Some(acpi) = get() else {
+        return Ok(None);
+    };
+
+    if signature == DSDT_SIGNATURE {
+        return acpi
+            .fadt
+            .as_ref()
+            .map(|fadt| Table::read(fadt.dsdt))
+            .transpose();
+    }
+
+    acpi.tables
+        .iter()
+        .find(|(table, _)| table == signature)
+        .map(|&(_, address)| Table::read(address))
+        .transpose()
+}
+
+/// Finds and parses the ACPI tables.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the root system description pointer isn't found.
+/// * If the root table fails to read or validate.
+///
+/// # Notes
+///
+/// * Tables that fail to validate are skipped with a warning.
+/// * The PCI express configuration space of the MCFG is handed to the PCI bus.
+pub fn init() -> Result<(), Error> {
+    let rsdp = find_rsdp()?;
+    let revision = rsdp[15];
+    let oem_id = text(&rsdp[9..15]);
+
+    // Revision 2 and later have a 64-bit extended root table.
+    let xsdt = if revision >= 2 {
+        u64::from_le_bytes(read_array(&rsdp, 24)?)
+    } else {
+        0
+    };
+    let (root, entry_size) = if xsdt == 0 {
+        let rsdt = u32::from_le_bytes(read_array(&rsdp, 16)?);
+        (Table::read(PhysAddr::new(u64::from(rsdt)))?, 4)
+    } else {
+        (Table::read(PhysAddr::new(xsdt))?, 8)
+    };
+
+    let mut acpi = Acpi {
+        revision,
+        oem_id,
+        tables: Vec::new(),
+        madt: None,
+        fadt: None,
+        hpet: None,
+    };
+
+    for entry in root.data().chunks_exact(entry_size) {
+        let address = if entry_size == 8 {
+            u64::from_le_bytes(read_array(entry, 0)?)
+        } else {
+            u64::from(u32::from_le_bytes(read_array(entry, 0)?))
+        };
+        if address == 0 {
+            continue;
+        }
+
+        let address = PhysAddr::new(address);
+        let table = match Table::read(address) {
+            Ok(table) => table,
+            Err(why) => {
+                println!("[WARN]: Skipping ACPI table: {why}");
+                continue;
+            }
+        };
+        acpi.tables.push((table.signature, address));
+
+        let parsed = match &table.signature {
+            MADT_SIGNATURE => Madt::parse(&table).map(|madt| acpi.madt = Some(madt)),
+            FADT_SIGNATURE => Fadt::parse(&table).map(|fadt| acpi.fadt = Some(fadt)),
+            HPET_SIGNATURE => Hpet::parse(&table).map(|hpet| acpi.hpet = Some(hpet)),
+            MCFG_SIGNATURE => enable_ecam(&table),
+            _ => Ok(()),
+        };
+        if let Err(why) = parsed {
+            println!("[WARN]: Failed to parse ACPI table: {why}");
+        }
+    }
+
+    print_summary(&acpi);
+    ACPI.try_init_once(|| acpi)
+        .map_err(|_| Error::ACPI("ACPI tables are already initialized!".into()))
+}
+
+/// Prints what the ACPI tables describe.
+///
+/// # Arguments
+///
+/// * `acpi` - The ACPI tables.
+fn print_summary(acpi: &Acpi) {
+    let signatures = acpi
+        .tables
+        .iter()
+        .map(|(signature, _)| String::from_utf8_lossy(signature).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+    println!(
+        "[INFO]: => ACPI revision {revision} ({oem}): {signatures}",
+        revision = acpi.revision,
+        oem = acpi.oem_id
+    );
+
+    if let Some(madt) = &acpi.madt {
+        println!(
+            "[INFO]: => {processors} processors, {io_apics} I/O APICs, {overrides} interrupt overrides",
+            processors = madt.processors.iter().filter(|cpu| cpu.enabled).count(),
+            io_apics = madt.io_apics.len(),
+            overrides = madt.overrides.len()
+        );
+    }
+    if let Some(hpet) = &acpi.hpet {
+        println!(
+            "[INFO]: => HPET at {address:#x} with {comparators} comparators",
+            address = hpet.address.address,
+            comparators = hpet.comparators
+        );
+    }
+}
+
+/// Hands the PCI express configuration space of the MCFG to the PCI bus.
+///
+/// # Arguments
+///
+/// * `table` - The MCFG.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the configuration space fails to map.
+///
+/// # Notes
+///
+/// * Only the first segment is used, as the PCI bus has a single one.
+fn enable_ecam(table: &Table) -> Result<(), Error> {
+    // The allocations follow 8 reserved bytes.
+    let allocation = table
+        .data()
+        .get(8..)
+        .unwrap_or_default()
+        .chunks_exact(16)
+        .find(|allocation| allocation[8] == 0 && allocation[9] == 0);
+
+    let Some(allocation) = allocation else {
+        return Ok(());
+    };
+
+    let base = u64::from_le_bytes(read_array(allocation, 0)?);
+    println!(
+        "[INFO]: => PCI express configuration space at {base:#x} (buses {start}-{end})",
+        start = allocation[10],
+        end = allocation[11]
+    );
+    pci::enable_ecam(PhysAddr::new(base), allocation[10], allocation[11])
+}
+
+/// Finds the root system description pointer.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The root system description pointer.
+///
+/// # Errors
+///
+/// * If the pointer isn't found.
+///
+/// # Notes
+///
+/// * The first KiB of the extended BIOS data area is searched first, then the BIOS read-only memory area.
+fn find_rsdp() -> Result<Vec<u8>, Error> {
+    let segment = u16::from_le_bytes(read_array(
+        &read_physical(PhysAddr::new(EBDA_SEGMENT), 2)?,
+        0,
+    )?);
+    let ebda = u64::from(segment) << 4;
+
+    let mut areas = vec![(BIOS_AREA_START, BIOS_AREA_SIZE)];
+    if ebda != 0 {
+        areas.insert(0, (ebda, EBDA_SEARCH_SIZE));
+    }
+
+    for (start, size) in areas {
+        let area = read_physical(PhysAddr::new(start), size)?;
+
+        // The pointer is always on a 16 byte boundary.
+        for offset in (0..size.saturating_sub(RSDP_V2_SIZE - 1)).step_by(16) {
+            let rsdp = &area[offset..offset + RSDP_V2_SIZE];
+            if &rsdp[..8] != RSDP_SIGNATURE || !checksum(&rsdp[..RSDP_V1_SIZE]) {
+                continue;
+            }
+
+            // The extended checksum covers the whole structure.
+            if rsdp[15] >= 2 && !checksum(rsdp) {
+                continue;
+            }
+
+            return Ok(rsdp.to_vec());
+        }
+    }
+
+    Err(Error::ACPI(
+        "Root system description pointer not found!".into(),
+    ))
+}
+
+/// Reads physical memory.
+///
+/// # Arguments
+///
+/// * `address` - The physical address.
+/// * `length` - The number of bytes to read.
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>, Error>` - The bytes.
+///
+/// # Errors
+///
+/// * If the memory fails to map.
+fn read_physical(address: PhysAddr, length: usize) -> Result<Vec<u8>, Error> {
+    let start = map_mmio(address, length as u64)?.as_ptr::<u8>();
+
+    Ok((0..length)
+        .map(|offset| unsafe { core::ptr::read_volatile(start.add(offset)) })
+        .collect())
+}
+
+/// Reads a fixed number of bytes.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes to read from.
+/// * `offset` - The offset to read at.
+///
+/// # Returns
+///
+/// * `Result<[u8; N], Error>` - The bytes.
+///
+/// # Errors
+///
+/// * If the bytes are out of bounds.
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], Error> {
+    Ok(bytes
+        .get(offset..offset + N)
+        .ok_or_else(|| Error::ACPI("Table is truncated!".into()))?
+        .try_into()?)
+}
+
+/// Checks that bytes sum to zero, as every ACPI structure does.
+///
+/// # Arguments
+///
+/// * `bytes` - The bytes.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the checksum is valid.
+fn checksum(bytes: &[u8]) -> bool {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) == 0
+}
+
+/// Decodes an OEM identifier, trimming its padding.
+///
+/// # Arguments
+///
+/// * `bytes` - The identifier.
+///
+/// # Returns
+///
+/// * `String` - The identifier.
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\0', ' '])
+        .into()
+}
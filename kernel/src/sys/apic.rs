@@ -0,0 +1,472 @@
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::registers::model_specific::Msr;
+use x86_64::{PhysAddr, VirtAddr};
+
+use crate::errors::Error;
+use crate::mem::map_mmio;
+use crate::println;
+use crate::sys::acpi::{self, Madt};
+use crate::sys::pic::{PICS, PIC_1_OFFSET};
+
+/// The vector of spurious local APIC interrupts.
+pub const SPURIOUS_VECTOR: u8 = 0xFF;
+
+/// The model specific register holding the base address of the local APIC.
+const APIC_BASE_MSR: u32 = 0x1B;
+/// The flag of the APIC base register that enables the local APIC.
+const APIC_GLOBAL_ENABLE: u64 = 1 << 11;
+
+/// The local APIC ID register.
+const LAPIC_ID: usize = 0x20;
+/// The local APIC task priority register.
+const LAPIC_TASK_PRIORITY: usize = 0x80;
+/// The local APIC end of interrupt register.
+const LAPIC_EOI: usize = 0xB0;
+/// The local APIC spurious interrupt vector register.
+const LAPIC_SPURIOUS: usize = 0xF0;
+/// The local vector table entry of the local APIC timer.
+const LAPIC_LVT_TIMER: usize = 0x320;
+/// The local vector table entries of the local interrupt pins.
+const LAPIC_LVT_LINT: [usize; 2] = [0x350, 0x360];
+/// The local vector table entry of internal errors.
+const LAPIC_LVT_ERROR: usize = 0x370;
+/// The flag of the spurious interrupt vector register that enables the local APIC.
+const LAPIC_SOFTWARE_ENABLE: u32 = 1 << 8;
+
+/// The I/O APIC register selector.
+const IOAPIC_SELECT: usize = 0x00;
+/// The I/O APIC register window.
+const IOAPIC_WINDOW: usize = 0x10;
+/// The I/O APIC version register, which holds the last redirection entry.
+const IOAPIC_VERSION: u8 = 0x01;
+/// The first I/O APIC redirection table register.
+const IOAPIC_REDIRECTION: u8 = 0x10;
+
+/// The delivery mode of non-maskable interrupts.
+const DELIVERY_NMI: u32 = 0b100 << 8;
+/// The flag of an active low interrupt.
+const ACTIVE_LOW: u32 = 1 << 13;
+/// The flag of a level triggered interrupt.
+const LEVEL_TRIGGERED: u32 = 1 << 15;
+/// The flag of a masked interrupt.
+const MASKED: u32 = 1 << 16;
+
+/// The number of ISA interrupts.
+const ISA_INTERRUPTS: u8 = 16;
+/// The ISA interrupt the secondary PIC cascades through.
+const CASCADE_IRQ: u8 = 2;
+
+/// The virtual address of the local APIC, or 0 while the PICs are used.
+static LOCAL_APIC: AtomicU64 = AtomicU64::new(0);
+
+/// The I/O APICs.
+static IO_APICS: Mutex<Vec<IoApic>> = Mutex::new(Vec::new());
+
+/// The local APIC of the current processor.
+///
+/// # Fields
+///
+/// * `base` - The virtual address of the registers.
+#[derive(Debug, Clone, Copy)]
+pub struct LocalApic {
+    base: VirtAddr,
+}
+
+/// An I/O APIC.
+///
+/// # Fields
+///
+/// * `base` - The virtual address of the registers.
+/// * `id` - The ID of the I/O APIC.
+/// * `gsi_base` - The first global system interrupt it handles.
+/// * `entries` - The number of global system interrupts it handles.
+#[derive(Debug)]
+pub struct IoApic {
+    base: VirtAddr,
+    pub id: u8,
+    pub gsi_base: u32,
+    pub entries: u32,
+}
+
+impl LocalApic {
+    /// Maps the local APIC.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The physical address of the registers.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The local APIC.
+    ///
+    /// # Errors
+    ///
+    /// * If the registers fail to map.
+    pub fn new(address: PhysAddr) -> Result<Self, Error> {
+        Ok(Self {
+            base: map_mmio(address, 0x400)?,
+        })
+    }
+
+    /// Gets the ID of the local APIC.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The ID.
+    #[must_use]
+    pub fn id(&self) -> u32 {
+        self.read(LAPIC_ID) >> 24
+    }
+
+    /// Enables the local APIC, masking its timer and wiring its pins as the MADT describes.
+    ///
+    /// # Arguments
+    ///
+    /// * `madt` - The multiple APIC description table.
+    ///
+    /// # Notes
+    ///
+    /// * Pins without a non-maskable interrupt are masked, as the PICs they'd pass through are masked.
+    pub fn enable(&self, madt: &Madt) {
+        // The local APIC is usually enabled by the firmware already.
+        let mut base = Msr::new(APIC_BASE_MSR);
+        unsafe {
+            let value = base.read();
+            if value & APIC_GLOBAL_ENABLE == 0 {
+                base.write(value | APIC_GLOBAL_ENABLE);
+            }
+        }
+
+        let id = self.id();
+        let uid = madt
+            .processors
+            .iter()
+            .find(|processor| processor.apic_id == id)
+            .map(|processor| processor.uid);
+
+        for (lint, register) in LAPIC_LVT_LINT.into_iter().enumerate() {
+            let nmi = madt.nmis.iter().find(|nmi| {
+                usize::from(nmi.lint) == lint && (nmi.uid == u32::MAX || Some(nmi.uid) == uid)
+            });
+
+            let entry = nmi.map_or(MASKED, |nmi| {
+                DELIVERY_NMI | redirection_flags(nmi.flags & 0b11 == 0b11, false)
+            });
+            self.write(register, entry);
+        }
+
+        self.write(LAPIC_LVT_TIMER, MASKED);
+        self.write(LAPIC_LVT_ERROR, MASKED);
+        self.write(LAPIC_TASK_PRIORITY, 0);
+        self.write(
+            LAPIC_SPURIOUS,
+            u32::from(SPURIOUS_VECTOR) | LAPIC_SOFTWARE_ENABLE,
+        );
+    }
+
+    /// Signals the end of the interrupt being handled.
+    pub fn end_of_interrupt(&self) {
+        self.write(LAPIC_EOI, 0);
+    }
+
+    /// Reads a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The offset of the register.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The value of the register.
+    fn read(&self, register: usize) -> u32 {
+        unsafe { core::ptr::read_volatile((self.base + register as u64).as_ptr::<u32>()) }
+    }
+
+    /// Writes a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The offset of the register.
+    /// * `value` - The value to write.
+    fn write(&self, register: usize, value: u32) {
+        unsafe {
+            core::ptr::write_volatile((self.base + register as u64).as_mut_ptr::<u32>(), value);
+        }
+    }
+}
+
+impl IoApic {
+    /// Maps an I/O APIC, and masks all of its interrupts.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The I/O APIC, as the MADT describes it.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The I/O APIC.
+    ///
+    /// # Errors
+    ///
+    /// * If the registers fail to map.
+    pub fn new(entry: &acpi::IoApic) -> Result<Self, Error> {
+        let mut io_apic = Self {
+            base: map_mmio(entry.address, 0x20)?,
+            id: entry.id,
+            gsi_base: entry.gsi_base,
+            entries: 0,
+        };
+
+        // The version register holds the index of the last redirection entry.
+        io_apic.entries = ((io_apic.read(IOAPIC_VERSION) >> 16) & 0xFF) + 1;
+        for gsi in io_apic.gsi_base..io_apic.gsi_base + io_apic.entries {
+            io_apic.route(gsi, 0, 0, MASKED);
+        }
+
+        Ok(io_apic)
+    }
+
+    /// Checks if a global system interrupt is handled by the I/O APIC.
+    ///
+    /// # Arguments
+    ///
+    /// * `gsi` - The global system interrupt.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the interrupt is handled by the I/O APIC.
+    #[must_use]
+    pub fn handles(&self, gsi: u32) -> bool {
+        (self.gsi_base..self.gsi_base + self.entries).contains(&gsi)
+    }
+
+    /// Routes a global system interrupt to a processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `gsi` - The global system interrupt, which must be handled by the I/O APIC.
+    /// * `vector` - The interrupt vector.
+    /// * `destination` - The local APIC ID of the processor.
+    /// * `flags` - The polarity, trigger mode and mask flags.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn route(&self, gsi: u32, vector: u8, destination: u32, flags: u32) {
+        let register = IOAPIC_REDIRECTION + ((gsi - self.gsi_base) * 2) as u8;
+
+        // The high half is written first, so the entry is never unmasked with a stale destination.
+        self.write(register + 1, destination << 24);
+        self.write(register, u32::from(vector) | flags);
+    }
+
+    /// Masks or unmasks a global system interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `gsi` - The global system interrupt, which must be handled by the I/O APIC.
+    /// * `masked` - Whether or not the interrupt is masked.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn set_masked(&self, gsi: u32, masked: bool) {
+        let register = IOAPIC_REDIRECTION + ((gsi - self.gsi_base) * 2) as u8;
+
+        let entry = self.read(register);
+        self.write(
+            register,
+            if masked {
+                entry | MASKED
+            } else {
+                entry & !MASKED
+            },
+        );
+    }
+
+    /// Reads a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The index of the register.
+    ///
+    /// # Returns
+    ///
+    /// * `u32` - The value of the register.
+    fn read(&self, register: u8) -> u32 {
+        unsafe {
+            core::ptr::write_volatile(
+                (self.base + IOAPIC_SELECT as u64).as_mut_ptr::<u32>(),
+                u32::from(register),
+            );
+            core::ptr::read_volatile((self.base + IOAPIC_WINDOW as u64).as_ptr::<u32>())
+        }
+    }
+
+    /// Writes a register.
+    ///
+    /// # Arguments
+    ///
+    /// * `register` - The index of the register.
+    /// * `value` - The value to write.
+    fn write(&self, register: u8, value: u32) {
+        unsafe {
+            core::ptr::write_volatile(
+                (self.base + IOAPIC_SELECT as u64).as_mut_ptr::<u32>(),
+                u32::from(register),
+            );
+            core::ptr::write_volatile(
+                (self.base + IOAPIC_WINDOW as u64).as_mut_ptr::<u32>(),
+                value,
+            );
+        }
+    }
+}
+
+/// Checks if the APICs are used instead of the PICs.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the APICs are used.
+pub fn is_enabled() -> bool {
+    LOCAL_APIC.load(Ordering::Acquire) != 0
+}
+
+/// Signals the end of an interrupt to the local APIC.
+///
+/// # Notes
+///
+/// * This does nothing while the PICs are used.
+pub fn end_of_interrupt() {
+    let base = LOCAL_APIC.load(Ordering::Acquire);
+    if base != 0 {
+        LocalApic {
+            base: VirtAddr::new(base),
+        }
+        .end_of_interrupt();
+    }
+}
+
+/// Masks or unmasks an ISA interrupt at the I/O APIC it's routed to.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA interrupt.
+/// * `masked` - Whether or not the interrupt is masked.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not an I/O APIC handles the interrupt.
+pub fn set_irq_masked(irq: u8, masked: bool) -> bool {
+    let Some(madt) = acpi::madt() else {
+        return false;
+    };
+    let (gsi, _, _) = madt.isa_interrupt(irq);
+
+    without_interrupts(|| {
+        let io_apics = IO_APICS.lock();
+        let io_apic = io_apics.iter().find(|io_apic| io_apic.handles(gsi));
+        if let Some(io_apic) = io_apic {
+            io_apic.set_masked(gsi, masked);
+        }
+
+        io_apic.is_some()
+    })
+}
+
+/// Switches from the PICs to the local APIC and the I/O APICs.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the processor has no local APIC.
+/// * If the MADT is missing, or lists no I/O APIC.
+/// * If the registers fail to map.
+///
+/// # Notes
+///
+/// * The PICs are masked, and the ISA interrupts keep their vectors, so the interrupt handlers don't change.
+/// * ISA interrupts are routed through the MADT overrides, and stay masked unless they were unmasked at the PICs.
+/// * On error, the PICs are left as they were.
+pub fn init() -> Result<(), Error> {
+    if core::arch::x86_64::__cpuid(1).edx & (1 << 9) == 0 {
+        return Err(Error::Internal("Processor has no local APIC!".into()));
+    }
+
+    let madt = acpi::madt().ok_or_else(|| Error::Internal("No MADT to find the APICs!".into()))?;
+    if madt.io_apics.is_empty() {
+        return Err(Error::Internal("MADT lists no I/O APIC!".into()));
+    }
+
+    let local_apic = LocalApic::new(madt.local_apic_address)?;
+    let io_apics = madt
+        .io_apics
+        .iter()
+        .map(IoApic::new)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    without_interrupts(|| {
+        // Mask the PICs, remembering which interrupts were unmasked.
+        let [primary, secondary] = unsafe { PICS.lock().read_masks() };
+        unsafe { PICS.lock().write_masks(0xFF, 0xFF) };
+        let unmasked = !(u16::from(primary) | u16::from(secondary) << 8);
+
+        local_apic.enable(madt);
+        let destination = local_apic.id();
+
+        for irq in (0..ISA_INTERRUPTS).filter(|&irq| irq != CASCADE_IRQ) {
+            let (gsi, active_low, level_triggered) = madt.isa_interrupt(irq);
+            let Some(io_apic) = io_apics.iter().find(|io_apic| io_apic.handles(gsi)) else {
+                println!("[WARN]: No I/O APIC handles IRQ {irq} (GSI {gsi})");
+                continue;
+            };
+
+            let mut flags = redirection_flags(active_low, level_triggered);
+            if unmasked & (1 << irq) == 0 {
+                flags |= MASKED;
+            }
+            io_apic.route(gsi, PIC_1_OFFSET + irq, destination, flags);
+        }
+
+        *IO_APICS.lock() = io_apics;
+        LOCAL_APIC.store(local_apic.base.as_u64(), Ordering::Release);
+    });
+
+    println!(
+        "[INFO]: => Local APIC {id} at {address:#x}",
+        id = local_apic.id(),
+        address = madt.local_apic_address.as_u64()
+    );
+    for io_apic in IO_APICS.lock().iter() {
+        println!(
+            "[INFO]: => I/O APIC {id} (GSIs {first}-{last})",
+            id = io_apic.id,
+            first = io_apic.gsi_base,
+            last = io_apic.gsi_base + io_apic.entries - 1
+        );
+    }
+
+    Ok(())
+}
+
+/// Builds the polarity and trigger mode flags of an interrupt.
+///
+/// # Arguments
+///
+/// * `active_low` - Whether or not the interrupt is active low.
+/// * `level_triggered` - Whether or not the interrupt is level triggered.
+///
+/// # Returns
+///
+/// * `u32` - The flags of a redirection or local vector table entry.
+fn redirection_flags(active_low: bool, level_triggered: bool) -> u32 {
+    let mut flags = 0;
+    if active_low {
+        flags |= ACTIVE_LOW;
+    }
+    if level_triggered {
+        flags |= LEVEL_TRIGGERED;
+    }
+
+    flags
+}
@@ -1,4 +1,4 @@
-use crate::sys::time::rtc::RTC;
+use crate::println;
 
 /// System calls are used to interact with the kernel.
 ///
@@ -6,14 +6,20 @@ use crate::sys::time::rtc::RTC;
 ///
 /// * `Sleep` - Sleep for a specified amount of time.
 /// * `Uptime` - Get the uptime of the system.
-/// * `RTC` - Get the current time from the RTC.
+/// * `RTC` - Get the wall clock time, in milliseconds since the Unix epoch.
 /// * `Unknown` - An unknown system call.
+/// * `Shutdown` - Power the system off.
+/// * `Reboot` - Restart the system.
+/// * `SetRTC` - Set the wall clock and the RTC, in seconds since the Unix epoch.
 #[derive(Debug)]
 pub enum Call {
     Sleep = 0x1,
     Uptime = 0x2,
     RTC = 0x3,
     Unknown = 0x4,
+    Shutdown = 0x5,
+    Reboot = 0x6,
+    SetRTC = 0x7,
 }
 
 /// Dispatches a system call.
@@ -42,11 +48,30 @@ pub fn dispatch(call: &Call, args: &[usize]) -> Option<usize> {
             Some(uptime as usize)
         }
         Call::RTC => {
-            let rtc = RTC::new();
-            let millis = rtc.as_millis();
+            let millis = crate::sys::time::clock::unix_nanos() / 1_000_000;
 
             usize::try_from(millis).ok()
         }
+        Call::Shutdown => {
+            // Only returns if the system failed to power off.
+            let Err(why) = crate::sys::power::shutdown();
+            println!("[WARN]: Failed to power off: {why}");
+
+            None
+        }
+        Call::Reboot => crate::sys::power::reboot(),
+        Call::SetRTC => {
+            let seconds = i64::try_from(args[0]).ok()?;
+            let datetime = crate::sys::time::date::DateTime::from_unix(seconds);
+
+            match crate::sys::time::clock::set(&datetime) {
+                Ok(()) => Some(0),
+                Err(why) => {
+                    println!("[WARN]: Failed to set the RTC: {why}");
+                    None
+                }
+            }
+        }
         Call::Unknown => None,
     }
 }
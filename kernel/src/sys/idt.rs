@@ -1,7 +1,7 @@
 use crate::println;
-use crate::sys::pic::{PICS, PIC_1_OFFSET, PIC_2_OFFSET};
-use crate::sys::time::rtc::RTC;
-use crate::sys::{gdt, time};
+use crate::sys::pic::{self, PIC_1_OFFSET, PIC_2_OFFSET};
+use crate::sys::time::rtc::{RTCInterrupt, RTC};
+use crate::sys::{apic, gdt, time};
 use core::sync::atomic::Ordering;
 use lazy_static::lazy_static;
 use x86_64::instructions::port::Port;
@@ -15,12 +15,22 @@ use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, Pag
 /// 1. `Timer` - The timer interrupt (exists at [`PIC_1_OFFSET`]).
 /// 2. `Keyboard` - The keyboard interrupt, used for keyboard input (exists at [`PIC_1_OFFSET`] + 1).
 /// 3. `RTC` - The RTC interrupt, used for the RTC (exists at [`PIC_2_OFFSET`]).
+/// 4. `PrimaryAta` - The primary ATA bus interrupt, IRQ 14 (exists at [`PIC_2_OFFSET`] + 6).
+/// 5. `SecondaryAta` - The secondary ATA bus interrupt, IRQ 15 (exists at [`PIC_2_OFFSET`] + 7).
+/// 6. `Spurious` - The spurious interrupt of the local APIC (exists at [`apic::SPURIOUS_VECTOR`]).
+///
+/// # Notes
+///
+/// * ISA interrupts keep these vectors when routed through the I/O APIC.
 #[derive(Debug, Clone, Copy)]
 #[repr(u8)]
 pub enum InterruptIndex {
     Timer = PIC_1_OFFSET,
     Keyboard = PIC_1_OFFSET + 1,
     RTC = PIC_2_OFFSET,
+    PrimaryAta = PIC_2_OFFSET + 6,
+    SecondaryAta = PIC_2_OFFSET + 7,
+    Spurious = apic::SPURIOUS_VECTOR,
 }
 
 impl InterruptIndex {
@@ -114,6 +124,10 @@ lazy_static! {
         idt[InterruptIndex::Timer.as_usize()].set_handler_fn(timer_interrupt_handler);
         idt[InterruptIndex::Keyboard.as_usize()].set_handler_fn(keyboard_interrupt_handler);
         idt[InterruptIndex::RTC.as_usize()].set_handler_fn(rtc_interrupt_handler);
+        idt[InterruptIndex::PrimaryAta.as_usize()].set_handler_fn(primary_ata_interrupt_handler);
+        idt[InterruptIndex::SecondaryAta.as_usize()]
+            .set_handler_fn(secondary_ata_interrupt_handler);
+        idt[InterruptIndex::Spurious.as_usize()].set_handler_fn(spurious_interrupt_handler);
 
         idt
     };
@@ -356,10 +370,10 @@ extern "x86-interrupt" fn timer_interrupt_handler(_stack_frame: InterruptStackFr
     // Increment the PIT tick.
     time::PIT_TICK.fetch_add(1, Ordering::Relaxed);
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Timer.as_u8());
-    }
+    // Wake the tasks whose timers are due.
+    crate::sys::task::timer::timer_tick();
+
+    pic::end_of_interrupt(InterruptIndex::Timer.as_u8());
 }
 
 extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStackFrame) {
@@ -367,27 +381,40 @@ extern "x86-interrupt" fn keyboard_interrupt_handler(_stack_frame: InterruptStac
     let scancode: u8 = unsafe { port.read() };
     crate::sys::task::keyboard::add_scancode(scancode);
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::Keyboard.as_u8());
-    }
+    pic::end_of_interrupt(InterruptIndex::Keyboard.as_u8());
 }
 
 extern "x86-interrupt" fn rtc_interrupt_handler(_stack_frame: InterruptStackFrame) {
-    // Store the last RTC update tick.
-    time::LAST_RTC_UPDATE.store(time::tick(), Ordering::Relaxed);
-
-    // Notify the RTC that the interrupt has ended.
-    RTC::default().notify_interrupt_end();
+    // Reading status register C tells which interrupts fired, and ends the interrupt.
+    let status = RTC::default().interrupt_status();
 
-    unsafe {
-        PICS.lock()
-            .notify_end_of_interrupt(InterruptIndex::RTC.as_u8());
+    // Store the last RTC update tick.
+    if RTCInterrupt::Update.is_pending(status) {
+        time::LAST_RTC_UPDATE.store(time::tick(), Ordering::Relaxed);
     }
+    crate::sys::task::rtc::rtc_interrupt(status);
+
+    pic::end_of_interrupt(InterruptIndex::RTC.as_u8());
 
     // crate::sys::task::clock::print(&RTC::new_no_check());
 }
 
+extern "x86-interrupt" fn primary_ata_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    crate::dev::ata::interrupt(0);
+
+    pic::end_of_interrupt(InterruptIndex::PrimaryAta.as_u8());
+}
+
+extern "x86-interrupt" fn secondary_ata_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    crate::dev::ata::interrupt(1);
+
+    pic::end_of_interrupt(InterruptIndex::SecondaryAta.as_u8());
+}
+
+extern "x86-interrupt" fn spurious_interrupt_handler(_stack_frame: InterruptStackFrame) {
+    // Spurious interrupts aren't in service, so they must not be acknowledged.
+}
+
 #[test_case]
 fn test_breakpoint_exception() {
     // Invoke a breakpoint exception.
@@ -1,7 +1,10 @@
+pub mod acpi;
+pub mod apic;
 pub mod calls;
 pub mod gdt;
 pub mod idt;
 pub mod pic;
 pub mod pit;
+pub mod power;
 pub mod task;
 pub mod time;
@@ -1,5 +1,9 @@
 use pic8259::ChainedPics;
 use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+use crate::println;
+use crate::sys::apic;
 
 /// The first PIC offset, used for remapping.
 pub const PIC_1_OFFSET: u8 = 32;
@@ -14,3 +18,48 @@ pub const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
 /// * This is a spinlock because it is shared between multiple CPUs.
 pub static PICS: Mutex<ChainedPics> =
     Mutex::new(unsafe { ChainedPics::new(PIC_1_OFFSET, PIC_2_OFFSET) });
+
+/// Signals the end of an interrupt to the interrupt controller in use.
+///
+/// # Arguments
+///
+/// * `vector` - The vector of the interrupt.
+///
+/// # Notes
+///
+/// * The APICs are used once [`apic::init`] succeeds; until then, and if it fails, the PICs are.
+pub fn end_of_interrupt(vector: u8) {
+    if apic::is_enabled() {
+        apic::end_of_interrupt();
+    } else {
+        unsafe { PICS.lock().notify_end_of_interrupt(vector) };
+    }
+}
+
+/// Unmasks an ISA interrupt at the interrupt controller in use.
+///
+/// # Arguments
+///
+/// * `irq` - The ISA interrupt.
+///
+/// # Notes
+///
+/// * Interrupts of the secondary PIC also unmask the cascade they go through.
+pub fn unmask(irq: u8) {
+    if apic::is_enabled() {
+        if !apic::set_irq_masked(irq, false) {
+            println!("[WARN]: No I/O APIC handles IRQ {irq}");
+        }
+        return;
+    }
+
+    without_interrupts(|| unsafe {
+        let mut pics = PICS.lock();
+        let [primary, secondary] = pics.read_masks();
+        if irq < 8 {
+            pics.write_masks(primary & !(1 << irq), secondary);
+        } else {
+            pics.write_masks(primary & !(1 << 2), secondary & !(1 << (irq - 8)));
+        }
+    });
+}
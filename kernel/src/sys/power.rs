@@ -0,0 +1,281 @@
+use alloc::format;
+use core::convert::Infallible;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use x86_64::instructions::tables::lidt;
+use x86_64::structures::DescriptorTablePointer;
+use x86_64::VirtAddr;
+
+use crate::errors::Error;
+use crate::println;
+use crate::sys::acpi::{self, Fadt};
+use crate::sys::time;
+use crate::{dev, fs};
+
+/// The PM1 control flag set while the system is in ACPI mode.
+const SCI_EN: u64 = 1;
+/// The shift of the sleep type in the PM1 control registers.
+const SLP_TYP_SHIFT: u64 = 10;
+/// The mask of the sleep type in the PM1 control registers.
+const SLP_TYP_MASK: u64 = 0b111 << SLP_TYP_SHIFT;
+/// The PM1 control flag that enters the sleep state.
+const SLP_EN: u64 = 1 << 13;
+
+/// How long to wait for the firmware to switch to ACPI mode, in nanoseconds.
+const ACPI_ENABLE_TIMEOUT: u64 = 3_000_000_000;
+/// How long to wait for each way of resetting or powering off to take effect, in nanoseconds.
+const RESET_DELAY: u64 = 500_000_000;
+
+/// The keyboard controller status and command port.
+const KEYBOARD_CONTROLLER: u16 = 0x64;
+/// The keyboard controller status flag set while its input buffer is full.
+const KEYBOARD_INPUT_FULL: u8 = 1 << 1;
+/// The keyboard controller command that pulses the reset line.
+const KEYBOARD_RESET: u8 = 0xFE;
+
+/// The AML name opcode.
+const AML_NAME: u8 = 0x08;
+/// The AML root prefix.
+const AML_ROOT: u8 = 0x5C;
+/// The AML package opcode.
+const AML_PACKAGE: u8 = 0x12;
+
+/// Powers the system off, entering the ACPI S5 sleep state.
+///
+/// # Returns
+///
+/// * `Result<Infallible, Error>` - Only returns if the system is still running.
+///
+/// # Errors
+///
+/// * If there is no FADT, or it has no PM1a control register.
+/// * If the DSDT has no `\_S5` package this can decode.
+/// * If the firmware doesn't switch to ACPI mode.
+/// * If the system is still running after entering the sleep state.
+pub fn shutdown() -> Result<Infallible, Error> {
+    let fadt = acpi::fadt().ok_or_else(|| Error::ACPI("No FADT to power off with!".into()))?;
+    let pm1a_control = fadt
+        .pm1a_control
+        .ok_or_else(|| Error::ACPI("FADT has no PM1a control register!".into()))?;
+
+    let dsdt = acpi::find_table(b"DSDT")?
+        .ok_or_else(|| Error::ACPI("No DSDT to find the S5 sleep type in!".into()))?;
+    let (sleep_type_a, sleep_type_b) = find_s5(dsdt.data())?;
+
+    enable_acpi_mode(fadt)?;
+
+    sync();
+    println!("[INFO]: Powering off...");
+    interrupts::disable();
+
+    // PM1b is written first, as writing PM1a may power off the system at once.
+    if let Some(pm1b_control) = fadt.pm1b_control {
+        let value = pm1b_control.read()? & !SLP_TYP_MASK;
+        pm1b_control.write(value | u64::from(sleep_type_b) << SLP_TYP_SHIFT | SLP_EN)?;
+    }
+    let value = pm1a_control.read()? & !SLP_TYP_MASK;
+    pm1a_control.write(value | u64::from(sleep_type_a) << SLP_TYP_SHIFT | SLP_EN)?;
+
+    time::wait(RESET_DELAY);
+    interrupts::enable();
+
+    Err(Error::ACPI(
+        "System is still running after entering S5!".into(),
+    ))
+}
+
+/// Restarts the system.
+///
+/// # Notes
+///
+/// * The ACPI reset register is tried first, then the keyboard controller, and finally a triple fault.
+pub fn reboot() -> ! {
+    sync();
+    println!("[INFO]: Restarting...");
+    interrupts::disable();
+
+    // Reset through the ACPI reset register.
+    if let Some(fadt) = acpi::fadt().filter(|fadt| fadt.reset_supported()) {
+        if let Some(reset) = fadt.reset {
+            match reset.write(u64::from(fadt.reset_value)) {
+                Ok(()) => time::wait(RESET_DELAY),
+                Err(why) => println!("[WARN]: Failed to use the ACPI reset register: {why}"),
+            }
+        }
+    }
+
+    // Pulse the reset line through the keyboard controller.
+    if acpi::fadt().is_none_or(Fadt::has_8042) {
+        let mut port: Port<u8> = Port::new(KEYBOARD_CONTROLLER);
+        unsafe {
+            for _ in 0..0x10000 {
+                if port.read() & KEYBOARD_INPUT_FULL == 0 {
+                    break;
+                }
+            }
+            port.write(KEYBOARD_RESET);
+        }
+        time::wait(RESET_DELAY);
+    }
+
+    // Triple fault, as no exception can be delivered without an interrupt descriptor table.
+    unsafe {
+        lidt(&DescriptorTablePointer {
+            limit: 0,
+            base: VirtAddr::new(0),
+        });
+    }
+    x86_64::instructions::interrupts::int3();
+
+    crate::hlt_loop()
+}
+
+/// Writes the file systems and the block cache back to their devices, before cutting power.
+///
+/// # Notes
+///
+/// * Failures are only logged, as the system goes down either way.
+/// * Interrupts must be enabled, so the drives can complete the writes.
+fn sync() {
+    if let Err(why) = fs::sync() {
+        println!("[WARN]: Failed to sync the file systems: {why}");
+    }
+
+    if let Err(why) = dev::cache::sync() {
+        println!("[WARN]: Failed to flush the block cache: {why}");
+    }
+}
+
+/// Switches the firmware to ACPI mode, if it isn't already.
+///
+/// # Arguments
+///
+/// * `fadt` - The fixed ACPI description table.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the PM1a control register can't be accessed.
+/// * If the firmware doesn't switch in time.
+fn enable_acpi_mode(fadt: &Fadt) -> Result<(), Error> {
+    let Some(pm1a_control) = fadt.pm1a_control else {
+        return Ok(());
+    };
+
+    // Hardware without a SMI command port is always in ACPI mode.
+    if pm1a_control.read()? & SCI_EN != 0 || fadt.smi_command == 0 || fadt.acpi_enable == 0 {
+        return Ok(());
+    }
+
+    let mut port: Port<u8> = Port::new(u16::try_from(fadt.smi_command)?);
+    unsafe { port.write(fadt.acpi_enable) };
+
+    let step = 10_000_000;
+    for _ in 0..ACPI_ENABLE_TIMEOUT / step {
+        if pm1a_control.read()? & SCI_EN != 0 {
+            return Ok(());
+        }
+        time::wait(step);
+    }
+
+    Err(Error::ACPI("Firmware didn't switch to ACPI mode!".into()))
+}
+
+/// Finds the sleep types of the S5 state in the AML of the DSDT.
+///
+/// # Arguments
+///
+/// * `aml` - The AML of the DSDT.
+///
+/// # Returns
+///
+/// * `Result<(u8, u8), Error>` - The sleep types of the PM1a and PM1b control registers.
+///
+/// # Errors
+///
+/// * If there is no `\_S5` package.
+/// * If the package can't be decoded.
+///
+/// # Notes
+///
+/// * Only a `Name (_S5, Package () { ... })` with constant elements is understood, as this is no AML interpreter.
+fn find_s5(aml: &[u8]) -> Result<(u8, u8), Error> {
+    let not_found = || Error::ACPI("DSDT has no S5 sleep type package!".into());
+
+    let position = (0..aml.len().saturating_sub(4))
+        .find(|&offset| {
+            &aml[offset..offset + 4] == b"_S5_"
+                && (offset >= 1 && aml[offset - 1] == AML_NAME
+                    || offset >= 2 && aml[offset - 1] == AML_ROOT && aml[offset - 2] == AML_NAME)
+                && aml.get(offset + 4) == Some(&AML_PACKAGE)
+        })
+        .ok_or_else(not_found)?;
+
+    // The package length takes 1 to 4 bytes, the count being in the top bits of the first one.
+    let mut offset = position + 5;
+    let lead = *aml.get(offset).ok_or_else(not_found)?;
+    offset += 1 + usize::from(lead >> 6);
+
+    // The number of elements precedes them.
+    let count = *aml.get(offset).ok_or_else(not_found)?;
+    offset += 1;
+    if count < 1 {
+        return Err(not_found());
+    }
+
+    let sleep_type_a = read_integer(aml, &mut offset)?;
+    let sleep_type_b = if count >= 2 {
+        read_integer(aml, &mut offset)?
+    } else {
+        0
+    };
+
+    Ok((sleep_type_a, sleep_type_b))
+}
+
+/// Reads a constant AML integer, truncated to a byte.
+///
+/// # Arguments
+///
+/// * `aml` - The AML.
+/// * `offset` - The offset of the integer, moved past it.
+///
+/// # Returns
+///
+/// * `Result<u8, Error>` - The integer.
+///
+/// # Errors
+///
+/// * If the AML at the offset isn't a constant integer.
+fn read_integer(aml: &[u8], offset: &mut usize) -> Result<u8, Error> {
+    let opcode = *aml
+        .get(*offset)
+        .ok_or_else(|| Error::ACPI("S5 package is truncated!".into()))?;
+
+    // The zero and one opcodes, then the byte, word, double word and quad word prefixes.
+    let (value, size) = match opcode {
+        0x00 => (0, 0),
+        0x01 => (1, 0),
+        0x0A | 0x0B | 0x0C | 0x0E => (
+            *aml.get(*offset + 1)
+                .ok_or_else(|| Error::ACPI("S5 package is truncated!".into()))?,
+            match opcode {
+                0x0A => 1,
+                0x0B => 2,
+                0x0C => 4,
+                _ => 8,
+            },
+        ),
+        opcode => {
+            return Err(Error::ACPI(format!(
+                "S5 package has an unsupported element with opcode {opcode:#04X}!"
+            )))
+        }
+    };
+
+    *offset += 1 + size;
+    Ok(value)
+}
@@ -7,7 +7,9 @@ pub mod clock;
 pub mod executor;
 pub mod keyboard;
 pub mod primes;
+pub mod rtc;
 pub mod simple_executor;
+pub mod timer;
 
 /// A task.
 ///
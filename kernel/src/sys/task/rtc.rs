@@ -0,0 +1,304 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use core::future::Future;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use futures_util::Stream;
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+use crate::errors::Error;
+use crate::sys::time::rtc::{Alarm, RTCInterrupt, RTC};
+
+/// The number of alarm interrupts so far.
+static ALARMS: AtomicU64 = AtomicU64::new(0);
+/// The number of periodic interrupts so far.
+static PERIODIC_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// The tasks waiting for the alarm.
+static ALARM_WAITERS: Waiters = Waiters::new();
+/// The tasks waiting for a periodic tick.
+static PERIODIC_WAITERS: Waiters = Waiters::new();
+
+/// The number of periodic tick subscriptions, which keep the periodic interrupt enabled.
+static PERIODIC_SUBSCRIBERS: AtomicUsize = AtomicUsize::new(0);
+
+/// The ID of the next waiter.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The fastest periodic rate that doesn't lose interrupts, at 8192 Hz.
+pub const MIN_PERIODIC_RATE: u8 = 3;
+/// The slowest periodic rate, at 2 Hz.
+pub const MAX_PERIODIC_RATE: u8 = 15;
+
+/// Called by the RTC interrupt handler
+///
+/// Must not block or allocate.
+///
+/// # Arguments
+///
+/// * `status` - The status C register, telling which interrupts fired.
+pub(crate) fn rtc_interrupt(status: u8) {
+    if RTCInterrupt::Alarm.is_pending(status) {
+        ALARMS.fetch_add(1, Ordering::Relaxed);
+        ALARM_WAITERS.wake();
+    }
+
+    if RTCInterrupt::Periodic.is_pending(status) {
+        PERIODIC_TICKS.fetch_add(1, Ordering::Relaxed);
+        PERIODIC_WAITERS.wake();
+    }
+}
+
+/// Gets the frequency of a periodic rate.
+///
+/// # Arguments
+///
+/// * `rate` - The periodic rate, from [`MIN_PERIODIC_RATE`] to [`MAX_PERIODIC_RATE`].
+///
+/// # Returns
+///
+/// * `u32` - The frequency, in Hz.
+#[must_use]
+pub const fn periodic_frequency(rate: u8) -> u32 {
+    32_768 >> (rate - 1)
+}
+
+/// The wakers of the tasks waiting for an RTC interrupt.
+///
+/// # Notes
+///
+/// * Tasks only lock the wakers with interrupts disabled, so the lock is always free in the interrupt handler.
+struct Waiters(Mutex<BTreeMap<u64, Waker>>);
+
+impl Waiters {
+    /// Creates an empty set of waiters.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The waiters.
+    const fn new() -> Self {
+        Self(Mutex::new(BTreeMap::new()))
+    }
+
+    /// Registers the waker of a task, or replaces it.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the waiter.
+    /// * `waker` - The waker of the task.
+    fn register(&self, id: u64, waker: &Waker) {
+        without_interrupts(|| {
+            let mut wakers = self.0.lock();
+            match wakers.get_mut(&id) {
+                // The task may have moved to another waker since the last poll.
+                Some(registered) => {
+                    if !registered.will_wake(waker) {
+                        registered.clone_from(waker);
+                    }
+                }
+                None => {
+                    wakers.insert(id, waker.clone());
+                }
+            }
+        });
+    }
+
+    /// Unregisters a waiter, if it's registered.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the waiter.
+    fn unregister(&self, id: u64) {
+        without_interrupts(|| {
+            self.0.lock().remove(&id);
+        });
+    }
+
+    /// Wakes every waiting task, without removing the wakers.
+    fn wake(&self) {
+        if let Some(wakers) = self.0.try_lock() {
+            for waker in wakers.values() {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+/// A future that completes on the next alarm interrupt.
+///
+/// # Fields
+///
+/// * `id` - The ID of the waiter.
+/// * `seen` - The number of alarm interrupts when the future was created.
+#[derive(Debug)]
+pub struct AlarmWait {
+    id: u64,
+    seen: u64,
+}
+
+impl AlarmWait {
+    /// Creates a future that completes on the next alarm interrupt.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The future.
+    ///
+    /// # Notes
+    ///
+    /// * Alarms that fire after this is created complete it, even if it wasn't polled yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            seen: ALARMS.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for AlarmWait {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Future for AlarmWait {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if ALARMS.load(Ordering::Relaxed) != self.seen {
+            ALARM_WAITERS.unregister(self.id);
+            return Poll::Ready(());
+        }
+
+        ALARM_WAITERS.register(self.id, cx.waker());
+
+        // The alarm may have fired before the waker was registered.
+        if ALARMS.load(Ordering::Relaxed) == self.seen {
+            Poll::Pending
+        } else {
+            ALARM_WAITERS.unregister(self.id);
+            Poll::Ready(())
+        }
+    }
+}
+
+impl Drop for AlarmWait {
+    fn drop(&mut self) {
+        ALARM_WAITERS.unregister(self.id);
+    }
+}
+
+/// Sets the alarm, and waits for it to go off.
+///
+/// # Arguments
+///
+/// * `alarm` - The time of the alarm.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If a field of the alarm is out of range.
+///
+/// # Notes
+///
+/// * There is only one alarm, so this replaces the alarm of every other waiting task.
+pub async fn alarm(alarm: Alarm) -> Result<(), Error> {
+    let wait = AlarmWait::new();
+    RTC::default().set_alarm(&alarm)?;
+
+    wait.await;
+    Ok(())
+}
+
+/// A stream that yields on the periodic interrupt of the RTC.
+///
+/// # Fields
+///
+/// * `id` - The ID of the waiter.
+/// * `seen` - The number of periodic interrupts when the stream last yielded.
+///
+/// # Notes
+///
+/// * The periodic interrupt is enabled while there is a subscription.
+#[derive(Debug)]
+pub struct PeriodicTicks {
+    id: u64,
+    seen: u64,
+}
+
+impl PeriodicTicks {
+    /// Subscribes to the periodic interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - The periodic rate, from [`MIN_PERIODIC_RATE`] to [`MAX_PERIODIC_RATE`], see [`periodic_frequency`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Self, Error>` - The stream.
+    ///
+    /// # Errors
+    ///
+    /// * If the rate is out of range.
+    ///
+    /// # Notes
+    ///
+    /// * There is only one periodic rate, so this changes the rate of every other subscription.
+    pub fn new(rate: u8) -> Result<Self, Error> {
+        if !(MIN_PERIODIC_RATE..=MAX_PERIODIC_RATE).contains(&rate) {
+            return Err(Error::Internal(format!(
+                "Invalid RTC periodic rate: {rate}"
+            )));
+        }
+
+        let mut rtc = RTC::default();
+        rtc.set_periodic_rate(rate);
+        if PERIODIC_SUBSCRIBERS.fetch_add(1, Ordering::Relaxed) == 0 {
+            rtc.set_interrupt(&RTCInterrupt::Periodic, true);
+        }
+
+        Ok(Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            seen: PERIODIC_TICKS.load(Ordering::Relaxed),
+        })
+    }
+}
+
+impl Stream for PeriodicTicks {
+    /// The number of periodic interrupts since the last item, more than 1 if some were missed.
+    type Item = u64;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<u64>> {
+        let mut ticks = PERIODIC_TICKS.load(Ordering::Relaxed);
+        if ticks == self.seen {
+            PERIODIC_WAITERS.register(self.id, cx.waker());
+
+            // A tick may have come before the waker was registered.
+            ticks = PERIODIC_TICKS.load(Ordering::Relaxed);
+            if ticks == self.seen {
+                return Poll::Pending;
+            }
+        }
+
+        let elapsed = ticks.wrapping_sub(self.seen);
+        self.seen = ticks;
+
+        Poll::Ready(Some(elapsed))
+    }
+}
+
+impl Drop for PeriodicTicks {
+    fn drop(&mut self) {
+        PERIODIC_WAITERS.unregister(self.id);
+
+        if PERIODIC_SUBSCRIBERS.fetch_sub(1, Ordering::Relaxed) == 1 {
+            RTC::default().set_interrupt(&RTCInterrupt::Periodic, false);
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use alloc::collections::BTreeMap;
+use core::future::Future;
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+
+use futures_util::future::{select, Either};
+use futures_util::Stream;
+use spin::Mutex;
+use x86_64::instructions::interrupts::without_interrupts;
+
+use crate::sys::time::clocksource::monotonic_now;
+
+/// The registered timers, by deadline and ID.
+static TIMERS: Mutex<BTreeMap<(u64, u64), Timer>> = Mutex::new(BTreeMap::new());
+
+/// The earliest deadline of a timer that hasn't fired, in nanoseconds.
+static NEXT_DEADLINE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// The ID of the next timer.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A registered timer.
+///
+/// # Fields
+///
+/// * `waker` - The waker of the task waiting for the timer.
+/// * `fired` - Whether or not the task was woken.
+///
+/// # Notes
+///
+/// * Fired timers stay registered until their future drops them, so the interrupt handler never deallocates.
+struct Timer {
+    waker: Waker,
+    fired: bool,
+}
+
+/// Called by the timer interrupt handler
+///
+/// Must not block or allocate.
+///
+/// # Notes
+///
+/// * Tasks only lock the timers with interrupts disabled, so the lock is always free here.
+pub(crate) fn timer_tick() {
+    let now = monotonic_now();
+    if now < NEXT_DEADLINE.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let Some(mut timers) = TIMERS.try_lock() else {
+        return;
+    };
+
+    for timer in timers
+        .range_mut(..=(now, u64::MAX))
+        .map(|(_, timer)| timer)
+        .filter(|timer| !timer.fired)
+    {
+        timer.waker.wake_by_ref();
+        timer.fired = true;
+    }
+
+    NEXT_DEADLINE.store(next_deadline(&timers), Ordering::Relaxed);
+}
+
+/// Finds the earliest deadline of a timer that hasn't fired.
+///
+/// # Arguments
+///
+/// * `timers` - The registered timers.
+///
+/// # Returns
+///
+/// * `u64` - The deadline, in nanoseconds, or `u64::MAX` if there is none.
+fn next_deadline(timers: &BTreeMap<(u64, u64), Timer>) -> u64 {
+    timers
+        .iter()
+        .find(|(_, timer)| !timer.fired)
+        .map_or(u64::MAX, |(&(deadline, _), _)| deadline)
+}
+
+/// Converts a duration to nanoseconds.
+///
+/// # Arguments
+///
+/// * `duration` - The duration.
+///
+/// # Returns
+///
+/// * `u64` - The duration, in nanoseconds, saturating at `u64::MAX`.
+fn nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// A future that completes once a deadline has passed.
+///
+/// # Fields
+///
+/// * `deadline` - The monotonic time to wait for, in nanoseconds.
+/// * `key` - The key of the registered timer, if any.
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: u64,
+    key: Option<(u64, u64)>,
+}
+
+impl Sleep {
+    /// Creates a future that completes after a duration.
+    ///
+    /// # Arguments
+    ///
+    /// * `duration` - The duration.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The future.
+    #[must_use]
+    pub fn new(duration: Duration) -> Self {
+        Self::until(monotonic_now().saturating_add(nanos(duration)))
+    }
+
+    /// Creates a future that completes at a deadline.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The monotonic time to wait for, in nanoseconds.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The future.
+    #[must_use]
+    pub const fn until(deadline: u64) -> Self {
+        Self {
+            deadline,
+            key: None,
+        }
+    }
+
+    /// Gets the deadline.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The monotonic time to wait for, in nanoseconds.
+    #[must_use]
+    pub const fn deadline(&self) -> u64 {
+        self.deadline
+    }
+
+    /// Moves the deadline, unregistering the timer.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - The monotonic time to wait for, in nanoseconds.
+    pub fn reset(&mut self, deadline: u64) {
+        self.unregister();
+        self.deadline = deadline;
+    }
+
+    /// Unregisters the timer, if it's registered.
+    fn unregister(&mut self) {
+        if let Some(key) = self.key.take() {
+            without_interrupts(|| {
+                let mut timers = TIMERS.lock();
+                timers.remove(&key);
+                NEXT_DEADLINE.store(next_deadline(&timers), Ordering::Relaxed);
+            });
+        }
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if monotonic_now() >= self.deadline {
+            self.unregister();
+            return Poll::Ready(());
+        }
+
+        let deadline = self.deadline;
+        let key = *self
+            .key
+            .get_or_insert_with(|| (deadline, NEXT_ID.fetch_add(1, Ordering::Relaxed)));
+
+        without_interrupts(|| {
+            let mut timers = TIMERS.lock();
+            match timers.get_mut(&key) {
+                // The task may have moved to another waker since the last poll.
+                Some(timer) => {
+                    if !timer.waker.will_wake(cx.waker()) {
+                        timer.waker = cx.waker().clone();
+                    }
+                    timer.fired = false;
+                }
+                None => {
+                    timers.insert(
+                        key,
+                        Timer {
+                            waker: cx.waker().clone(),
+                            fired: false,
+                        },
+                    );
+                }
+            }
+
+            NEXT_DEADLINE.fetch_min(deadline, Ordering::Relaxed);
+        });
+
+        // The deadline may have passed before the timer was registered.
+        if monotonic_now() >= self.deadline {
+            self.unregister();
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        self.unregister();
+    }
+}
+
+/// A stream that yields at a fixed period.
+///
+/// # Fields
+///
+/// * `period` - The period, in nanoseconds.
+/// * `sleep` - The sleep until the next tick.
+///
+/// # Notes
+///
+/// * Ticks missed by a busy task are skipped, rather than yielded in a burst.
+#[derive(Debug)]
+pub struct Interval {
+    period: u64,
+    sleep: Sleep,
+}
+
+impl Interval {
+    /// Creates a stream whose first tick is one period from now.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - The period, which is at least a nanosecond.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The stream.
+    #[must_use]
+    pub fn new(period: Duration) -> Self {
+        let period = nanos(period).max(1);
+
+        Self {
+            period,
+            sleep: Sleep::until(monotonic_now().saturating_add(period)),
+        }
+    }
+
+    /// Waits for the next tick.
+    pub async fn tick(&mut self) {
+        futures_util::StreamExt::next(self).await;
+    }
+}
+
+impl Stream for Interval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<()>> {
+        if Pin::new(&mut self.sleep).poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        // Skip the ticks that were missed.
+        let now = monotonic_now();
+        let mut next = self.sleep.deadline().saturating_add(self.period);
+        if next <= now {
+            next = now.saturating_add(self.period - (now - self.sleep.deadline()) % self.period);
+        }
+        self.sleep.reset(next);
+
+        Poll::Ready(Some(()))
+    }
+}
+
+/// Waits for a duration, without blocking other tasks.
+///
+/// # Arguments
+///
+/// * `duration` - The duration.
+pub async fn sleep(duration: Duration) {
+    Sleep::new(duration).await;
+}
+
+/// Runs a future, giving up once a duration has passed.
+///
+/// # Arguments
+///
+/// * `duration` - The duration.
+/// * `future` - The future.
+///
+/// # Returns
+///
+/// * `Option<F::Output>` - The output of the future, or `None` if the duration passed first.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let future = pin!(future);
+    let sleep = pin!(sleep(duration));
+
+    match select(future, sleep).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(((), _)) => None,
+    }
+}
@@ -1,4 +1,16 @@
-use crate::sys::time;
+use core::sync::atomic::{AtomicI64, Ordering};
+
+use crate::errors::Error;
+use crate::println;
+use crate::sys::time::clocksource;
+use crate::sys::time::date::DateTime;
+use crate::sys::time::rtc::RTC;
+
+/// The number of nanoseconds in a second.
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// The Unix time at a monotonic time of zero, in nanoseconds.
+static BOOT_TIME: AtomicI64 = AtomicI64::new(0);
 
 /// Gets the uptime of the sys.
 ///
@@ -7,5 +19,92 @@ use crate::sys::time;
 /// * `f64` - The uptime of the system in seconds.
 #[must_use]
 pub fn uptime() -> f64 {
-    time::pit_interval() * time::tick() as f64
+    clocksource::monotonic_now() as f64 / 1e9
+}
+
+/// Gets the wall clock time.
+///
+/// # Returns
+///
+/// * `i64` - The nanoseconds since the Unix epoch.
+#[must_use]
+pub fn unix_nanos() -> i64 {
+    let monotonic = i64::try_from(clocksource::monotonic_now()).unwrap_or(i64::MAX);
+
+    BOOT_TIME.load(Ordering::Relaxed).saturating_add(monotonic)
+}
+
+/// Gets the wall clock time.
+///
+/// # Returns
+///
+/// * `i64` - The seconds since the Unix epoch.
+#[must_use]
+pub fn unix_time() -> i64 {
+    unix_nanos().div_euclid(NANOS_PER_SECOND)
+}
+
+/// Gets the wall clock date and time.
+///
+/// # Returns
+///
+/// * `DateTime` - The date and time, in UTC.
+#[must_use]
+pub fn now() -> DateTime {
+    DateTime::from_unix(unix_time())
+}
+
+/// Sets the wall clock.
+///
+/// # Arguments
+///
+/// * `seconds` - The seconds since the Unix epoch.
+///
+/// # Notes
+///
+/// * This doesn't change the RTC.
+pub fn set_unix_time(seconds: i64) {
+    let monotonic = i64::try_from(clocksource::monotonic_now()).unwrap_or(i64::MAX);
+
+    BOOT_TIME.store(
+        seconds
+            .saturating_mul(NANOS_PER_SECOND)
+            .saturating_sub(monotonic),
+        Ordering::Relaxed,
+    );
+}
+
+/// Sets the wall clock and the RTC.
+///
+/// # Arguments
+///
+/// * `datetime` - The date and time, in UTC.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If the RTC can't hold the date and time.
+pub fn set(datetime: &DateTime) -> Result<(), Error> {
+    RTC::default().set(datetime)?;
+    set_unix_time(datetime.to_unix());
+
+    Ok(())
+}
+
+/// Sets the wall clock from the RTC.
+///
+/// # Notes
+///
+/// * The RTC is only read here; the wall clock is then advanced by the monotonic clock.
+pub fn init() {
+    let datetime = RTC::new().datetime();
+    if !datetime.is_valid() {
+        println!("[WARN]: RTC has an invalid date and time: {datetime}");
+    }
+
+    set_unix_time(datetime.to_unix());
+    println!("[INFO]: => {datetime} UTC");
 }
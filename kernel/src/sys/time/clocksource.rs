@@ -0,0 +1,333 @@
+use alloc::format;
+use conquer_once::spin::OnceCell;
+use core::fmt::{self, Display, Formatter};
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicU64, Ordering};
+use x86_64::{PhysAddr, VirtAddr};
+
+use crate::errors::Error;
+use crate::mem::map_mmio;
+use crate::println;
+use crate::sys::acpi::{self, SPACE_SYSTEM_MEMORY};
+use crate::sys::time;
+
+/// The number of nanoseconds in a second.
+const NANOS_PER_SECOND: u128 = 1_000_000_000;
+
+/// How long the TSC is calibrated for, in nanoseconds.
+const CALIBRATION_TIME: u64 = 50_000_000;
+
+/// The HPET general capabilities register, holding the period of the counter in femtoseconds in its high half.
+const HPET_CAPABILITIES: u64 = 0x000;
+/// The HPET general configuration register.
+const HPET_CONFIGURATION: u64 = 0x010;
+/// The HPET main counter register.
+const HPET_COUNTER: u64 = 0x0F0;
+/// The flag of the HPET configuration register that starts the main counter.
+const HPET_ENABLE: u64 = 1;
+/// The longest period of the HPET counter allowed, in femtoseconds.
+const HPET_MAX_PERIOD: u64 = 100_000_000;
+/// The number of femtoseconds in a second.
+const FEMTOS_PER_SECOND: u64 = 1_000_000_000_000_000;
+
+/// The clock source used by [`monotonic_now`], once [`init`] selects one.
+static CLOCK: OnceCell<Clock> = OnceCell::uninit();
+
+/// The frequency of the time-stamp counter, in Hz.
+static TSC_FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// The kinds of clock sources.
+///
+/// # Variants
+///
+/// * `Tsc` - The invariant time-stamp counter of the processor.
+/// * `Hpet` - The main counter of the high precision event timer.
+/// * `Pit` - The interrupts of the programmable interval timer, the fallback with a resolution of a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Tsc,
+    Hpet,
+    Pit,
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Tsc => write!(f, "TSC"),
+            Self::Hpet => write!(f, "HPET"),
+            Self::Pit => write!(f, "PIT"),
+        }
+    }
+}
+
+/// A free-running counter the monotonic clock is read from.
+///
+/// # Fields
+///
+/// * `kind` - The kind of the counter.
+/// * `counter` - The virtual address of the counter, for memory-mapped ones.
+/// * `frequency` - The frequency of the counter, in Hz.
+/// * `start_counter` - The value of the counter when it was selected.
+/// * `start_ns` - The monotonic time when it was selected, in nanoseconds.
+#[derive(Debug)]
+struct Clock {
+    kind: Kind,
+    counter: VirtAddr,
+    frequency: u64,
+    start_counter: u64,
+    start_ns: u64,
+}
+
+impl Clock {
+    /// Creates a clock continuing from the current monotonic time.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The kind of the counter.
+    /// * `counter` - The virtual address of the counter, for memory-mapped ones.
+    /// * `frequency` - The frequency of the counter, in Hz.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The clock.
+    fn new(kind: Kind, counter: VirtAddr, frequency: u64) -> Self {
+        let mut clock = Self {
+            kind,
+            counter,
+            frequency,
+            start_counter: 0,
+            start_ns: monotonic_now(),
+        };
+        clock.start_counter = clock.read();
+
+        clock
+    }
+
+    /// Reads the counter.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The value of the counter.
+    fn read(&self) -> u64 {
+        match self.kind {
+            Kind::Tsc => read_tsc(),
+            Kind::Hpet => unsafe { core::ptr::read_volatile(self.counter.as_ptr::<u64>()) },
+            Kind::Pit => time::tick() as u64,
+        }
+    }
+
+    /// Gets the monotonic time.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The time, in nanoseconds.
+    #[allow(clippy::cast_possible_truncation)]
+    fn now(&self) -> u64 {
+        let elapsed = u128::from(self.read().wrapping_sub(self.start_counter));
+
+        self.start_ns + (elapsed * NANOS_PER_SECOND / u128::from(self.frequency)) as u64
+    }
+}
+
+/// Gets the monotonic time since boot.
+///
+/// # Returns
+///
+/// * `u64` - The time, in nanoseconds.
+///
+/// # Notes
+///
+/// * Until [`init`] selects a clock source, the resolution is a PIT tick.
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn monotonic_now() -> u64 {
+    match CLOCK.get() {
+        Some(clock) => clock.now(),
+        None => (time::tick() as f64 * time::pit_interval() * 1e9) as u64,
+    }
+}
+
+/// Gets the kind of the clock source in use.
+///
+/// # Returns
+///
+/// * `Kind` - The kind of the clock source.
+#[must_use]
+pub fn current() -> Kind {
+    CLOCK.get().map_or(Kind::Pit, |clock| clock.kind)
+}
+
+/// Reads the time-stamp counter.
+///
+/// # Returns
+///
+/// * `u64` - The time-stamp counter.
+pub(crate) fn read_tsc() -> u64 {
+    unsafe {
+        core::arch::x86_64::_mm_lfence(); // Prevents instruction reordering.
+        core::arch::x86_64::_rdtsc() // Reads the time-stamp counter.
+    }
+}
+
+/// Gets the frequency of the time-stamp counter.
+///
+/// # Returns
+///
+/// * `u64` - The frequency, in Hz, or 0 if it isn't calibrated yet.
+pub fn tsc_frequency() -> u64 {
+    TSC_FREQUENCY.load(Ordering::Relaxed)
+}
+
+/// Calibrates the time-stamp counter against the PIT.
+///
+/// # Notes
+///
+/// * The PIT must be ticking, with interrupts enabled.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub(crate) fn calibrate_tsc() {
+    let ticks = (CALIBRATION_TIME as f64 / 1e9 / time::pit_interval()) as usize + 1;
+
+    // Start on a tick boundary, so no partial tick is counted.
+    let first = time::tick();
+    while time::tick() == first {
+        spin_loop();
+    }
+
+    let start_tick = time::tick();
+    let start = read_tsc();
+    while time::tick() - start_tick < ticks {
+        spin_loop();
+    }
+    let cycles = read_tsc() - start;
+
+    let elapsed = ticks as f64 * time::pit_interval();
+    TSC_FREQUENCY.store((cycles as f64 / elapsed) as u64, Ordering::Relaxed);
+}
+
+/// Selects the most precise clock source, and recalibrates the time-stamp counter against the HPET.
+///
+/// # Returns
+///
+/// * `Result<(), Error>` - The result of the operation.
+///
+/// # Errors
+///
+/// * If a clock source was already selected.
+///
+/// # Notes
+///
+/// * An invariant TSC is preferred, then a 64-bit HPET, and the PIT otherwise.
+/// * This must run after the ACPI tables are read.
+pub fn init() -> Result<(), Error> {
+    let hpet = match init_hpet() {
+        Ok(hpet) => hpet,
+        Err(why) => {
+            println!("[WARN]: Not using the HPET: {why}");
+            None
+        }
+    };
+
+    if let Some((counter, frequency)) = hpet {
+        calibrate_tsc_against(counter, frequency);
+    }
+
+    let clock = if has_invariant_tsc() && tsc_frequency() != 0 {
+        Some(Clock::new(Kind::Tsc, VirtAddr::zero(), tsc_frequency()))
+    } else {
+        hpet.map(|(counter, frequency)| Clock::new(Kind::Hpet, counter, frequency))
+    };
+
+    if let Some(clock) = clock {
+        println!(
+            "[INFO]: => {kind} at {frequency} Hz",
+            kind = clock.kind,
+            frequency = clock.frequency
+        );
+        CLOCK
+            .try_init_once(|| clock)
+            .map_err(|_| Error::Internal("Clock source is already selected!".into()))?;
+    } else {
+        println!("[INFO]: => PIT");
+    }
+
+    Ok(())
+}
+
+/// Starts the main counter of the HPET.
+///
+/// # Returns
+///
+/// * `Result<Option<(VirtAddr, u64)>, Error>` - The virtual address and the frequency of the main counter, or `None` if there is no HPET.
+///
+/// # Errors
+///
+/// * If the HPET isn't memory-mapped, or its counter is only 32 bits wide.
+/// * If the registers fail to map.
+/// * If the period of the counter is invalid.
+fn init_hpet() -> Result<Option<(VirtAddr, u64)>, Error> {
+    let Some(hpet) = acpi::hpet() else {
+        return Ok(None);
+    };
+
+    if hpet.address.space != SPACE_SYSTEM_MEMORY {
+        return Err(Error::Internal("HPET isn't memory-mapped!".into()));
+    }
+    // A 32-bit counter wraps every few minutes, which the clock doesn't track.
+    if !hpet.counter_64bit {
+        return Err(Error::Internal("HPET counter is only 32 bits wide!".into()));
+    }
+
+    let base = map_mmio(PhysAddr::new(hpet.address.address), 0x400)?;
+    let register = |offset: u64| (base + offset).as_mut_ptr::<u64>();
+
+    let period = unsafe { core::ptr::read_volatile(register(HPET_CAPABILITIES)) } >> 32;
+    if period == 0 || period > HPET_MAX_PERIOD {
+        return Err(Error::Internal(format!(
+            "HPET has an invalid period of {period} fs!"
+        )));
+    }
+
+    unsafe {
+        let configuration = core::ptr::read_volatile(register(HPET_CONFIGURATION));
+        core::ptr::write_volatile(register(HPET_CONFIGURATION), configuration | HPET_ENABLE);
+    }
+
+    Ok(Some((base + HPET_COUNTER, FEMTOS_PER_SECOND / period)))
+}
+
+/// Calibrates the time-stamp counter against the HPET.
+///
+/// # Arguments
+///
+/// * `counter` - The virtual address of the main counter.
+/// * `frequency` - The frequency of the main counter, in Hz.
+#[allow(clippy::cast_possible_truncation)]
+fn calibrate_tsc_against(counter: VirtAddr, frequency: u64) {
+    let read = || unsafe { core::ptr::read_volatile(counter.as_ptr::<u64>()) };
+    let ticks = (u128::from(frequency) * u128::from(CALIBRATION_TIME) / NANOS_PER_SECOND) as u64;
+
+    let start_counter = read();
+    let start = read_tsc();
+    let mut elapsed = 0;
+    while elapsed < ticks {
+        spin_loop();
+        elapsed = read().wrapping_sub(start_counter);
+    }
+    let cycles = read_tsc() - start;
+
+    TSC_FREQUENCY.store(
+        (u128::from(cycles) * u128::from(frequency) / u128::from(elapsed)) as u64,
+        Ordering::Relaxed,
+    );
+}
+
+/// Checks if the time-stamp counter runs at a constant rate, in every power state.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the time-stamp counter is invariant.
+fn has_invariant_tsc() -> bool {
+    let highest = core::arch::x86_64::__cpuid(0x8000_0000).eax;
+
+    highest >= 0x8000_0007 && core::arch::x86_64::__cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
@@ -10,14 +10,18 @@ const CMOS_DATA: u8 = 0x71;
 /// # Variants
 ///
 /// * [`Register::Seconds`]
+/// * [`Register::SecondsAlarm`]
 /// * [`Register::Minutes`]
+/// * [`Register::MinutesAlarm`]
 /// * [`Register::Hours`]
+/// * [`Register::HoursAlarm`]
 /// * [`Register::Day`]
 /// * [`Register::Month`]
 /// * [`Register::Year`]
 /// * [`Register::Century`]
 /// * [`Register::StatusA`]
 /// * [`Register::StatusB`]
+/// * [`Register::StatusC`]
 ///
 /// # See
 ///
@@ -26,10 +30,16 @@ const CMOS_DATA: u8 = 0x71;
 pub enum Register {
     /// The seconds register, which is located at `0x00`.
     Seconds = 0x00,
+    /// The seconds alarm register, which is located at `0x01`.
+    SecondsAlarm = 0x01,
     /// The minutes register, which is located at `0x02`.
     Minutes = 0x02,
+    /// The minutes alarm register, which is located at `0x03`.
+    MinutesAlarm = 0x03,
     /// The hours register, which is located at `0x04`.
     Hours = 0x04,
+    /// The hours alarm register, which is located at `0x05`.
+    HoursAlarm = 0x05,
     /// The day register, which is located at `0x07`.
     Day = 0x07,
     /// The month register, which is located at `0x08`.
@@ -52,7 +62,19 @@ pub enum Register {
     /// * This register is used for storing information about the RTC:
     ///   * `Bit 1` - Enable/disable 24-hour format. (0 = 12-hour, 1 = 24-hour)
     ///   * `Bit 2` - Enable/disable binary mode. (0 = BCD, 1 = Binary)
+    ///   * `Bit 4` - Enable/disable the update interrupt.
+    ///   * `Bit 5` - Enable/disable the alarm interrupt.
+    ///   * `Bit 6` - Enable/disable the periodic interrupt.
+    ///   * `Bit 7` - Stop/resume updates, while the time is being set. (0 = Updating, 1 = Stopped)
     StatusB = 0x0B,
+    /// The status C register, which is located at `0x0C`.
+    ///
+    /// # Notes
+    ///
+    /// * This register holds the interrupts that fired, and reading it clears them:
+    ///   * `Bit 4` - The update interrupt fired.
+    ///   * `Bit 5` - The alarm interrupt fired.
+    ///   * `Bit 6` - The periodic interrupt fired.
     StatusC = 0x0C,
 }
 
@@ -68,14 +90,18 @@ impl From<u8> for Register {
     /// * `Register` - The converted value. If the value is not a valid register, then [`Register::Seconds`] is returned.
     fn from(value: u8) -> Self {
         match value {
+            0x01 => Self::SecondsAlarm,
             0x02 => Self::Minutes,
+            0x03 => Self::MinutesAlarm,
             0x04 => Self::Hours,
+            0x05 => Self::HoursAlarm,
             0x07 => Self::Day,
             0x08 => Self::Month,
             0x09 => Self::Year,
             0x32 => Self::Century,
             0x0A => Self::StatusA,
             0x0B => Self::StatusB,
+            0x0C => Self::StatusC,
             _ => Self::Seconds,
         }
     }
@@ -0,0 +1,183 @@
+use core::fmt::{self, Display, Formatter};
+
+/// The number of seconds in a day.
+const SECONDS_PER_DAY: i64 = 86_400;
+/// The number of days in a 400-year era of the Gregorian calendar.
+const DAYS_PER_ERA: i64 = 146_097;
+/// The number of days from 0000-03-01 to the Unix epoch, 1970-01-01.
+const EPOCH_OFFSET: i64 = 719_468;
+
+/// A date and time of the proleptic Gregorian calendar, in UTC.
+///
+/// # Fields
+///
+/// * `year` - The year, such as 2024.
+/// * `month` - The month, from 1 to 12.
+/// * `day` - The day of the month, from 1 to 31.
+/// * `hour` - The hour, from 0 to 23.
+/// * `minute` - The minute, from 0 to 59.
+/// * `second` - The second, from 0 to 59.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// The Unix epoch, 1970-01-01 00:00:00.
+    pub const EPOCH: Self = Self {
+        year: 1970,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+    };
+
+    /// Converts Unix seconds to a date and time.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The seconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// * `Self` - The date and time.
+    ///
+    /// # Notes
+    ///
+    /// * Years are clamped to `0..=65535`.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn from_unix(seconds: i64) -> Self {
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let time = seconds.rem_euclid(SECONDS_PER_DAY);
+
+        // Count years from March, so the leap day is the last day of the year.
+        let days = days + EPOCH_OFFSET;
+        let era = days.div_euclid(DAYS_PER_ERA);
+        let day_of_era = days - era * DAYS_PER_ERA;
+        let year_of_era =
+            (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year: year.clamp(0, i64::from(u16::MAX)) as u16,
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3_600) as u8,
+            minute: (time % 3_600 / 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    /// Converts the date and time to Unix seconds.
+    ///
+    /// # Returns
+    ///
+    /// * `i64` - The seconds since the Unix epoch, negative before it.
+    #[must_use]
+    pub fn to_unix(&self) -> i64 {
+        // Count years from March, so the leap day is the last day of the year.
+        let (year, month) = if self.month <= 2 {
+            (i64::from(self.year) - 1, i64::from(self.month) + 9)
+        } else {
+            (i64::from(self.year), i64::from(self.month) - 3)
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let day_of_year = (153 * month + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * DAYS_PER_ERA + day_of_era - EPOCH_OFFSET;
+
+        days * SECONDS_PER_DAY
+            + i64::from(self.hour) * 3_600
+            + i64::from(self.minute) * 60
+            + i64::from(self.second)
+    }
+
+    /// Checks if the fields form a real date and time.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the date and time is valid.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        (1..=12).contains(&self.month)
+            && (1..=days_in_month(self.year, self.month)).contains(&self.day)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// Gets the day of the week.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The day of the week, from 0 (Sunday) to 6 (Saturday).
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn weekday(&self) -> u8 {
+        // The Unix epoch was a Thursday.
+        (self.to_unix().div_euclid(SECONDS_PER_DAY) + 4).rem_euclid(7) as u8
+    }
+}
+
+impl Display for DateTime {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02}",
+            year = self.year,
+            month = self.month,
+            day = self.day,
+            hour = self.hour,
+            minute = self.minute,
+            second = self.second
+        )
+    }
+}
+
+/// Checks if a year is a leap year.
+///
+/// # Arguments
+///
+/// * `year` - The year.
+///
+/// # Returns
+///
+/// * `bool` - Whether or not the year has a 29th of February.
+#[must_use]
+pub const fn is_leap_year(year: u16) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+/// Gets the number of days in a month.
+///
+/// # Arguments
+///
+/// * `year` - The year.
+/// * `month` - The month, from 1 to 12.
+///
+/// # Returns
+///
+/// * `u8` - The number of days, or 0 if the month is invalid.
+#[must_use]
+pub const fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
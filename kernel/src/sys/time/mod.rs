@@ -1,9 +1,11 @@
 pub mod clock;
+pub mod clocksource;
 pub mod cmos;
+pub mod date;
 pub mod rtc;
 
 use core::hint::spin_loop;
-use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use x86_64::instructions::{interrupts, port::Port};
 
 use crate::errors::Error;
@@ -25,9 +27,6 @@ pub(crate) static PIT_TICK: AtomicUsize = AtomicUsize::new(0);
 /// The last RTC update, in PIT ticks.
 pub(crate) static LAST_RTC_UPDATE: AtomicUsize = AtomicUsize::new(0);
 
-/// The number of clock cycles per nanosecond.
-static CLOCK_CYCLES_PER_NS: AtomicU64 = AtomicU64::new(0);
-
 /// Gets the last PIT tick.
 ///
 /// # Returns
@@ -85,35 +84,24 @@ pub fn init() -> Result<(), Error> {
     // Enable the RTC Update interrupt.
     RTC::default().set_interrupt(&RTCInterrupt::Update, true);
 
-    // Calibrate the clock.
-    let calibration = 250_000;
-
-    let start = read_tsc();
-    sleep(calibration as f64 / 1e6); // Sleeps for 0.25 ms.
-    let end = read_tsc();
+    // Calibrate the time-stamp counter, until a better clock source is selected.
+    clocksource::calibrate_tsc();
 
-    CLOCK_CYCLES_PER_NS.store((end - start) / calibration, Ordering::Relaxed);
+    // Read the wall clock from the RTC.
+    clock::init();
 
     Ok(())
 }
 
-/// Reads the time-stamp counter.
-///
-/// # Returns
-///
-/// * `u64` - The time-stamp counter.
-fn read_tsc() -> u64 {
-    unsafe {
-        core::arch::x86_64::_mm_lfence(); // Prevents instruction reordering.
-        core::arch::x86_64::_rdtsc() // Reads the time-stamp counter.
-    }
-}
-
 /// Sleeps for the given amount of seconds.
 ///
 /// # Arguments
 ///
 /// * `seconds` - The amount of seconds to sleep.
+///
+/// # Notes
+///
+/// * This blocks the executor, so tasks should await [`crate::sys::task::timer::sleep`] instead.
 pub fn sleep(seconds: f64) {
     let start = clock::uptime();
 
@@ -127,11 +115,16 @@ pub fn sleep(seconds: f64) {
 /// # Arguments
 ///
 /// * `ns` - The amount of nanoseconds to wait.
+///
+/// # Notes
+///
+/// * This spins on the time-stamp counter, so it's precise even before [`clocksource::init`].
+#[allow(clippy::cast_possible_truncation)]
 pub fn wait(ns: u64) {
-    let start = read_tsc();
-    let delta = ns * CLOCK_CYCLES_PER_NS.load(Ordering::Relaxed);
+    let start = clocksource::read_tsc();
+    let delta = (u128::from(ns) * u128::from(clocksource::tsc_frequency()) / 1_000_000_000) as u64;
 
-    while read_tsc() - start < delta {
+    while clocksource::read_tsc() - start < delta {
         spin_loop();
     }
 }
@@ -1,6 +1,17 @@
-use crate::sys::time::cmos::{Register, CMOS};
+use alloc::format;
 use x86_64::instructions::interrupts::without_interrupts;
 
+use crate::errors::Error;
+use crate::sys::acpi;
+use crate::sys::time::cmos::{Register, CMOS};
+use crate::sys::time::date::DateTime;
+
+/// The flag of the status B register that stops updates while the time is being set.
+const SET: u8 = 1 << 7;
+
+/// The alarm register value that matches any time, as set by its two top bits.
+const ALARM_ANY: u8 = 0xC0;
+
 /// The real time clock.
 ///
 /// # Fields
@@ -33,13 +44,22 @@ impl RTC {
     /// # Notes
     ///
     /// * This function will wait for the RTC to finish updating.
+    /// * The RTC is read until two reads agree, so an update can't tear the values.
     #[must_use]
     pub fn new() -> Self {
         let mut rtc = Self::default();
 
         rtc.wait_for_rtc_update();
         rtc.update();
-        rtc
+        loop {
+            let previous = rtc.fields();
+
+            rtc.wait_for_rtc_update();
+            rtc.update();
+            if rtc.fields() == previous {
+                return rtc;
+            }
+        }
     }
 
     /// Creates a new `RTC` instance without checking if the RTC is updating.
@@ -59,12 +79,16 @@ impl RTC {
     pub fn update(&mut self) {
         let mut seconds = self.cmos.read(&Register::Seconds);
         let mut minutes = self.cmos.read(&Register::Minutes);
-        let mut hours = self.cmos.read(&Register::Hours);
+        let hours = self.cmos.read(&Register::Hours);
         let mut day = self.cmos.read(&Register::Day);
         let mut month = self.cmos.read(&Register::Month);
         let mut year = self.cmos.read(&Register::Year);
         let mut century = self.cmos.read(&Register::Century);
 
+        // In 12-hour mode, the top bit of the hours is set after noon.
+        let pm = hours & 0x80 != 0;
+        let mut hours = hours & 0x7F;
+
         // If the RTC is in BCD mode, then convert the values to binary.
         if !self.binary_mode() {
             seconds = Self::bcd_to_binary(seconds);
@@ -76,12 +100,8 @@ impl RTC {
             century = Self::bcd_to_binary(century);
         }
 
-        // If the RTC is in 12-hour mode, then convert the hours to 24-hour mode.
         if !self.military_time_mode() {
-            // If the PM bit is set, then add 12 to the hours.
-            if hours & 0x80 != 0 {
-                hours = (hours & 0x7F) + 12;
-            }
+            hours = Self::hours_from_12h(hours, pm);
         }
 
         self.seconds = seconds;
@@ -103,7 +123,7 @@ impl RTC {
         let update_bit = 1 << 7;
 
         // If the RTC update in progress bit is 0, then the RTC is not updating, and vice versa.
-        status & update_bit == 0
+        status & update_bit != 0
     }
 
     /// Waits for the RTC to finish updating.
@@ -125,8 +145,8 @@ impl RTC {
     fn military_time_mode(&mut self) -> bool {
         let value = self.cmos.read(&Register::StatusB);
 
-        // If the first bit is 0, then the RTC is in 12-hour mode, and vice versa.
-        value & 1 == 0
+        // If bit 1 is 0, then the RTC is in 12-hour mode, and vice versa.
+        value & 0x02 != 0
     }
 
     /// Gets whether or not the RTC is in binary mode.
@@ -137,8 +157,8 @@ impl RTC {
     fn binary_mode(&mut self) -> bool {
         let value = self.cmos.read(&Register::StatusB);
 
-        // If the second bit is 0, then the RTC is in BCD mode, and vice versa.
-        value & 2 == 0
+        // If bit 2 is 0, then the RTC is in BCD mode, and vice versa.
+        value & 0x04 != 0
     }
 
     /// Disables the given interrupt.
@@ -149,11 +169,6 @@ impl RTC {
     /// * `enabled` - Whether or not the interrupt should be enabled.
     pub fn set_interrupt(&mut self, interrupt: &RTCInterrupt, enabled: bool) {
         without_interrupts(|| {
-            // Get the previous register.
-            let prev_addr = self.cmos.prev_addr();
-            // Disable NMI to prevent the RTC from updating.
-            self.cmos.set_nmi(&prev_addr, false);
-
             // Get the previous data.
             let prev_data = self.cmos.read(&Register::StatusB);
             let value = if enabled {
@@ -163,9 +178,6 @@ impl RTC {
             };
             self.cmos.write(&Register::StatusB, value);
 
-            // Re-enable NMI to allow the RTC to update.
-            self.cmos.set_nmi(&prev_addr, true);
-
             self.notify_interrupt_end();
         });
     }
@@ -181,28 +193,213 @@ impl RTC {
     /// * This won't enable the periodic interrupt if it's disabled.
     pub fn set_periodic_rate(&mut self, rate: u8) {
         without_interrupts(|| {
-            // Get the previous register.
-            let prev_addr = self.cmos.prev_addr();
-            // Disable NMI to prevent the RTC from updating.
-            self.cmos.set_nmi(&prev_addr, false);
-
             // Set the rate of the periodic interrupt to the provided rate.
             let prev_data = self.cmos.read(&Register::StatusA);
             let value = (prev_data & 0xF0) | rate;
             self.cmos.write(&Register::StatusA, value);
 
-            // Re-enable NMI to allow the RTC to update.
-            self.cmos.set_nmi(&prev_addr, true);
-
             self.notify_interrupt_end();
         });
     }
 
+    /// Sets the date and time of the RTC.
+    ///
+    /// # Arguments
+    ///
+    /// * `datetime` - The date and time, in UTC.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If the date and time is invalid.
+    /// * If the year is outside of the 21st century, and the RTC has no century register.
+    ///
+    /// # Notes
+    ///
+    /// * The century register is only written if the FADT says it exists.
+    /// * This doesn't change the wall clock, see [`crate::sys::time::clock::set`].
+    pub fn set(&mut self, datetime: &DateTime) -> Result<(), Error> {
+        let has_century = Self::has_century();
+
+        if !datetime.is_valid() || datetime.year > 9999 {
+            return Err(Error::Internal(format!(
+                "Invalid date and time: {datetime}"
+            )));
+        }
+        if !has_century && !(2000..=2099).contains(&datetime.year) {
+            return Err(Error::Internal(format!(
+                "RTC can't hold the year {year} without a century register!",
+                year = datetime.year
+            )));
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let (century, year) = ((datetime.year / 100) as u8, (datetime.year % 100) as u8);
+
+        let seconds = self.encode(datetime.second);
+        let minutes = self.encode(datetime.minute);
+        let hours = self.encode_hours(datetime.hour);
+        let day = self.encode(datetime.day);
+        let month = self.encode(datetime.month);
+        let year = self.encode(year);
+        let century = self.encode(century);
+
+        without_interrupts(|| {
+            // Stop updates, so the RTC doesn't tick in the middle of the write.
+            let status = self.cmos.read(&Register::StatusB);
+            self.cmos.write(&Register::StatusB, status | SET);
+
+            self.cmos.write(&Register::Seconds, seconds);
+            self.cmos.write(&Register::Minutes, minutes);
+            self.cmos.write(&Register::Hours, hours);
+            self.cmos.write(&Register::Day, day);
+            self.cmos.write(&Register::Month, month);
+            self.cmos.write(&Register::Year, year);
+            if has_century {
+                self.cmos.write(&Register::Century, century);
+            }
+
+            // Resume updates.
+            self.cmos.write(&Register::StatusB, status & !SET);
+        });
+
+        self.update();
+        Ok(())
+    }
+
+    /// Programs the alarm, and enables the alarm interrupt.
+    ///
+    /// # Arguments
+    ///
+    /// * `alarm` - The time of the alarm.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), Error>` - The result of the operation.
+    ///
+    /// # Errors
+    ///
+    /// * If a field of the alarm is out of range.
+    ///
+    /// # Notes
+    ///
+    /// * There is only one alarm, so this replaces the previous one.
+    pub fn set_alarm(&mut self, alarm: &Alarm) -> Result<(), Error> {
+        if alarm.hour.is_some_and(|hour| hour > 23)
+            || alarm.minute.is_some_and(|minute| minute > 59)
+            || alarm.second.is_some_and(|second| second > 59)
+        {
+            return Err(Error::Internal(format!("Invalid RTC alarm: {alarm:?}")));
+        }
+
+        let hour = alarm.hour.map_or(ALARM_ANY, |hour| self.encode_hours(hour));
+        let minute = alarm.minute.map_or(ALARM_ANY, |minute| self.encode(minute));
+        let second = alarm.second.map_or(ALARM_ANY, |second| self.encode(second));
+
+        without_interrupts(|| {
+            self.cmos.write(&Register::HoursAlarm, hour);
+            self.cmos.write(&Register::MinutesAlarm, minute);
+            self.cmos.write(&Register::SecondsAlarm, second);
+        });
+        self.set_interrupt(&RTCInterrupt::Alarm, true);
+
+        Ok(())
+    }
+
     /// Notifies the RTC that the interrupt has ended.
     pub fn notify_interrupt_end(&mut self) {
         self.cmos.read(&Register::StatusC);
     }
 
+    /// Gets the interrupts that fired, ending the interrupt.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The status C register, see [`RTCInterrupt::is_pending`].
+    pub fn interrupt_status(&mut self) -> u8 {
+        self.cmos.read(&Register::StatusC)
+    }
+
+    /// Converts a binary value to the format of the RTC registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The binary value.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The value, in BCD if the RTC is in BCD mode.
+    fn encode(&mut self, value: u8) -> u8 {
+        if self.binary_mode() {
+            value
+        } else {
+            Self::binary_to_bcd(value)
+        }
+    }
+
+    /// Converts hours to the format of the RTC hours registers.
+    ///
+    /// # Arguments
+    ///
+    /// * `hours` - The hours, from 0 to 23.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The hours, in the 12-hour format with the PM bit if the RTC is in 12-hour mode.
+    fn encode_hours(&mut self, hours: u8) -> u8 {
+        if self.military_time_mode() {
+            return self.encode(hours);
+        }
+
+        let (value, pm) = Self::hours_to_12h(hours);
+        self.encode(value) | if pm { 0x80 } else { 0 }
+    }
+
+    /// Converts hours from the 12-hour format to the 24-hour format.
+    ///
+    /// # Arguments
+    ///
+    /// * `hours` - The hours, from 1 to 12.
+    /// * `pm` - Whether or not the hours are after noon.
+    ///
+    /// # Returns
+    ///
+    /// * `u8` - The hours, from 0 to 23, where 12 AM is 0 and 12 PM is 12.
+    #[must_use]
+    pub const fn hours_from_12h(hours: u8, pm: bool) -> u8 {
+        if pm {
+            hours % 12 + 12
+        } else {
+            hours % 12
+        }
+    }
+
+    /// Converts hours from the 24-hour format to the 12-hour format.
+    ///
+    /// # Arguments
+    ///
+    /// * `hours` - The hours, from 0 to 23.
+    ///
+    /// # Returns
+    ///
+    /// * `(u8, bool)` - The hours, from 1 to 12, and whether or not they are after noon.
+    ///
+    /// # Notes
+    ///
+    /// * Midnight and noon are both 12.
+    #[must_use]
+    pub const fn hours_to_12h(hours: u8) -> (u8, bool) {
+        let value = match hours % 12 {
+            0 => 12,
+            hours => hours,
+        };
+
+        (value, hours >= 12)
+    }
+
     /// Converts the given BCD value to a binary value.
     ///
     /// # Arguments
@@ -222,25 +419,82 @@ impl RTC {
         ((value & 0xF0) >> 1) + ((value & 0xF0) >> 3) + (value & 0xF)
     }
 
-    /// Converts the RTC time to milliseconds.
+    /// Converts the given binary value to a BCD value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The binary value to convert, below 100.
     ///
     /// # Returns
     ///
-    /// * `u64` - The RTC time in milliseconds.
+    /// * `u8` - The BCD value.
     #[must_use]
-    pub const fn as_millis(&self) -> u64 {
-        let mut millis = 0;
-
-        // Convert the RTC time to milliseconds.
-        millis += self.seconds as u64 * 1_000;
-        millis += self.minutes as u64 * 60 * 1_000;
-        millis += self.hours as u64 * 60 * 60 * 1_000;
-        millis += self.day as u64 * 24 * 60 * 60 * 1_000;
-        millis += self.month as u64 * 30 * 24 * 60 * 60 * 1_000;
-        millis += self.year as u64 * 365 * 24 * 60 * 60 * 1_000;
-        millis += self.century as u64 * 100 * 365 * 24 * 60 * 60 * 1_000;
-
-        millis
+    pub const fn binary_to_bcd(value: u8) -> u8 {
+        ((value / 10) << 4) | (value % 10)
+    }
+
+    /// Gets the date and time the RTC was read at.
+    ///
+    /// # Returns
+    ///
+    /// * `DateTime` - The date and time.
+    ///
+    /// # Notes
+    ///
+    /// * Not every RTC has a century register, so the 21st century is assumed without one.
+    #[must_use]
+    pub fn datetime(&self) -> DateTime {
+        // Without the register, its CMOS offset holds unrelated data.
+        let century = if Self::has_century() && self.century != 0 {
+            self.century
+        } else {
+            20
+        };
+
+        DateTime {
+            year: u16::from(century) * 100 + u16::from(self.year),
+            month: self.month,
+            day: self.day,
+            hour: self.hours,
+            minute: self.minutes,
+            second: self.seconds,
+        }
+    }
+
+    /// Checks if the RTC has a century register.
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the FADT reports the century register.
+    fn has_century() -> bool {
+        acpi::fadt().is_some_and(|fadt| fadt.century == Register::Century as u8)
+    }
+
+    /// Converts the RTC time to milliseconds since the Unix epoch.
+    ///
+    /// # Returns
+    ///
+    /// * `u64` - The RTC time in milliseconds, or 0 if it's before the epoch.
+    #[must_use]
+    pub fn as_millis(&self) -> u64 {
+        u64::try_from(self.datetime().to_unix()).map_or(0, |seconds| seconds * 1_000)
+    }
+
+    /// Gets the values read from the RTC.
+    ///
+    /// # Returns
+    ///
+    /// * `[u8; 7]` - The seconds, minutes, hours, day, month, year and century.
+    fn fields(&self) -> [u8; 7] {
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.day,
+            self.month,
+            self.year,
+            self.century,
+        ]
     }
 }
 
@@ -251,12 +505,42 @@ impl RTC {
 /// * [`RTCInterrupt::Periodic`]
 /// * [`RTCInterrupt::Alarm`]
 /// * [`RTCInterrupt::Update`]
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RTCInterrupt {
-    /// The periodic interrupt, which is triggered every 244 microseconds.
+    /// The periodic interrupt, which is triggered at the rate set by [`RTC::set_periodic_rate`].
     Periodic = 1 << 6,
     /// The alarm interrupt, which is triggered when the RTC alarm goes off.
     Alarm = 1 << 5,
     /// The update interrupt, which is triggered when the RTC updates.
     Update = 1 << 4,
 }
+
+impl RTCInterrupt {
+    /// Checks if the interrupt fired.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The status C register, as read by [`RTC::interrupt_status`].
+    ///
+    /// # Returns
+    ///
+    /// * `bool` - Whether or not the interrupt fired.
+    #[must_use]
+    pub const fn is_pending(self, status: u8) -> bool {
+        status & self as u8 != 0
+    }
+}
+
+/// The time of the RTC alarm.
+///
+/// # Fields
+///
+/// * `hour` - The hour, from 0 to 23, or `None` to match every hour.
+/// * `minute` - The minute, from 0 to 59, or `None` to match every minute.
+/// * `second` - The second, from 0 to 59, or `None` to match every second.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Alarm {
+    pub hour: Option<u8>,
+    pub minute: Option<u8>,
+    pub second: Option<u8>,
+}
@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+use core::panic::PanicInfo;
+
+use kernel::sys::time::date::{days_in_month, is_leap_year, DateTime};
+use kernel::sys::time::rtc::RTC;
+
+#[allow(clippy::empty_loop)]
+#[no_mangle]
+pub extern "C" fn _start() -> ! {
+    test_main();
+
+    loop {}
+}
+
+/// This function is called on panic.
+///
+/// # Arguments
+///
+/// * `info` - The panic information.
+///
+/// # Returns
+///
+/// * `!` - Never.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kernel::test_panic_handler(info)
+}
+
+/// Tests converting the Unix epoch.
+///
+/// # Panics
+///
+/// * If the epoch isn't at 0 seconds.
+#[test_case]
+fn test_epoch() {
+    assert_eq!(DateTime::EPOCH.to_unix(), 0);
+    assert_eq!(DateTime::from_unix(0), DateTime::EPOCH);
+    assert_eq!(DateTime::EPOCH.weekday(), 4);
+}
+
+/// Tests the leap years of the Gregorian calendar.
+///
+/// # Panics
+///
+/// * If a year is misclassified.
+#[test_case]
+fn test_leap_years() {
+    assert!(is_leap_year(2000));
+    assert!(is_leap_year(2024));
+    assert!(!is_leap_year(1900));
+    assert!(!is_leap_year(2023));
+    assert_eq!(days_in_month(2000, 2), 29);
+    assert_eq!(days_in_month(2100, 2), 28);
+}
+
+/// Tests converting dates around leap days.
+///
+/// # Panics
+///
+/// * If a date doesn't convert to the expected Unix time, or back.
+#[test_case]
+fn test_conversion() {
+    let leap_day = DateTime {
+        year: 2000,
+        month: 2,
+        day: 29,
+        hour: 12,
+        minute: 34,
+        second: 56,
+    };
+    assert_eq!(leap_day.to_unix(), 951_827_696);
+    assert_eq!(DateTime::from_unix(951_827_696), leap_day);
+
+    // The day before the epoch.
+    assert_eq!(
+        DateTime::from_unix(-1),
+        DateTime {
+            year: 1969,
+            month: 12,
+            day: 31,
+            hour: 23,
+            minute: 59,
+            second: 59,
+        }
+    );
+
+    for seconds in (0..4_000_000_000).step_by(86_399 * 7) {
+        assert_eq!(DateTime::from_unix(seconds).to_unix(), seconds);
+    }
+}
+
+/// Tests converting the BCD values of the RTC registers.
+///
+/// # Panics
+///
+/// * If a value doesn't convert to the expected value, or back.
+#[test_case]
+fn test_bcd() {
+    assert_eq!(RTC::bcd_to_binary(0x00), 0);
+    assert_eq!(RTC::bcd_to_binary(0x09), 9);
+    assert_eq!(RTC::bcd_to_binary(0x12), 12);
+    assert_eq!(RTC::bcd_to_binary(0x59), 59);
+    assert_eq!(RTC::binary_to_bcd(10), 0x10);
+    assert_eq!(RTC::binary_to_bcd(99), 0x99);
+
+    for value in 0..100 {
+        assert_eq!(RTC::bcd_to_binary(RTC::binary_to_bcd(value)), value);
+    }
+}
+
+/// Tests converting the hours of the RTC in 12-hour mode.
+///
+/// # Panics
+///
+/// * If 12 AM isn't midnight, if 12 PM isn't noon, or if an hour doesn't convert back.
+#[test_case]
+fn test_12_hour_mode() {
+    assert_eq!(RTC::hours_from_12h(12, false), 0);
+    assert_eq!(RTC::hours_from_12h(1, false), 1);
+    assert_eq!(RTC::hours_from_12h(12, true), 12);
+    assert_eq!(RTC::hours_from_12h(1, true), 13);
+    assert_eq!(RTC::hours_from_12h(11, true), 23);
+    assert_eq!(RTC::hours_to_12h(0), (12, false));
+    assert_eq!(RTC::hours_to_12h(12), (12, true));
+    assert_eq!(RTC::hours_to_12h(23), (11, true));
+
+    for hours in 0..24 {
+        let (value, pm) = RTC::hours_to_12h(hours);
+        assert_eq!(RTC::hours_from_12h(value, pm), hours);
+    }
+}
@@ -0,0 +1,167 @@
+#![no_std]
+#![no_main]
+#![feature(custom_test_frameworks)]
+#![test_runner(kernel::test_runner)]
+#![reexport_test_harness_main = "test_main"]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::panic::PanicInfo;
+
+use bootloader::{entry_point, BootInfo};
+
+use kernel::fs::tmpfs::TmpFs;
+use kernel::fs::{self, InodeKind, OpenFlags, SeekFrom};
+
+entry_point!(main);
+
+/// Test the tmpfs file system.
+///
+/// # Arguments
+///
+/// * `boot_info` - The boot information.
+#[allow(clippy::expect_used, clippy::empty_loop)]
+fn main(boot_info: &'static BootInfo) -> ! {
+    kernel::init::start_kernel(boot_info).expect("Failed to start kernel!");
+
+    // Mount a fresh tmpfs, whatever the file system at `/tmp` is.
+    fs::unmount("/tmp").expect("Failed to unmount /tmp!");
+    fs::mount("/tmp", Arc::new(TmpFs::new())).expect("Failed to mount the tmpfs!");
+
+    test_main();
+
+    loop {}
+}
+
+/// This function is called on panic.
+///
+/// # Arguments
+///
+/// * `info` - The panic information.
+///
+/// # Returns
+///
+/// * `!` - Never.
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    kernel::test_panic_handler(info)
+}
+
+/// Tests writing and reading back a file.
+///
+/// # Panics
+///
+/// * If the file can't be written or read.
+#[test_case]
+fn write_and_read() {
+    let mut file = fs::open("/tmp/hello.txt", OpenFlags::WRITE | OpenFlags::CREATE)
+        .expect("Failed to create the file!");
+    file.write(b"Hello, ").expect("Failed to write!");
+    file.write(b"world!").expect("Failed to write!");
+
+    assert_eq!(
+        fs::read("/tmp/hello.txt").expect("Failed to read!"),
+        b"Hello, world!"
+    );
+    assert_eq!(
+        fs::metadata("/tmp/hello.txt").expect("Missing file!").size,
+        13
+    );
+}
+
+/// Tests seeking, appending and truncating.
+///
+/// # Panics
+///
+/// * If the file contents are not as expected.
+#[test_case]
+fn seek_append_truncate() {
+    let mut file = fs::open(
+        "/tmp/seek.txt",
+        OpenFlags::READ | OpenFlags::WRITE | OpenFlags::CREATE,
+    )
+    .expect("Failed to create the file!");
+    file.write(b"0123456789").expect("Failed to write!");
+
+    // Writing past the end fills the gap with zeros.
+    file.seek(SeekFrom::End(2)).expect("Failed to seek!");
+    file.write(b"!").expect("Failed to write!");
+    assert_eq!(
+        fs::read("/tmp/seek.txt").expect("Failed to read!"),
+        b"0123456789\0\0!"
+    );
+
+    file.seek(SeekFrom::Start(4)).expect("Failed to seek!");
+    let mut buffer = [0; 3];
+    assert_eq!(file.read(&mut buffer).expect("Failed to read!"), 3);
+    assert_eq!(&buffer, b"456");
+
+    let mut file = fs::open("/tmp/seek.txt", OpenFlags::WRITE | OpenFlags::TRUNCATE)
+        .expect("Failed to truncate the file!");
+    assert_eq!(
+        fs::metadata("/tmp/seek.txt").expect("Missing file!").size,
+        0
+    );
+
+    let mut file_append = fs::open("/tmp/seek.txt", OpenFlags::WRITE | OpenFlags::APPEND)
+        .expect("Failed to open the file!");
+    file.write(b"abc").expect("Failed to write!");
+    file_append.write(b"def").expect("Failed to write!");
+    assert_eq!(
+        fs::read("/tmp/seek.txt").expect("Failed to read!"),
+        b"abcdef"
+    );
+}
+
+/// Tests directories, `.` and `..` resolution and unlinking.
+///
+/// # Panics
+///
+/// * If the directory tree is not as expected.
+#[test_case]
+fn directories() {
+    fs::create("/tmp/dir", InodeKind::Directory).expect("Failed to create the directory!");
+    fs::create("/tmp/dir/sub", InodeKind::Directory).expect("Failed to create the directory!");
+    fs::create("/tmp/dir/./sub/../file", InodeKind::File).expect("Failed to create the file!");
+
+    let names: Vec<_> = fs::read_dir("/tmp/dir/sub/..")
+        .expect("Failed to list the directory!")
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect();
+    assert_eq!(names, ["file", "sub"]);
+
+    assert!(fs::remove("/tmp/dir").is_err());
+    fs::remove("/tmp/dir/file").expect("Failed to remove the file!");
+    fs::remove("/tmp/dir/sub").expect("Failed to remove the directory!");
+    fs::remove("/tmp/dir").expect("Failed to remove the directory!");
+    assert!(fs::metadata("/tmp/dir").is_err());
+}
+
+/// Tests renaming files and directories.
+///
+/// # Panics
+///
+/// * If the entries are not moved as expected.
+#[test_case]
+fn rename() {
+    fs::create("/tmp/a", InodeKind::Directory).expect("Failed to create the directory!");
+    fs::create("/tmp/b", InodeKind::Directory).expect("Failed to create the directory!");
+    let mut file = fs::open("/tmp/a/old", OpenFlags::WRITE | OpenFlags::CREATE)
+        .expect("Failed to create the file!");
+    file.write(b"moved").expect("Failed to write!");
+
+    fs::rename("/tmp/a/old", "/tmp/b/new").expect("Failed to rename the file!");
+    assert!(fs::metadata("/tmp/a/old").is_err());
+    assert_eq!(fs::read("/tmp/b/new").expect("Failed to read!"), b"moved");
+
+    // A directory can't be moved into itself.
+    assert!(fs::rename("/tmp/b", "/tmp/b/inner").is_err());
+    fs::rename("/tmp/b", "/tmp/a/b").expect("Failed to rename the directory!");
+    assert_eq!(fs::read("/tmp/a/b/new").expect("Failed to read!"), b"moved");
+
+    // Entries can't leave their file system.
+    assert!(fs::rename("/tmp/a/b/new", "/new").is_err());
+}